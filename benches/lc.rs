@@ -1,7 +1,93 @@
-use bellperson::{bls::Bls12, Index, LinearCombination, Variable};
+use bellperson::gadgets::blake2s::blake2s;
+use bellperson::gadgets::boolean::{AllocatedBit, Boolean};
+use bellperson::gadgets::sha256::sha256;
+use bellperson::{
+    bls::Bls12, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable,
+};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use ff::{Field, ScalarEngine};
 
+/// Builds the linear combinations of every constraint, as the prover does,
+/// but keeps nothing but their number of terms.
+#[derive(Default)]
+struct LcCS {
+    inputs: usize,
+    aux: usize,
+    terms: usize,
+}
+
+impl ConstraintSystem<Bls12> for LcCS {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<<Bls12 as ScalarEngine>::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        black_box(f()?);
+        self.aux += 1;
+
+        Ok(Variable::new_unchecked(Index::Aux(self.aux - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<<Bls12 as ScalarEngine>::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        black_box(f()?);
+        self.inputs += 1;
+
+        Ok(Variable::new_unchecked(Index::Input(self.inputs)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Bls12>) -> LinearCombination<Bls12>,
+        LB: FnOnce(LinearCombination<Bls12>) -> LinearCombination<Bls12>,
+        LC: FnOnce(LinearCombination<Bls12>) -> LinearCombination<Bls12>,
+    {
+        for lc in &[
+            a(LinearCombination::zero()),
+            b(LinearCombination::zero()),
+            c(LinearCombination::zero()),
+        ] {
+            self.terms += lc.iter().count();
+        }
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+fn alloc_input(cs: &mut LcCS, len: usize) -> Vec<Boolean> {
+    (0..len)
+        .map(|i| {
+            Boolean::from(
+                AllocatedBit::alloc(
+                    cs.namespace(|| format!("input bit {}", i)),
+                    Some(i % 3 == 0),
+                )
+                .unwrap(),
+            )
+        })
+        .collect()
+}
+
 fn lc_benchmark(c: &mut Criterion) {
     c.bench_function("LinearCombination::add((Fr, Variable))", |b| {
         b.iter(|| {
@@ -13,6 +99,19 @@ fn lc_benchmark(c: &mut Criterion) {
             black_box(lc);
         });
     })
+    .bench_function(
+        "LinearCombination::add((Fr, Variable)) in reverse order",
+        |b| {
+            b.iter(|| {
+                let mut lc = LinearCombination::<Bls12>::zero();
+                for i in (0..100).rev() {
+                    let coeff = <Bls12 as ScalarEngine>::Fr::one();
+                    lc = lc + (coeff, Variable::new_unchecked(Index::Aux(i)));
+                }
+                black_box(lc);
+            });
+        },
+    )
     .bench_function("LinearCombination::add(LinearCombination)", |b| {
         let mut lc1 = LinearCombination::<Bls12>::zero();
         let mut lc2 = LinearCombination::<Bls12>::zero();
@@ -34,5 +133,30 @@ fn lc_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, lc_benchmark);
+fn synthesis_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("synthesis");
+    group.sample_size(20);
+
+    group
+        .bench_function("sha256 (512 bits)", |b| {
+            b.iter(|| {
+                let mut cs = LcCS::default();
+                let input = alloc_input(&mut cs, 512);
+                black_box(sha256(cs.namespace(|| "sha256"), &input).unwrap());
+                black_box(cs.terms);
+            });
+        })
+        .bench_function("blake2s (512 bits)", |b| {
+            b.iter(|| {
+                let mut cs = LcCS::default();
+                let input = alloc_input(&mut cs, 512);
+                black_box(blake2s(cs.namespace(|| "blake2s"), &input, b"12345678").unwrap());
+                black_box(cs.terms);
+            });
+        });
+
+    group.finish();
+}
+
+criterion_group!(benches, lc_benchmark, synthesis_benchmark);
 criterion_main!(benches);
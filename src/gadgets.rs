@@ -2,13 +2,23 @@
 
 pub mod test;
 
+pub mod bits;
 pub mod blake2s;
 pub mod boolean;
+pub mod ecc;
+#[cfg(feature = "groth16")]
+pub mod groth16;
+pub mod hash;
 pub mod lookup;
+pub mod memory;
 pub mod multieq;
 pub mod multipack;
+pub mod mux;
+pub mod nonnative;
 pub mod num;
+pub mod pedersen_hash;
 pub mod sha256;
+pub mod signature;
 pub mod uint32;
 
 use crate::SynthesisError;
@@ -0,0 +1,586 @@
+//! Strings of bits and bytes which keep track of their bit order, with
+//! conversions from and to allocated numbers and packing into public
+//! inputs.
+
+use std::ops::Range;
+
+use ff::{PrimeField, ScalarEngine};
+
+use super::boolean::{AllocatedBit, Boolean};
+use super::multipack;
+use super::num::AllocatedNum;
+use crate::{ConstraintSystem, SynthesisError};
+
+/// The order of bits, by significance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    /// The least significant bit comes first.
+    Little,
+    /// The most significant bit comes first.
+    Big,
+}
+
+/// A string of bits, ordered by significance according to its endianness
+/// when it is interpreted as a number.
+#[derive(Clone)]
+pub struct Bits {
+    bits: Vec<Boolean>,
+    endianness: Endianness,
+}
+
+impl Bits {
+    pub fn new(bits: Vec<Boolean>, endianness: Endianness) -> Self {
+        Bits { bits, endianness }
+    }
+
+    pub fn constant(value: &[bool], endianness: Endianness) -> Self {
+        Bits {
+            bits: value.iter().map(|b| Boolean::constant(*b)).collect(),
+            endianness,
+        }
+    }
+
+    /// Allocates `len` bits, given in the order of `endianness`.
+    pub fn alloc<E, CS>(
+        mut cs: CS,
+        value: Option<&[bool]>,
+        len: usize,
+        endianness: Endianness,
+    ) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        if let Some(value) = value {
+            assert_eq!(value.len(), len);
+        }
+
+        let bits = (0..len)
+            .map(|i| {
+                AllocatedBit::alloc(
+                    cs.namespace(|| format!("bit {}", i)),
+                    value.map(|value| value[i]),
+                )
+                .map(Boolean::from)
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Ok(Bits { bits, endianness })
+    }
+
+    /// Decomposes the number into the bits of its canonical representation
+    /// in the field.
+    pub fn from_num<E, CS>(
+        cs: CS,
+        num: &AllocatedNum<E>,
+        endianness: Endianness,
+    ) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let bits = num.to_bits_le_strict(cs)?;
+
+        Ok(Bits::new(bits, Endianness::Little).with_endianness(endianness))
+    }
+
+    /// Decomposes the number into `len` bits, enforcing that it is smaller
+    /// than `2^len`.
+    pub fn from_num_bounded<E, CS>(
+        cs: CS,
+        num: &AllocatedNum<E>,
+        len: usize,
+        endianness: Endianness,
+    ) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let bits = num.to_bits_le_bounded(cs, len)?;
+
+        Ok(Bits::new(bits, Endianness::Little).with_endianness(endianness))
+    }
+
+    /// Packs the bits into the number they represent. The string must fit
+    /// in the capacity of the field.
+    pub fn to_num<E, CS>(&self, cs: CS) -> Result<AllocatedNum<E>, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        assert!(self.len() <= E::Fr::CAPACITY as usize);
+
+        multipack::pack_bits(cs, &self.to_bits_le())
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// Returns the bits, in the order of the endianness of the string.
+    pub fn bits(&self) -> &[Boolean] {
+        &self.bits
+    }
+
+    pub fn into_bits(self) -> Vec<Boolean> {
+        self.bits
+    }
+
+    pub fn get_value(&self) -> Option<Vec<bool>> {
+        self.bits.iter().map(|b| b.get_value()).collect()
+    }
+
+    /// Returns the same number, with its bits in the given order.
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        if self.endianness != endianness {
+            self.bits.reverse();
+            self.endianness = endianness;
+        }
+
+        self
+    }
+
+    pub fn to_bits_le(&self) -> Vec<Boolean> {
+        self.clone().with_endianness(Endianness::Little).bits
+    }
+
+    pub fn to_bits_be(&self) -> Vec<Boolean> {
+        self.clone().with_endianness(Endianness::Big).bits
+    }
+
+    /// Appends the bits of `other`, in the order of `self`: these are more
+    /// significant than the bits of `self` for a little-endian string, and
+    /// less significant for a big-endian one.
+    pub fn concat(&self, other: &Self) -> Self {
+        let mut bits = self.bits.clone();
+        bits.extend(other.clone().with_endianness(self.endianness).bits);
+
+        Bits::new(bits, self.endianness)
+    }
+
+    /// Returns the bits in `range`, indexed in the order of the string.
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        Bits::new(self.bits[range].to_vec(), self.endianness)
+    }
+
+    /// Enforces that the strings represent the same number. This needs a
+    /// single constraint per chunk of bits fitting in the capacity of the
+    /// field.
+    pub fn enforce_equal<E, CS>(cs: CS, a: &Self, b: &Self) -> Result<(), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        assert_eq!(a.len(), b.len());
+
+        Boolean::enforce_equal_vectors(cs, &a.to_bits_le(), &b.to_bits_le())
+    }
+
+    /// Exposes the bits as public inputs, packed in little-endian order
+    /// into as few inputs as possible.
+    pub fn pack_into_inputs<E, CS>(&self, cs: CS) -> Result<(), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        multipack::pack_into_inputs(cs, &self.to_bits_le())
+    }
+
+    /// Computes the public inputs exposed by
+    /// [`pack_into_inputs`](Self::pack_into_inputs) for the given bits.
+    pub fn compute_multipacking<E: ScalarEngine>(
+        value: &[bool],
+        endianness: Endianness,
+    ) -> Vec<E::Fr> {
+        let mut value = value.to_vec();
+        if endianness == Endianness::Big {
+            value.reverse();
+        }
+
+        multipack::compute_multipacking::<E>(&value)
+    }
+}
+
+/// A string of bytes, each of which is represented by 8 bits in the order
+/// of the endianness of the string. The bytes always come in their natural
+/// order, so that the bits of the string represent the number encoded by
+/// the bytes in the same endianness.
+#[derive(Clone)]
+pub struct Bytes {
+    bits: Bits,
+}
+
+impl Bytes {
+    /// Groups the bits into bytes. The length of the string must be a
+    /// multiple of 8.
+    pub fn from_bits(bits: Bits) -> Self {
+        assert_eq!(bits.len() % 8, 0);
+
+        Bytes { bits }
+    }
+
+    pub fn constant(value: &[u8], endianness: Endianness) -> Self {
+        Bytes {
+            bits: Bits::constant(&bytes_to_bits(value, endianness), endianness),
+        }
+    }
+
+    /// Allocates `len` bytes.
+    pub fn alloc<E, CS>(
+        cs: CS,
+        value: Option<&[u8]>,
+        len: usize,
+        endianness: Endianness,
+    ) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let bits = value.map(|value| {
+            assert_eq!(value.len(), len);
+            bytes_to_bits(value, endianness)
+        });
+
+        Ok(Bytes {
+            bits: Bits::alloc(cs, bits.as_deref(), len * 8, endianness)?,
+        })
+    }
+
+    /// Decomposes the number into `len` bytes, enforcing that it is smaller
+    /// than `2^(8 len)`.
+    pub fn from_num<E, CS>(
+        cs: CS,
+        num: &AllocatedNum<E>,
+        len: usize,
+        endianness: Endianness,
+    ) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        Ok(Bytes {
+            bits: Bits::from_num_bounded(cs, num, len * 8, endianness)?,
+        })
+    }
+
+    /// Packs the bytes into the number they encode. The string must fit in
+    /// the capacity of the field.
+    pub fn to_num<E, CS>(&self, cs: CS) -> Result<AllocatedNum<E>, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        self.bits.to_num(cs)
+    }
+
+    /// Returns the number of bytes.
+    pub fn len(&self) -> usize {
+        self.bits.len() / 8
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    pub fn endianness(&self) -> Endianness {
+        self.bits.endianness()
+    }
+
+    pub fn bits(&self) -> &Bits {
+        &self.bits
+    }
+
+    pub fn into_bits(self) -> Bits {
+        self.bits
+    }
+
+    pub fn get_value(&self) -> Option<Vec<u8>> {
+        let bits = self.bits.get_value()?;
+
+        Some(
+            bits.chunks(8)
+                .map(|byte| {
+                    byte.iter().enumerate().fold(0u8, |acc, (i, bit)| {
+                        let shift = match self.endianness() {
+                            Endianness::Little => i,
+                            Endianness::Big => 7 - i,
+                        };
+                        acc | ((*bit as u8) << shift)
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the same bytes, with the bits of each byte in the given
+    /// order.
+    pub fn with_endianness(self, endianness: Endianness) -> Self {
+        if self.endianness() == endianness {
+            return self;
+        }
+
+        let bits = self
+            .bits
+            .bits
+            .chunks(8)
+            .flat_map(|byte| byte.iter().rev().cloned())
+            .collect();
+
+        Bytes {
+            bits: Bits::new(bits, endianness),
+        }
+    }
+
+    /// Appends the bytes of `other`, converted to the bit order of `self`.
+    pub fn concat(&self, other: &Self) -> Self {
+        let other = other.clone().with_endianness(self.endianness());
+        let mut bits = self.bits.bits.clone();
+        bits.extend(other.bits.bits);
+
+        Bytes {
+            bits: Bits::new(bits, self.endianness()),
+        }
+    }
+
+    /// Returns the bytes in `range`.
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        Bytes {
+            bits: self.bits.slice(range.start * 8..range.end * 8),
+        }
+    }
+
+    /// Enforces that the strings are made of the same bytes.
+    pub fn enforce_equal<E, CS>(cs: CS, a: &Self, b: &Self) -> Result<(), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let b = b.clone().with_endianness(a.endianness());
+
+        Bits::enforce_equal(cs, &a.bits, &b.bits)
+    }
+
+    /// Exposes the bytes as public inputs, packing the bits of the bytes in
+    /// little-endian order into as few inputs as possible. The inputs do
+    /// not depend on the endianness of the string.
+    pub fn pack_into_inputs<E, CS>(&self, cs: CS) -> Result<(), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let bytes = self.clone().with_endianness(Endianness::Little);
+
+        multipack::pack_into_inputs(cs, bytes.bits.bits())
+    }
+
+    /// Computes the public inputs exposed by
+    /// [`pack_into_inputs`](Self::pack_into_inputs) for the given bytes.
+    pub fn compute_multipacking<E: ScalarEngine>(value: &[u8]) -> Vec<E::Fr> {
+        multipack::compute_multipacking::<E>(&multipack::bytes_to_bits_le(value))
+    }
+}
+
+fn bytes_to_bits(value: &[u8], endianness: Endianness) -> Vec<bool> {
+    match endianness {
+        Endianness::Little => multipack::bytes_to_bits_le(value),
+        Endianness::Big => multipack::bytes_to_bits(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::test::*;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_bits_num_conversions() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(Fr::from_str("11").unwrap()))
+            .unwrap();
+
+        let le =
+            Bits::from_num_bounded(cs.namespace(|| "le"), &num, 6, Endianness::Little).unwrap();
+        assert_eq!(
+            le.get_value(),
+            Some(vec![true, true, false, true, false, false])
+        );
+        let be = Bits::from_num_bounded(cs.namespace(|| "be"), &num, 6, Endianness::Big).unwrap();
+        assert_eq!(
+            be.get_value(),
+            Some(vec![false, false, true, false, true, true])
+        );
+
+        for (i, bits) in [&le, &be].iter().enumerate() {
+            let packed = bits.to_num(cs.namespace(|| format!("pack {}", i))).unwrap();
+            assert_eq!(packed.get_value(), num.get_value());
+        }
+
+        let strict = Bits::from_num(cs.namespace(|| "strict"), &num, Endianness::Big).unwrap();
+        assert_eq!(strict.len(), 255);
+        assert_eq!(strict.slice(249..255).get_value(), be.get_value());
+
+        Bits::enforce_equal(cs.namespace(|| "le == be"), &le, &be).unwrap();
+        assert!(cs.is_satisfied());
+
+        // 11 does not fit in 3 bits
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(Fr::from_str("11").unwrap()))
+            .unwrap();
+        Bits::from_num_bounded(cs.namespace(|| "bits"), &num, 3, Endianness::Little).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_bits_concat_and_slice() {
+        let a = Bits::constant(&[true, false], Endianness::Little);
+        let b = Bits::constant(&[true, true, false], Endianness::Big);
+
+        let c = a.concat(&b);
+        assert_eq!(c.endianness(), Endianness::Little);
+        assert_eq!(c.get_value(), Some(vec![true, false, false, true, true]));
+        assert_eq!(c.slice(1..4).get_value(), Some(vec![false, false, true]));
+
+        let d = b.concat(&a);
+        assert_eq!(d.get_value(), Some(vec![true, true, false, false, true]));
+        assert_eq!(
+            d.clone().with_endianness(Endianness::Little).get_value(),
+            Some(vec![true, false, false, true, true])
+        );
+        assert_eq!(d.to_bits_be().len(), 5);
+    }
+
+    #[test]
+    fn test_bits_enforce_equal() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for len in [1, 100, 254, 255, 600].iter().cloned() {
+            let value: Vec<bool> = (0..len).map(|_| rng.next_u32() % 2 != 0).collect();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a = Bits::alloc(
+                cs.namespace(|| "a"),
+                Some(&value[..]),
+                len,
+                Endianness::Little,
+            )
+            .unwrap();
+            let reversed: Vec<bool> = value.iter().rev().cloned().collect();
+            let b = Bits::alloc(
+                cs.namespace(|| "b"),
+                Some(&reversed[..]),
+                len,
+                Endianness::Big,
+            )
+            .unwrap();
+
+            let before = cs.num_constraints();
+            Bits::enforce_equal(cs.namespace(|| "a == b"), &a, &b).unwrap();
+            assert_eq!(cs.num_constraints() - before, (len + 253) / 254);
+            assert!(cs.is_satisfied());
+
+            let mut bad_value = value.clone();
+            bad_value[len / 2] = !bad_value[len / 2];
+            let c = Bits::alloc(
+                cs.namespace(|| "c"),
+                Some(&bad_value[..]),
+                len,
+                Endianness::Little,
+            )
+            .unwrap();
+            Bits::enforce_equal(cs.namespace(|| "a == c"), &a, &c).unwrap();
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some(&*format!("a == c/chunk {}", (len / 2) / 254))
+            );
+        }
+    }
+
+    #[test]
+    fn test_bytes() {
+        let value = b"the quick brown fox jumps over the lazy dog";
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let le = Bytes::alloc(
+            cs.namespace(|| "le"),
+            Some(&value[..]),
+            value.len(),
+            Endianness::Little,
+        )
+        .unwrap();
+        let be = Bytes::alloc(
+            cs.namespace(|| "be"),
+            Some(&value[..]),
+            value.len(),
+            Endianness::Big,
+        )
+        .unwrap();
+
+        assert_eq!(le.len(), value.len());
+        assert_eq!(le.get_value(), Some(value.to_vec()));
+        assert_eq!(be.get_value(), Some(value.to_vec()));
+        assert_eq!(
+            be.clone().with_endianness(Endianness::Little).get_value(),
+            Some(value.to_vec())
+        );
+        assert_eq!(be.bits().get_value(), Some(multipack::bytes_to_bits(value)));
+        assert_eq!(le.slice(4..9).get_value(), Some(b"quick".to_vec()));
+        assert_eq!(
+            le.slice(0..4).concat(&be.slice(10..15)).get_value(),
+            Some(b"the brown".to_vec())
+        );
+
+        Bytes::enforce_equal(cs.namespace(|| "le == be"), &le, &be).unwrap();
+        assert!(cs.is_satisfied());
+
+        // Two bytes packed into a number, in both byte orders
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let bytes = Bytes::constant(&[0x12, 0x34], Endianness::Big);
+        let num = bytes.to_num(cs.namespace(|| "big")).unwrap();
+        assert_eq!(num.get_value(), Some(Fr::from_str("4660").unwrap()));
+        let bytes =
+            Bytes::from_num(cs.namespace(|| "little"), &num, 2, Endianness::Little).unwrap();
+        assert_eq!(bytes.get_value(), Some(vec![0x34, 0x12]));
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_bytes_pack_into_inputs() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for len in [0, 1, 31, 32, 64, 100].iter().cloned() {
+            let mut value = vec![0u8; len];
+            rng.fill_bytes(&mut value);
+
+            for endianness in [Endianness::Little, Endianness::Big].iter().cloned() {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let bytes =
+                    Bytes::alloc(cs.namespace(|| "bytes"), Some(&value[..]), len, endianness)
+                        .unwrap();
+                bytes.pack_into_inputs(cs.namespace(|| "pack")).unwrap();
+
+                let expected = Bytes::compute_multipacking::<Bls12>(&value);
+                assert_eq!(expected.len(), (8 * len + 253) / 254);
+                assert!(cs.is_satisfied());
+                assert!(cs.verify(&expected));
+            }
+        }
+    }
+}
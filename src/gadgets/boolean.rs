@@ -4,6 +4,7 @@ use ff::{BitIterator, Field, PrimeField, ScalarEngine};
 
 use crate::{ConstraintSystem, LinearCombination, SynthesisError, Variable};
 
+use super::num::lc_is_zero;
 use super::Assignment;
 
 /// Represents a variable in the constraint system which is guaranteed
@@ -498,6 +499,203 @@ impl Boolean {
         }
     }
 
+    /// Perform OR over two boolean operands
+    pub fn or<'a, E, CS>(cs: CS, a: &'a Self, b: &'a Self) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        // a OR b = NOT ((NOT a) AND (NOT b))
+        Ok(Boolean::and(cs, &a.not(), &b.not())?.not())
+    }
+
+    /// Perform AND over any number of operands. This costs at most three
+    /// constraints, whatever the number of operands.
+    pub fn kary_and<E, CS>(cs: CS, operands: &[Self]) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let mut variables = vec![];
+        for operand in operands {
+            match *operand {
+                // false AND x is always false
+                Boolean::Constant(false) => return Ok(Boolean::Constant(false)),
+                // true AND x is always x
+                Boolean::Constant(true) => {}
+                _ => variables.push(operand),
+            }
+        }
+
+        match variables.len() {
+            0 => Ok(Boolean::Constant(true)),
+            1 => Ok(variables[0].clone()),
+            2 => Boolean::and(cs, variables[0], variables[1]),
+            _ => {
+                // The operands are all true iff the number of false
+                // operands is zero.
+                let mut lc = LinearCombination::zero();
+                let mut value = Some(E::Fr::zero());
+                for operand in variables {
+                    lc = lc + &operand.not().lc(CS::one(), E::Fr::one());
+                    value = match (value, operand.get_value()) {
+                        (Some(mut value), Some(operand)) => {
+                            if !operand {
+                                value.add_assign(&E::Fr::one());
+                            }
+                            Some(value)
+                        }
+                        _ => None,
+                    };
+                }
+
+                lc_is_zero(cs, value, lc)
+            }
+        }
+    }
+
+    /// Perform OR over any number of operands. This costs at most three
+    /// constraints, whatever the number of operands.
+    pub fn kary_or<E, CS>(cs: CS, operands: &[Self]) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        // OR(x_i) = NOT AND(NOT x_i)
+        let negated: Vec<Self> = operands.iter().map(|operand| operand.not()).collect();
+
+        Ok(Boolean::kary_and(cs, &negated)?.not())
+    }
+
+    /// Returns `a` if `condition` is true, and `b` otherwise.
+    pub fn conditionally_select<E, CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Self,
+    ) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        match (a, b, condition) {
+            (a, _, &Boolean::Constant(true)) => return Ok(a.clone()),
+            (_, b, &Boolean::Constant(false)) => return Ok(b.clone()),
+            (&Boolean::Constant(a), &Boolean::Constant(b), condition) => {
+                return Ok(match (a, b) {
+                    (true, true) => Boolean::Constant(true),
+                    (false, false) => Boolean::Constant(false),
+                    (true, false) => condition.clone(),
+                    (false, true) => condition.not(),
+                });
+            }
+            // condition ? a : false = condition AND a
+            (a, &Boolean::Constant(false), condition) => return Boolean::and(cs, condition, a),
+            // condition ? a : true = (NOT condition) OR a
+            (a, &Boolean::Constant(true), condition) => {
+                return Boolean::or(cs, &condition.not(), a)
+            }
+            // condition ? false : b = (NOT condition) AND b
+            (&Boolean::Constant(false), b, condition) => {
+                return Boolean::and(cs, &condition.not(), b)
+            }
+            // condition ? true : b = condition OR b
+            (&Boolean::Constant(true), b, condition) => return Boolean::or(cs, condition, b),
+            _ => {}
+        }
+
+        let value = match (a.get_value(), b.get_value(), condition.get_value()) {
+            (Some(a), Some(b), Some(condition)) => Some(if condition { a } else { b }),
+            _ => None,
+        };
+
+        let result = cs.alloc(
+            || "select result",
+            || {
+                if *value.get()? {
+                    Ok(E::Fr::one())
+                } else {
+                    Ok(E::Fr::zero())
+                }
+            },
+        )?;
+
+        // Constrain: (a - b) * condition = result - b
+        // The result is either a or b, so it is boolean.
+        cs.enforce(
+            || "select constraint",
+            |_| a.lc(CS::one(), E::Fr::one()) - &b.lc(CS::one(), E::Fr::one()),
+            |_| condition.lc(CS::one(), E::Fr::one()),
+            |lc| lc + result - &b.lc(CS::one(), E::Fr::one()),
+        );
+
+        Ok(AllocatedBit {
+            variable: result,
+            value,
+        }
+        .into())
+    }
+
+    /// Enforces that the vectors are equal, with a single constraint per
+    /// chunk of operands fitting in the capacity of the field.
+    pub fn enforce_equal_vectors<E, CS>(
+        mut cs: CS,
+        a: &[Self],
+        b: &[Self],
+    ) -> Result<(), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        assert_eq!(a.len(), b.len());
+
+        let chunk_size = E::Fr::CAPACITY as usize;
+        for (i, (a, b)) in a.chunks(chunk_size).zip(b.chunks(chunk_size)).enumerate() {
+            if a.iter().chain(b.iter()).all(Boolean::is_constant) {
+                if a.iter()
+                    .zip(b.iter())
+                    .any(|(a, b)| a.get_value() != b.get_value())
+                {
+                    return Err(SynthesisError::Unsatisfiable);
+                }
+                continue;
+            }
+
+            let (lc, _) = packed_difference::<E>(CS::one(), a, b);
+            cs.enforce(|| format!("chunk {}", i), |lc| lc, |lc| lc, |_| lc);
+        }
+
+        Ok(())
+    }
+
+    /// Returns a `Boolean` which is true iff the vectors are equal. This
+    /// costs three constraints per chunk of operands fitting in the capacity
+    /// of the field, and at most three more to combine the chunks.
+    pub fn is_equal_vectors<E, CS>(
+        mut cs: CS,
+        a: &[Self],
+        b: &[Self],
+    ) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        assert_eq!(a.len(), b.len());
+
+        let chunk_size = E::Fr::CAPACITY as usize;
+        let mut chunks = vec![];
+        for (i, (a, b)) in a.chunks(chunk_size).zip(b.chunks(chunk_size)).enumerate() {
+            let (lc, value) = packed_difference::<E>(CS::one(), a, b);
+            chunks.push(lc_is_zero(
+                cs.namespace(|| format!("chunk {}", i)),
+                value,
+                lc,
+            )?);
+        }
+
+        Boolean::kary_and(cs.namespace(|| "all chunks"), &chunks)
+    }
+
     /// Computes (a and b) xor ((not a) and c)
     pub fn sha256_ch<'a, E, CS>(
         mut cs: CS,
@@ -723,6 +921,40 @@ impl Boolean {
     }
 }
 
+/// Returns the sum of `2^i (a_i - b_i)`, and its value. As long as the
+/// operands fit in the capacity of the field, the sum is zero iff the
+/// operands are pairwise equal.
+fn packed_difference<E: ScalarEngine>(
+    one: Variable,
+    a: &[Boolean],
+    b: &[Boolean],
+) -> (LinearCombination<E>, Option<E::Fr>) {
+    assert!(a.len() <= E::Fr::CAPACITY as usize);
+
+    let mut lc = LinearCombination::zero();
+    let mut value = Some(E::Fr::zero());
+    let mut coeff = E::Fr::one();
+    for (a, b) in a.iter().zip(b.iter()) {
+        lc = lc + &a.lc(one, coeff) - &b.lc(one, coeff);
+        value = match (value, a.get_value(), b.get_value()) {
+            (Some(mut value), Some(a), Some(b)) => {
+                if a {
+                    value.add_assign(&coeff);
+                }
+                if b {
+                    value.sub_assign(&coeff);
+                }
+                Some(value)
+            }
+            _ => None,
+        };
+
+        coeff.double();
+    }
+
+    (lc, value)
+}
+
 impl From<AllocatedBit> for Boolean {
     fn from(b: AllocatedBit) -> Boolean {
         Boolean::Is(b)
@@ -736,6 +968,8 @@ mod test {
     use crate::gadgets::test::*;
     use crate::ConstraintSystem;
     use ff::{Field, PrimeField};
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
 
     #[test]
     fn test_allocated_bit() {
@@ -1510,6 +1744,262 @@ mod test {
         }
     }
 
+    fn construct_operand<CS: ConstraintSystem<Bls12>>(cs: CS, operand: OperandType) -> Boolean {
+        match operand {
+            OperandType::True => Boolean::constant(true),
+            OperandType::False => Boolean::constant(false),
+            OperandType::AllocatedTrue => {
+                Boolean::from(AllocatedBit::alloc(cs, Some(true)).unwrap())
+            }
+            OperandType::AllocatedFalse => {
+                Boolean::from(AllocatedBit::alloc(cs, Some(false)).unwrap())
+            }
+            OperandType::NegatedAllocatedTrue => {
+                Boolean::from(AllocatedBit::alloc(cs, Some(true)).unwrap()).not()
+            }
+            OperandType::NegatedAllocatedFalse => {
+                Boolean::from(AllocatedBit::alloc(cs, Some(false)).unwrap()).not()
+            }
+        }
+    }
+
+    #[test]
+    fn test_boolean_or() {
+        let variants = [
+            OperandType::True,
+            OperandType::False,
+            OperandType::AllocatedTrue,
+            OperandType::AllocatedFalse,
+            OperandType::NegatedAllocatedTrue,
+            OperandType::NegatedAllocatedFalse,
+        ];
+
+        for first_operand in variants.iter().cloned() {
+            for second_operand in variants.iter().cloned() {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                let a = construct_operand(cs.namespace(|| "a"), first_operand);
+                let b = construct_operand(cs.namespace(|| "b"), second_operand);
+                let c = Boolean::or(&mut cs, &a, &b).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(
+                    c.get_value().unwrap(),
+                    first_operand.val() | second_operand.val()
+                );
+
+                if first_operand.is_constant() && second_operand.is_constant() {
+                    assert_eq!(cs.num_constraints(), 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_boolean_kary_and_or() {
+        let variants = [
+            OperandType::True,
+            OperandType::False,
+            OperandType::AllocatedTrue,
+            OperandType::AllocatedFalse,
+            OperandType::NegatedAllocatedTrue,
+            OperandType::NegatedAllocatedFalse,
+        ];
+
+        for first_operand in variants.iter().cloned() {
+            for second_operand in variants.iter().cloned() {
+                for third_operand in variants.iter().cloned() {
+                    let operand_types = [first_operand, second_operand, third_operand];
+
+                    let mut cs = TestConstraintSystem::<Bls12>::new();
+                    let operands: Vec<Boolean> = operand_types
+                        .iter()
+                        .enumerate()
+                        .map(|(i, operand)| {
+                            construct_operand(cs.namespace(|| format!("operand {}", i)), *operand)
+                        })
+                        .collect();
+                    let num_allocated = cs.num_constraints();
+
+                    let and = Boolean::kary_and(cs.namespace(|| "and"), &operands).unwrap();
+                    let or = Boolean::kary_or(cs.namespace(|| "or"), &operands).unwrap();
+
+                    assert!(cs.is_satisfied());
+                    assert_eq!(
+                        and.get_value().unwrap(),
+                        operand_types.iter().all(|operand| operand.val())
+                    );
+                    assert_eq!(
+                        or.get_value().unwrap(),
+                        operand_types.iter().any(|operand| operand.val())
+                    );
+                    assert!(cs.num_constraints() <= num_allocated + 6);
+
+                    if operand_types.iter().all(|operand| operand.is_constant()) {
+                        assert_eq!(cs.num_constraints(), 0);
+                    }
+                }
+            }
+        }
+
+        for num_operands in 0..10 {
+            for false_operand in 0..=num_operands {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let operands: Vec<Boolean> = (0..num_operands)
+                    .map(|i| {
+                        let value = Some(i != false_operand);
+                        AllocatedBit::alloc(cs.namespace(|| format!("operand {}", i)), value)
+                            .unwrap()
+                            .into()
+                    })
+                    .collect();
+
+                let and = Boolean::kary_and(cs.namespace(|| "and"), &operands).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(and.get_value().unwrap(), false_operand == num_operands);
+                assert!(cs.num_constraints() <= num_operands + 3);
+
+                if num_operands > 2 {
+                    // Claim that the operands are all true
+                    let expected = false_operand == num_operands;
+                    cs.set("and/is zero/boolean", {
+                        if expected {
+                            Fr::zero()
+                        } else {
+                            Fr::one()
+                        }
+                    });
+                    assert!(!cs.is_satisfied());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_boolean_conditionally_select() {
+        let variants = [
+            OperandType::True,
+            OperandType::False,
+            OperandType::AllocatedTrue,
+            OperandType::AllocatedFalse,
+            OperandType::NegatedAllocatedTrue,
+            OperandType::NegatedAllocatedFalse,
+        ];
+
+        for first_operand in variants.iter().cloned() {
+            for second_operand in variants.iter().cloned() {
+                for condition in variants.iter().cloned() {
+                    let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                    let a = construct_operand(cs.namespace(|| "a"), first_operand);
+                    let b = construct_operand(cs.namespace(|| "b"), second_operand);
+                    let c = construct_operand(cs.namespace(|| "condition"), condition);
+
+                    let expected = if condition.val() {
+                        first_operand.val()
+                    } else {
+                        second_operand.val()
+                    };
+
+                    let result =
+                        Boolean::conditionally_select(cs.namespace(|| "select"), &a, &b, &c)
+                            .unwrap();
+
+                    assert!(cs.is_satisfied());
+                    assert_eq!(result.get_value().unwrap(), expected);
+
+                    if !first_operand.is_constant()
+                        && !second_operand.is_constant()
+                        && !condition.is_constant()
+                    {
+                        assert_eq!(cs.get("select/select result"), {
+                            if expected {
+                                Fr::one()
+                            } else {
+                                Fr::zero()
+                            }
+                        });
+                        cs.set("select/select result", {
+                            if expected {
+                                Fr::zero()
+                            } else {
+                                Fr::one()
+                            }
+                        });
+                        assert_eq!(
+                            cs.which_is_unsatisfied().unwrap(),
+                            "select/select constraint"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn alloc_bits<CS: ConstraintSystem<Bls12>>(mut cs: CS, values: &[bool]) -> Vec<Boolean> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b))
+                    .unwrap()
+                    .into()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_boolean_equal_vectors() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for len in [0, 1, 253, 254, 255, 600].iter().cloned() {
+            let values: Vec<bool> = (0..len).map(|_| rng.next_u32() % 2 != 0).collect();
+            let mut other_values = values.clone();
+            if len > 0 {
+                let i = rng.next_u32() as usize % len;
+                other_values[i] = !other_values[i];
+            }
+
+            let num_chunks = (len + 253) / 254;
+
+            for equal in [true, false].iter().cloned() {
+                let other_values = if equal { &values } else { &other_values };
+
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let a = alloc_bits(cs.namespace(|| "a"), &values);
+                let b = alloc_bits(cs.namespace(|| "b"), other_values);
+                Boolean::enforce_equal_vectors(cs.namespace(|| "a == b"), &a, &b).unwrap();
+
+                assert_eq!(cs.num_constraints(), 2 * len + num_chunks);
+                assert_eq!(cs.is_satisfied(), equal || len == 0);
+
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let a = alloc_bits(cs.namespace(|| "a"), &values);
+                let b = alloc_bits(cs.namespace(|| "b"), other_values);
+                let is_equal =
+                    Boolean::is_equal_vectors(cs.namespace(|| "a == b"), &a, &b).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(is_equal.get_value().unwrap(), equal || len == 0);
+            }
+
+            // Constant operands are checked at synthesis
+            let a: Vec<Boolean> = values.iter().map(|b| Boolean::constant(*b)).collect();
+            let b: Vec<Boolean> = other_values.iter().map(|b| Boolean::constant(*b)).collect();
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            assert!(Boolean::enforce_equal_vectors(&mut cs, &a, &a).is_ok());
+            assert_eq!(
+                Boolean::enforce_equal_vectors(&mut cs, &a, &b).is_ok(),
+                len == 0
+            );
+            assert_eq!(cs.num_constraints(), 0);
+        }
+    }
+
     #[allow(clippy::identity_op)]
     #[test]
     fn test_u64_into_boolean_vec_le() {
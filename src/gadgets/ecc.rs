@@ -0,0 +1,881 @@
+//! Gadgets for the Jubjub twisted Edwards curve, which is embedded in the
+//! scalar field of BLS12-381. See [`crate::jubjub`] for the native curve.
+
+use ff::{Field, ScalarEngine};
+
+use super::boolean::Boolean;
+use super::lookup::lookup3_xy;
+use super::num::{AllocatedNum, Num};
+use super::Assignment;
+use crate::jubjub::{JubjubParams, Point};
+use crate::{ConstraintSystem, SynthesisError};
+
+/// A point of the curve in twisted Edwards coordinates.
+pub struct EdwardsPoint<E: ScalarEngine> {
+    x: AllocatedNum<E>,
+    y: AllocatedNum<E>,
+}
+
+impl<E: ScalarEngine> Clone for EdwardsPoint<E> {
+    fn clone(&self) -> Self {
+        EdwardsPoint {
+            x: self.x.clone(),
+            y: self.y.clone(),
+        }
+    }
+}
+
+/// Performs a scalar multiplication of a fixed base by the little-endian
+/// bits `by`, where `base` is the window table computed by
+/// [`Point::window_table`] with at least `by.len() / 3` windows.
+pub fn fixed_base_multiplication<E, CS>(
+    mut cs: CS,
+    base: &[Vec<(E::Fr, E::Fr)>],
+    by: &[Boolean],
+    params: &JubjubParams<E>,
+) -> Result<EdwardsPoint<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(!by.is_empty());
+    assert!(base.len() * 3 >= by.len());
+
+    let mut result: Option<EdwardsPoint<E>> = None;
+
+    for (i, (chunk, window)) in by.chunks(3).zip(base.iter()).enumerate() {
+        // Pad the last chunk with zeroes
+        let chunk_a = chunk[0].clone();
+        let chunk_b = chunk
+            .get(1)
+            .cloned()
+            .unwrap_or_else(|| Boolean::constant(false));
+        let chunk_c = chunk
+            .get(2)
+            .cloned()
+            .unwrap_or_else(|| Boolean::constant(false));
+
+        let (x, y) = lookup3_xy(
+            cs.namespace(|| format!("window table lookup {}", i)),
+            &[chunk_a, chunk_b, chunk_c],
+            window,
+        )?;
+
+        let p = EdwardsPoint { x, y };
+
+        result = Some(match result {
+            None => p,
+            Some(result) => result.add(cs.namespace(|| format!("addition {}", i)), &p, params)?,
+        });
+    }
+
+    Ok(result.expect("by is not empty"))
+}
+
+impl<E: ScalarEngine> EdwardsPoint<E> {
+    pub fn get_x(&self) -> &AllocatedNum<E> {
+        &self.x
+    }
+
+    pub fn get_y(&self) -> &AllocatedNum<E> {
+        &self.y
+    }
+
+    pub fn get_value(&self) -> Option<Point<E>> {
+        match (self.x.get_value(), self.y.get_value()) {
+            (Some(x), Some(y)) => Some(Point::from_xy_unchecked(x, y)),
+            _ => None,
+        }
+    }
+
+    /// Allocates a point, enforcing that it is on the curve.
+    pub fn witness<CS>(
+        mut cs: CS,
+        p: Option<Point<E>>,
+        params: &JubjubParams<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(p.get()?.get_x()))?;
+        let y = AllocatedNum::alloc(cs.namespace(|| "y"), || Ok(p.get()?.get_y()))?;
+
+        Self::interpret(cs.namespace(|| "point interpretation"), &x, &y, params)
+    }
+
+    /// Interprets the given coordinates as a point, enforcing that it is
+    /// on the curve.
+    pub fn interpret<CS>(
+        mut cs: CS,
+        x: &AllocatedNum<E>,
+        y: &AllocatedNum<E>,
+        params: &JubjubParams<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        // -x^2 + y^2 = 1 + d.x^2.y^2
+        let x2 = x.square(cs.namespace(|| "x^2"))?;
+        let y2 = y.square(cs.namespace(|| "y^2"))?;
+        let x2y2 = x2.mul(cs.namespace(|| "x^2 y^2"), &y2)?;
+
+        let one = CS::one();
+        cs.enforce(
+            || "on curve check",
+            |lc| lc - x2.get_variable() + y2.get_variable(),
+            |lc| lc + one,
+            |lc| lc + one + (*params.edwards_d(), x2y2.get_variable()),
+        );
+
+        Ok(EdwardsPoint {
+            x: x.clone(),
+            y: y.clone(),
+        })
+    }
+
+    /// Exposes the coordinates of the point as public inputs.
+    pub fn inputize<CS>(&self, mut cs: CS) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        self.x.inputize(cs.namespace(|| "x"))?;
+        self.y.inputize(cs.namespace(|| "y"))?;
+
+        Ok(())
+    }
+
+    /// Returns the little-endian bits of the y-coordinate followed by the
+    /// sign of the x-coordinate, as encoded by [`Point::to_bytes`].
+    pub fn repr<CS>(&self, mut cs: CS) -> Result<Vec<Boolean>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let x = self.x.to_bits_le_strict(cs.namespace(|| "unpack x"))?;
+        let mut y = self.y.to_bits_le_strict(cs.namespace(|| "unpack y"))?;
+
+        y.push(x[0].clone());
+
+        Ok(y)
+    }
+
+    /// Enforces that the point is not of small order, i.e. that it is
+    /// not the identity after multiplication by the cofactor.
+    pub fn assert_not_small_order<CS>(
+        &self,
+        mut cs: CS,
+        params: &JubjubParams<E>,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let tmp = self.double(cs.namespace(|| "first doubling"), params)?;
+        let tmp = tmp.double(cs.namespace(|| "second doubling"), params)?;
+        let tmp = tmp.double(cs.namespace(|| "third doubling"), params)?;
+
+        // (0, -1) is of order two, so it cannot be the result of three
+        // doublings, and (0, 1) is the identity: checking that x is
+        // nonzero is enough.
+        tmp.x.assert_nonzero(cs.namespace(|| "check x != 0"))?;
+
+        Ok(())
+    }
+
+    /// Returns this point if `condition` is true, and the identity otherwise.
+    pub fn conditionally_select<CS>(
+        &self,
+        mut cs: CS,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        // x' = x if condition, and 0 otherwise
+        let x_prime = AllocatedNum::alloc(cs.namespace(|| "x'"), || {
+            if *condition.get_value().get()? {
+                Ok(*self.x.get_value().get()?)
+            } else {
+                Ok(E::Fr::zero())
+            }
+        })?;
+
+        // x * condition = x'
+        let one = CS::one();
+        cs.enforce(
+            || "x' computation",
+            |lc| lc + self.x.get_variable(),
+            |_| condition.lc(one, E::Fr::one()),
+            |lc| lc + x_prime.get_variable(),
+        );
+
+        // y' = y if condition, and 1 otherwise
+        let y_prime = AllocatedNum::alloc(cs.namespace(|| "y'"), || {
+            if *condition.get_value().get()? {
+                Ok(*self.y.get_value().get()?)
+            } else {
+                Ok(E::Fr::one())
+            }
+        })?;
+
+        // (y - 1) * condition = y' - 1
+        cs.enforce(
+            || "y' computation",
+            |lc| lc + self.y.get_variable() - one,
+            |_| condition.lc(one, E::Fr::one()),
+            |lc| lc + y_prime.get_variable() - one,
+        );
+
+        Ok(EdwardsPoint {
+            x: x_prime,
+            y: y_prime,
+        })
+    }
+
+    /// Performs a scalar multiplication of this point by the little-endian
+    /// bits `by`, with a conditional addition per bit.
+    pub fn mul<CS>(
+        &self,
+        mut cs: CS,
+        by: &[Boolean],
+        params: &JubjubParams<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        assert!(!by.is_empty());
+
+        // The base shifted by the index of the current bit
+        let mut curbase = self.clone();
+        let mut result: Option<Self> = None;
+
+        for (i, bit) in by.iter().enumerate() {
+            if i > 0 {
+                curbase = curbase.double(cs.namespace(|| format!("doubling {}", i)), params)?;
+            }
+
+            let thisbase =
+                curbase.conditionally_select(cs.namespace(|| format!("selection {}", i)), bit)?;
+
+            result = Some(match result {
+                None => thisbase,
+                Some(result) => result.add(
+                    cs.namespace(|| format!("addition {}", i)),
+                    &thisbase,
+                    params,
+                )?,
+            });
+        }
+
+        Ok(result.expect("by is not empty"))
+    }
+
+    pub fn double<CS>(&self, mut cs: CS, params: &JubjubParams<E>) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        // Compute T = (x1 + y1) * (x1 + y1)
+        let t = AllocatedNum::alloc(cs.namespace(|| "T"), || {
+            let mut t0 = *self.x.get_value().get()?;
+            t0.add_assign(self.y.get_value().get()?);
+            t0.square();
+
+            Ok(t0)
+        })?;
+
+        cs.enforce(
+            || "T computation",
+            |lc| lc + self.x.get_variable() + self.y.get_variable(),
+            |lc| lc + self.x.get_variable() + self.y.get_variable(),
+            |lc| lc + t.get_variable(),
+        );
+
+        // Compute A = x1 * y1
+        let a = self.x.mul(cs.namespace(|| "A computation"), &self.y)?;
+
+        // Compute C = d*A*A
+        let c = AllocatedNum::alloc(cs.namespace(|| "C"), || {
+            let mut t0 = *a.get_value().get()?;
+            t0.square();
+            t0.mul_assign(params.edwards_d());
+
+            Ok(t0)
+        })?;
+
+        cs.enforce(
+            || "C computation",
+            |lc| lc + (*params.edwards_d(), a.get_variable()),
+            |lc| lc + a.get_variable(),
+            |lc| lc + c.get_variable(),
+        );
+
+        // Compute x3 = (2.A) / (1 + C)
+        let x3 = AllocatedNum::alloc(cs.namespace(|| "x3"), || {
+            let mut t0 = *a.get_value().get()?;
+            t0.double();
+
+            let mut t1 = E::Fr::one();
+            t1.add_assign(c.get_value().get()?);
+            let t1 = t1.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+            t0.mul_assign(&t1);
+
+            Ok(t0)
+        })?;
+
+        let one = CS::one();
+        cs.enforce(
+            || "x3 computation",
+            |lc| lc + one + c.get_variable(),
+            |lc| lc + x3.get_variable(),
+            |lc| lc + a.get_variable() + a.get_variable(),
+        );
+
+        // Compute y3 = (T - 2.A) / (1 - C)
+        let y3 = AllocatedNum::alloc(cs.namespace(|| "y3"), || {
+            let mut t0 = *a.get_value().get()?;
+            t0.double();
+            t0.negate();
+            t0.add_assign(t.get_value().get()?);
+
+            let mut t1 = E::Fr::one();
+            t1.sub_assign(c.get_value().get()?);
+            let t1 = t1.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+            t0.mul_assign(&t1);
+
+            Ok(t0)
+        })?;
+
+        cs.enforce(
+            || "y3 computation",
+            |lc| lc + one - c.get_variable(),
+            |lc| lc + y3.get_variable(),
+            |lc| lc + t.get_variable() - a.get_variable() - a.get_variable(),
+        );
+
+        Ok(EdwardsPoint { x: x3, y: y3 })
+    }
+
+    pub fn add<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        params: &JubjubParams<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        // Compute U = (x1 + y1) * (x2 + y2)
+        let u = AllocatedNum::alloc(cs.namespace(|| "U"), || {
+            let mut t0 = *self.x.get_value().get()?;
+            t0.add_assign(self.y.get_value().get()?);
+
+            let mut t1 = *other.x.get_value().get()?;
+            t1.add_assign(other.y.get_value().get()?);
+
+            t0.mul_assign(&t1);
+
+            Ok(t0)
+        })?;
+
+        cs.enforce(
+            || "U computation",
+            |lc| lc + self.x.get_variable() + self.y.get_variable(),
+            |lc| lc + other.x.get_variable() + other.y.get_variable(),
+            |lc| lc + u.get_variable(),
+        );
+
+        // Compute A = y2 * x1
+        let a = other.y.mul(cs.namespace(|| "A computation"), &self.x)?;
+
+        // Compute B = x2 * y1
+        let b = other.x.mul(cs.namespace(|| "B computation"), &self.y)?;
+
+        // Compute C = d*A*B
+        let c = AllocatedNum::alloc(cs.namespace(|| "C"), || {
+            let mut t0 = *a.get_value().get()?;
+            t0.mul_assign(b.get_value().get()?);
+            t0.mul_assign(params.edwards_d());
+
+            Ok(t0)
+        })?;
+
+        cs.enforce(
+            || "C computation",
+            |lc| lc + (*params.edwards_d(), a.get_variable()),
+            |lc| lc + b.get_variable(),
+            |lc| lc + c.get_variable(),
+        );
+
+        // Compute x3 = (A + B) / (1 + C)
+        let x3 = AllocatedNum::alloc(cs.namespace(|| "x3"), || {
+            let mut t0 = *a.get_value().get()?;
+            t0.add_assign(b.get_value().get()?);
+
+            let mut t1 = E::Fr::one();
+            t1.add_assign(c.get_value().get()?);
+            let t1 = t1.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+            t0.mul_assign(&t1);
+
+            Ok(t0)
+        })?;
+
+        let one = CS::one();
+        cs.enforce(
+            || "x3 computation",
+            |lc| lc + one + c.get_variable(),
+            |lc| lc + x3.get_variable(),
+            |lc| lc + a.get_variable() + b.get_variable(),
+        );
+
+        // Compute y3 = (U - A - B) / (1 - C)
+        let y3 = AllocatedNum::alloc(cs.namespace(|| "y3"), || {
+            let mut t0 = *u.get_value().get()?;
+            t0.sub_assign(a.get_value().get()?);
+            t0.sub_assign(b.get_value().get()?);
+
+            let mut t1 = E::Fr::one();
+            t1.sub_assign(c.get_value().get()?);
+            let t1 = t1.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+            t0.mul_assign(&t1);
+
+            Ok(t0)
+        })?;
+
+        cs.enforce(
+            || "y3 computation",
+            |lc| lc + one - c.get_variable(),
+            |lc| lc + y3.get_variable(),
+            |lc| lc + u.get_variable() - a.get_variable() - b.get_variable(),
+        );
+
+        Ok(EdwardsPoint { x: x3, y: y3 })
+    }
+}
+
+/// A point of the birationally equivalent Montgomery curve, whose
+/// incomplete addition is cheaper than addition in Edwards form.
+pub struct MontgomeryPoint<E: ScalarEngine> {
+    x: Num<E>,
+    y: Num<E>,
+}
+
+impl<E: ScalarEngine> MontgomeryPoint<E> {
+    /// Interprets the given coordinates as a point, without checking
+    /// that it is on the curve.
+    pub fn interpret_unchecked(x: Num<E>, y: Num<E>) -> Self {
+        MontgomeryPoint { x, y }
+    }
+
+    /// Converts the point into twisted Edwards form. This is not defined
+    /// for the points of order one and two.
+    pub fn into_edwards<CS>(
+        &self,
+        mut cs: CS,
+        params: &JubjubParams<E>,
+    ) -> Result<EdwardsPoint<E>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        // Compute u = (scale*x) / y
+        let u = AllocatedNum::alloc(cs.namespace(|| "u"), || {
+            let mut t0 = *self.x.get_value().get()?;
+            t0.mul_assign(params.scale());
+
+            let t1 = self
+                .y
+                .get_value()
+                .get()?
+                .inverse()
+                .ok_or(SynthesisError::DivisionByZero)?;
+
+            t0.mul_assign(&t1);
+
+            Ok(t0)
+        })?;
+
+        cs.enforce(
+            || "u computation",
+            |lc| lc + &self.y.lc(E::Fr::one()),
+            |lc| lc + u.get_variable(),
+            |lc| lc + &self.x.lc(*params.scale()),
+        );
+
+        // Compute v = (x - 1) / (x + 1)
+        let v = AllocatedNum::alloc(cs.namespace(|| "v"), || {
+            let mut t0 = *self.x.get_value().get()?;
+            let mut t1 = t0;
+            t0.sub_assign(&E::Fr::one());
+            t1.add_assign(&E::Fr::one());
+
+            let t1 = t1.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+            t0.mul_assign(&t1);
+
+            Ok(t0)
+        })?;
+
+        let one = CS::one();
+        cs.enforce(
+            || "v computation",
+            |lc| lc + &self.x.lc(E::Fr::one()) + one,
+            |lc| lc + v.get_variable(),
+            |lc| lc + &self.x.lc(E::Fr::one()) - one,
+        );
+
+        Ok(EdwardsPoint { x: u, y: v })
+    }
+
+    /// Performs an affine point addition. This is not defined when the
+    /// points have the same x-coordinate, which yields an error during
+    /// witness generation.
+    pub fn add<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        params: &JubjubParams<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        // Compute lambda = (y' - y) / (x' - x)
+        let lambda = AllocatedNum::alloc(cs.namespace(|| "lambda"), || {
+            let mut n = *other.y.get_value().get()?;
+            n.sub_assign(self.y.get_value().get()?);
+
+            let mut d = *other.x.get_value().get()?;
+            d.sub_assign(self.x.get_value().get()?);
+            let d = d.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+            n.mul_assign(&d);
+
+            Ok(n)
+        })?;
+
+        cs.enforce(
+            || "evaluate lambda",
+            |lc| lc + &other.x.lc(E::Fr::one()) - &self.x.lc(E::Fr::one()),
+            |lc| lc + lambda.get_variable(),
+            |lc| lc + &other.y.lc(E::Fr::one()) - &self.y.lc(E::Fr::one()),
+        );
+
+        // Compute x'' = lambda^2 - A - x - x'
+        let xprime = AllocatedNum::alloc(cs.namespace(|| "xprime"), || {
+            let mut t0 = *lambda.get_value().get()?;
+            t0.square();
+            t0.sub_assign(params.montgomery_a());
+            t0.sub_assign(self.x.get_value().get()?);
+            t0.sub_assign(other.x.get_value().get()?);
+
+            Ok(t0)
+        })?;
+
+        // (lambda) * (lambda) = (A + x + x' + x'')
+        let one = CS::one();
+        cs.enforce(
+            || "evaluate xprime",
+            |lc| lc + lambda.get_variable(),
+            |lc| lc + lambda.get_variable(),
+            |lc| {
+                lc + (*params.montgomery_a(), one)
+                    + &self.x.lc(E::Fr::one())
+                    + &other.x.lc(E::Fr::one())
+                    + xprime.get_variable()
+            },
+        );
+
+        // Compute y'' = lambda(x - x'') - y
+        let yprime = AllocatedNum::alloc(cs.namespace(|| "yprime"), || {
+            let mut t0 = *self.x.get_value().get()?;
+            t0.sub_assign(xprime.get_value().get()?);
+            t0.mul_assign(lambda.get_value().get()?);
+            t0.sub_assign(self.y.get_value().get()?);
+
+            Ok(t0)
+        })?;
+
+        // (lambda) * (x - x'') = (y'' + y)
+        cs.enforce(
+            || "evaluate yprime",
+            |lc| lc + &self.x.lc(E::Fr::one()) - xprime.get_variable(),
+            |lc| lc + lambda.get_variable(),
+            |lc| lc + yprime.get_variable() + &self.y.lc(E::Fr::one()),
+        );
+
+        Ok(MontgomeryPoint {
+            x: xprime.into(),
+            y: yprime.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::test::*;
+    use crate::ConstraintSystem;
+    use ff::Field;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use super::{fixed_base_multiplication, EdwardsPoint, MontgomeryPoint};
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::multipack::bytes_to_bits_le;
+    use crate::gadgets::num::AllocatedNum;
+    use crate::jubjub::{JubjubParams, Point};
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ])
+    }
+
+    fn random_point<R: RngCore>(rng: &mut R, params: &JubjubParams<Bls12>) -> Point<Bls12> {
+        params.pedersen_hash_generators()[0].mul([rng.next_u64()], params)
+    }
+
+    fn random_bits<R: RngCore>(rng: &mut R, num_bits: usize) -> Vec<bool> {
+        (0..num_bits).map(|_| rng.next_u32() % 2 != 0).collect()
+    }
+
+    fn bits_to_limbs(bits: &[bool]) -> Vec<u64> {
+        let mut limbs = vec![0u64; (bits.len() + 63) / 64];
+        for (i, bit) in bits.iter().enumerate() {
+            if *bit {
+                limbs[i / 64] |= 1 << (i % 64);
+            }
+        }
+        limbs
+    }
+
+    fn alloc_bits<CS: ConstraintSystem<Bls12>>(mut cs: CS, bits: &[bool]) -> Vec<Boolean> {
+        bits.iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b)).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_edwards_interpret() {
+        let mut rng = rng();
+        let params = JubjubParams::<Bls12>::new();
+
+        let p = random_point(&mut rng, &params);
+
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let q = EdwardsPoint::witness(&mut cs, Some(p), &params).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 4);
+            assert_eq!(q.get_value(), Some(p));
+        }
+
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let mut y = p.get_y();
+            y.add_assign(&Fr::one());
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(p.get_x())).unwrap();
+            let y = AllocatedNum::alloc(cs.namespace(|| "y"), || Ok(y)).unwrap();
+
+            EdwardsPoint::interpret(&mut cs, &x, &y, &params).unwrap();
+
+            assert!(!cs.is_satisfied());
+            assert_eq!(cs.which_is_unsatisfied(), Some("on curve check"));
+        }
+    }
+
+    #[test]
+    fn test_edwards_repr() {
+        let mut rng = rng();
+        let params = JubjubParams::<Bls12>::new();
+
+        for _ in 0..10 {
+            let p = random_point(&mut rng, &params);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let q = EdwardsPoint::witness(cs.namespace(|| "p"), Some(p), &params).unwrap();
+            let repr = q.repr(cs.namespace(|| "repr")).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(
+                repr.iter()
+                    .map(|b| b.get_value().unwrap())
+                    .collect::<Vec<_>>(),
+                bytes_to_bits_le(&p.to_bytes())
+            );
+        }
+    }
+
+    #[test]
+    fn test_edwards_addition() {
+        let mut rng = rng();
+        let params = JubjubParams::<Bls12>::new();
+
+        for _ in 0..10 {
+            let p1 = random_point(&mut rng, &params);
+            let p2 = random_point(&mut rng, &params);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a = EdwardsPoint::witness(cs.namespace(|| "a"), Some(p1), &params).unwrap();
+            let b = EdwardsPoint::witness(cs.namespace(|| "b"), Some(p2), &params).unwrap();
+
+            let sum = a.add(cs.namespace(|| "a + b"), &b, &params).unwrap();
+            let double = a.double(cs.namespace(|| "2a"), &params).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(sum.get_value(), Some(p1.add(&p2, &params)));
+            assert_eq!(double.get_value(), Some(p1.double(&params)));
+
+            cs.set("a + b/x3/num", Fr::one());
+            assert_eq!(cs.which_is_unsatisfied(), Some("a + b/x3 computation"));
+        }
+    }
+
+    #[test]
+    fn test_edwards_conditionally_select() {
+        let mut rng = rng();
+        let params = JubjubParams::<Bls12>::new();
+
+        for condition in &[false, true] {
+            let p = random_point(&mut rng, &params);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a = EdwardsPoint::witness(cs.namespace(|| "a"), Some(p), &params).unwrap();
+            let condition = Boolean::from(
+                AllocatedBit::alloc(cs.namespace(|| "condition"), Some(*condition)).unwrap(),
+            );
+
+            let res = a
+                .conditionally_select(cs.namespace(|| "select"), &condition)
+                .unwrap();
+
+            assert!(cs.is_satisfied());
+            if condition.get_value().unwrap() {
+                assert_eq!(res.get_value(), Some(p));
+            } else {
+                assert_eq!(res.get_value(), Some(Point::identity()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_edwards_multiplication() {
+        let mut rng = rng();
+        let params = JubjubParams::<Bls12>::new();
+
+        for num_bits in &[1, 2, 64, 252] {
+            let p = random_point(&mut rng, &params);
+            let bits = random_bits(&mut rng, *num_bits);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a = EdwardsPoint::witness(cs.namespace(|| "a"), Some(p), &params).unwrap();
+            let by = alloc_bits(cs.namespace(|| "scalar"), &bits);
+
+            let res = a
+                .mul(cs.namespace(|| "multiplication"), &by, &params)
+                .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(res.get_value(), Some(p.mul(bits_to_limbs(&bits), &params)));
+        }
+    }
+
+    #[test]
+    fn test_edwards_fixed_base_multiplication() {
+        let mut rng = rng();
+        let params = JubjubParams::<Bls12>::new();
+
+        let base = params.pedersen_hash_generators()[2];
+        let table = base.window_table(84, &params);
+
+        for num_bits in &[1, 3, 100, 252] {
+            let bits = random_bits(&mut rng, *num_bits);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let by = alloc_bits(cs.namespace(|| "scalar"), &bits);
+
+            let res =
+                fixed_base_multiplication(cs.namespace(|| "multiplication"), &table, &by, &params)
+                    .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(
+                res.get_value(),
+                Some(base.mul(bits_to_limbs(&bits), &params))
+            );
+        }
+    }
+
+    #[test]
+    fn test_edwards_assert_not_small_order() {
+        let mut rng = rng();
+        let params = JubjubParams::<Bls12>::new();
+
+        {
+            let p = random_point(&mut rng, &params);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a = EdwardsPoint::witness(cs.namespace(|| "a"), Some(p), &params).unwrap();
+            a.assert_not_small_order(cs.namespace(|| "check"), &params)
+                .unwrap();
+
+            assert!(cs.is_satisfied());
+        }
+
+        {
+            // (0, -1) is of order two
+            let mut y = Fr::one();
+            y.negate();
+            let p = Point::from_xy(Fr::zero(), y, &params).unwrap();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a = EdwardsPoint::witness(cs.namespace(|| "a"), Some(p), &params).unwrap();
+
+            assert!(a
+                .assert_not_small_order(cs.namespace(|| "check"), &params)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_montgomery_addition() {
+        let mut rng = rng();
+        let params = JubjubParams::<Bls12>::new();
+
+        for _ in 0..10 {
+            let p1 = random_point(&mut rng, &params);
+            let p2 = random_point(&mut rng, &params);
+
+            let (x1, y1) = p1.to_montgomery(&params).unwrap();
+            let (x2, y2) = p2.to_montgomery(&params).unwrap();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let x1 = AllocatedNum::alloc(cs.namespace(|| "x1"), || Ok(x1)).unwrap();
+            let y1 = AllocatedNum::alloc(cs.namespace(|| "y1"), || Ok(y1)).unwrap();
+            let x2 = AllocatedNum::alloc(cs.namespace(|| "x2"), || Ok(x2)).unwrap();
+            let y2 = AllocatedNum::alloc(cs.namespace(|| "y2"), || Ok(y2)).unwrap();
+
+            let a = MontgomeryPoint::interpret_unchecked(x1.into(), y1.into());
+            let b = MontgomeryPoint::interpret_unchecked(x2.into(), y2.into());
+
+            let sum = a.add(cs.namespace(|| "addition"), &b, &params).unwrap();
+            let sum = sum
+                .into_edwards(cs.namespace(|| "conversion"), &params)
+                .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(sum.get_value(), Some(p1.add(&p2, &params)));
+
+            cs.set("addition/yprime/num", Fr::one());
+            assert_eq!(cs.which_is_unsatisfied(), Some("addition/evaluate yprime"));
+        }
+    }
+}
@@ -0,0 +1,467 @@
+//! A gadget verifying [Groth16] proofs inside a circuit, generic over the
+//! implementation of the pairing in the circuit.
+//!
+//! The pairing of an engine can be implemented natively by a circuit over
+//! the other curve of a cycle, or with non-native arithmetic as done by
+//! [`bls12_381::Bls12NonNative`].
+//!
+//! [Groth16]: https://eprint.iacr.org/2016/260
+
+pub mod bls12_381;
+
+use ff::ScalarEngine;
+
+use super::boolean::Boolean;
+use crate::bls::Engine;
+use crate::groth16::{Proof, VerifyingKey};
+use crate::{ConstraintSystem, SynthesisError};
+
+/// The groups of a pairing-friendly engine, and the check of a product of
+/// pairings, in a circuit over the scalar field of `E`.
+pub trait PairingGadget<E: ScalarEngine> {
+    /// The engine whose proofs are verified.
+    type Engine: Engine;
+    /// An allocated point of G1.
+    type G1: Clone;
+    /// An allocated point of G2.
+    type G2: Clone;
+
+    /// Allocates a point of G1, enforcing that it is on the curve and in
+    /// the prime order subgroup, as [`verify_proof`] is only sound for such
+    /// points. An implementation which skips the subgroup check must say so.
+    fn alloc_g1<CS>(
+        &self,
+        cs: CS,
+        value: Option<<Self::Engine as Engine>::G1Affine>,
+    ) -> Result<Self::G1, SynthesisError>
+    where
+        CS: ConstraintSystem<E>;
+
+    /// Allocates a point of G2, enforcing that it is on the curve and in
+    /// the prime order subgroup, as [`verify_proof`] is only sound for such
+    /// points. An implementation which skips the subgroup check must say so.
+    fn alloc_g2<CS>(
+        &self,
+        cs: CS,
+        value: Option<<Self::Engine as Engine>::G2Affine>,
+    ) -> Result<Self::G2, SynthesisError>
+    where
+        CS: ConstraintSystem<E>;
+
+    /// Adds two points of G1.
+    fn g1_add<CS>(&self, cs: CS, a: &Self::G1, b: &Self::G1) -> Result<Self::G1, SynthesisError>
+    where
+        CS: ConstraintSystem<E>;
+
+    /// Negates a point of G1.
+    fn g1_negate<CS>(&self, cs: CS, a: &Self::G1) -> Result<Self::G1, SynthesisError>
+    where
+        CS: ConstraintSystem<E>;
+
+    /// Multiplies a point of G1 by a scalar, given by its little-endian
+    /// bits.
+    fn g1_mul<CS>(
+        &self,
+        cs: CS,
+        base: &Self::G1,
+        scalar: &[Boolean],
+    ) -> Result<Self::G1, SynthesisError>
+    where
+        CS: ConstraintSystem<E>;
+
+    /// Enforces that the product of the pairings of `pairs` is the identity
+    /// of the target group.
+    fn enforce_pairing_product_is_one<CS>(
+        &self,
+        cs: CS,
+        pairs: &[(Self::G1, Self::G2)],
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>;
+}
+
+/// The parts of a [`VerifyingKey`] used by [`verify_proof`], allocated with
+/// a [`PairingGadget`].
+pub struct AllocatedVerifyingKey<E, P>
+where
+    E: ScalarEngine,
+    P: PairingGadget<E>,
+{
+    alpha_g1: P::G1,
+    beta_g2: P::G2,
+    gamma_g2: P::G2,
+    delta_g2: P::G2,
+    ic: Vec<P::G1>,
+}
+
+impl<E, P> AllocatedVerifyingKey<E, P>
+where
+    E: ScalarEngine,
+    P: PairingGadget<E>,
+{
+    /// Allocates a verifying key for `num_inputs` public inputs.
+    pub fn alloc<CS>(
+        mut cs: CS,
+        pairing: &P,
+        vk: Option<&VerifyingKey<P::Engine>>,
+        num_inputs: usize,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        if let Some(vk) = vk {
+            if vk.ic.len() != num_inputs + 1 {
+                return Err(SynthesisError::MalformedVerifyingKey);
+            }
+        }
+
+        let alpha_g1 = pairing.alloc_g1(cs.namespace(|| "alpha_g1"), vk.map(|vk| vk.alpha_g1))?;
+        let beta_g2 = pairing.alloc_g2(cs.namespace(|| "beta_g2"), vk.map(|vk| vk.beta_g2))?;
+        let gamma_g2 = pairing.alloc_g2(cs.namespace(|| "gamma_g2"), vk.map(|vk| vk.gamma_g2))?;
+        let delta_g2 = pairing.alloc_g2(cs.namespace(|| "delta_g2"), vk.map(|vk| vk.delta_g2))?;
+
+        let mut ic = Vec::with_capacity(num_inputs + 1);
+        for i in 0..=num_inputs {
+            ic.push(pairing.alloc_g1(cs.namespace(|| format!("ic {}", i)), vk.map(|vk| vk.ic[i]))?);
+        }
+
+        Ok(AllocatedVerifyingKey {
+            alpha_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g2,
+            ic,
+        })
+    }
+
+    /// Returns the number of public inputs of the key.
+    pub fn num_inputs(&self) -> usize {
+        self.ic.len() - 1
+    }
+}
+
+/// A [`Proof`], allocated with a [`PairingGadget`].
+pub struct AllocatedProof<E, P>
+where
+    E: ScalarEngine,
+    P: PairingGadget<E>,
+{
+    a: P::G1,
+    b: P::G2,
+    c: P::G1,
+}
+
+impl<E, P> AllocatedProof<E, P>
+where
+    E: ScalarEngine,
+    P: PairingGadget<E>,
+{
+    pub fn alloc<CS>(
+        mut cs: CS,
+        pairing: &P,
+        proof: Option<&Proof<P::Engine>>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        Ok(AllocatedProof {
+            a: pairing.alloc_g1(cs.namespace(|| "a"), proof.map(|proof| proof.a))?,
+            b: pairing.alloc_g2(cs.namespace(|| "b"), proof.map(|proof| proof.b))?,
+            c: pairing.alloc_g1(cs.namespace(|| "c"), proof.map(|proof| proof.c))?,
+        })
+    }
+}
+
+/// Enforces that `proof` is valid for `vk` and the public inputs, each
+/// given by its little-endian bits.
+///
+/// The points of the proof are allocated by `pairing`, which must check that
+/// they are in the prime order subgroups: otherwise a point with a component
+/// of small order may satisfy the pairing check of an invalid proof.
+pub fn verify_proof<E, P, CS>(
+    mut cs: CS,
+    pairing: &P,
+    vk: &AllocatedVerifyingKey<E, P>,
+    proof: &AllocatedProof<E, P>,
+    public_inputs: &[Vec<Boolean>],
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    P: PairingGadget<E>,
+    CS: ConstraintSystem<E>,
+{
+    if public_inputs.len() != vk.num_inputs() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+
+    let mut acc = vk.ic[0].clone();
+    for (i, (input, base)) in public_inputs.iter().zip(vk.ic.iter().skip(1)).enumerate() {
+        let mut cs = cs.namespace(|| format!("input {}", i));
+
+        let term = pairing.g1_mul(cs.namespace(|| "multiplication"), base, input)?;
+        acc = pairing.g1_add(cs.namespace(|| "accumulation"), &acc, &term)?;
+    }
+
+    // As in the native verifier, the equation
+    // e(A, B) = e(alpha, beta) * e(acc, gamma) * e(C, delta)
+    // is rearranged so that a single final exponentiation is needed.
+    let neg_acc = pairing.g1_negate(cs.namespace(|| "negate acc"), &acc)?;
+    let neg_c = pairing.g1_negate(cs.namespace(|| "negate c"), &proof.c)?;
+    let neg_alpha = pairing.g1_negate(cs.namespace(|| "negate alpha"), &vk.alpha_g1)?;
+
+    pairing.enforce_pairing_product_is_one(
+        cs.namespace(|| "pairing check"),
+        &[
+            (proof.a.clone(), proof.b.clone()),
+            (neg_acc, vk.gamma_g2.clone()),
+            (neg_c, vk.delta_g2.clone()),
+            (neg_alpha, vk.beta_g2.clone()),
+        ],
+    )
+}
+
+// The dummy engine is only built along with the groth16 tests
+#[cfg(all(test, not(feature = "gpu")))]
+mod test {
+    use super::*;
+    use crate::bls::Bls12;
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::nonnative::{NonNativeElement, NonNativeParams};
+    use crate::gadgets::test::*;
+    use crate::groth16::tests::dummy_engine::{DummyEngine, Fr as DummyFr};
+    use ff::{Field, PrimeField};
+    use num_bigint::BigUint;
+    use num_traits::Zero;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// The pairing of the dummy engine, whose groups are all its scalar
+    /// field, and whose pairing multiplies the scalars: a product of
+    /// pairings is one when the sum of the products of the scalars is zero.
+    struct DummyPairing {
+        params: NonNativeParams,
+    }
+
+    impl DummyPairing {
+        fn new() -> Self {
+            DummyPairing {
+                params: NonNativeParams::new(BigUint::from(64513u64), 16, 1),
+            }
+        }
+    }
+
+    fn to_biguint(value: DummyFr) -> BigUint {
+        BigUint::from(value.into_repr().as_ref()[0])
+    }
+
+    impl PairingGadget<Bls12> for DummyPairing {
+        type Engine = DummyEngine;
+        type G1 = NonNativeElement<Bls12>;
+        type G2 = NonNativeElement<Bls12>;
+
+        fn alloc_g1<CS>(&self, cs: CS, value: Option<DummyFr>) -> Result<Self::G1, SynthesisError>
+        where
+            CS: ConstraintSystem<Bls12>,
+        {
+            NonNativeElement::alloc(cs, value.map(to_biguint), &self.params)
+        }
+
+        fn alloc_g2<CS>(&self, cs: CS, value: Option<DummyFr>) -> Result<Self::G2, SynthesisError>
+        where
+            CS: ConstraintSystem<Bls12>,
+        {
+            NonNativeElement::alloc(cs, value.map(to_biguint), &self.params)
+        }
+
+        fn g1_add<CS>(&self, cs: CS, a: &Self::G1, b: &Self::G1) -> Result<Self::G1, SynthesisError>
+        where
+            CS: ConstraintSystem<Bls12>,
+        {
+            a.add(b).reduce(cs, &self.params)
+        }
+
+        fn g1_negate<CS>(&self, mut cs: CS, a: &Self::G1) -> Result<Self::G1, SynthesisError>
+        where
+            CS: ConstraintSystem<Bls12>,
+        {
+            let zero = NonNativeElement::constant(&BigUint::zero(), &self.params);
+            zero.sub(a, &self.params)
+                .reduce(cs.namespace(|| "reduction"), &self.params)
+        }
+
+        fn g1_mul<CS>(
+            &self,
+            mut cs: CS,
+            base: &Self::G1,
+            scalar: &[Boolean],
+        ) -> Result<Self::G1, SynthesisError>
+        where
+            CS: ConstraintSystem<Bls12>,
+        {
+            let mut acc = NonNativeElement::constant(&BigUint::zero(), &self.params);
+            for (i, bit) in scalar.iter().enumerate().rev() {
+                let doubled = acc
+                    .add(&acc)
+                    .reduce(cs.namespace(|| format!("doubling {}", i)), &self.params)?;
+                let sum = doubled
+                    .add(base)
+                    .reduce(cs.namespace(|| format!("addition {}", i)), &self.params)?;
+                acc = NonNativeElement::conditionally_select(
+                    cs.namespace(|| format!("selection {}", i)),
+                    &sum,
+                    &doubled,
+                    bit,
+                )?;
+            }
+
+            Ok(acc)
+        }
+
+        fn enforce_pairing_product_is_one<CS>(
+            &self,
+            mut cs: CS,
+            pairs: &[(Self::G1, Self::G2)],
+        ) -> Result<(), SynthesisError>
+        where
+            CS: ConstraintSystem<Bls12>,
+        {
+            let zero = NonNativeElement::constant(&BigUint::zero(), &self.params);
+            let mut sum = zero.clone();
+            for (i, (a, b)) in pairs.iter().enumerate() {
+                let product =
+                    a.mul_without_reduction(cs.namespace(|| format!("pairing {}", i)), b)?;
+                sum = sum.add(&product);
+            }
+
+            NonNativeElement::enforce_equal(cs.namespace(|| "sum check"), &sum, &zero, &self.params)
+        }
+    }
+
+    fn alloc_input<CS: ConstraintSystem<Bls12>>(mut cs: CS, value: DummyFr) -> Vec<Boolean> {
+        let value = to_biguint(value);
+        (0..16)
+            .map(|i| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(value.bit(i)))
+                        .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_proof() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let pairing = DummyPairing::new();
+
+        for _ in 0..10 {
+            let alpha = DummyFr::random(&mut rng);
+            let beta = DummyFr::random(&mut rng);
+            let gamma = DummyFr::random(&mut rng);
+            let delta = DummyFr::random(&mut rng);
+            let ic = vec![
+                DummyFr::random(&mut rng),
+                DummyFr::random(&mut rng),
+                DummyFr::random(&mut rng),
+            ];
+            let vk = VerifyingKey::<DummyEngine> {
+                alpha_g1: alpha,
+                beta_g1: beta,
+                beta_g2: beta,
+                gamma_g2: gamma,
+                delta_g1: delta,
+                delta_g2: delta,
+                ic: ic.clone(),
+            };
+
+            let inputs = vec![DummyFr::random(&mut rng), DummyFr::random(&mut rng)];
+
+            // A * B = alpha * beta + (ic_0 + x_1 * ic_1 + x_2 * ic_2) * gamma + C * delta
+            let a = DummyFr::random(&mut rng);
+            let b = DummyFr::random(&mut rng);
+            let mut acc = ic[0];
+            for (input, ic) in inputs.iter().zip(ic.iter().skip(1)) {
+                let mut term = *ic;
+                term.mul_assign(input);
+                acc.add_assign(&term);
+            }
+            acc.mul_assign(&gamma);
+            let mut c = a;
+            c.mul_assign(&b);
+            let mut alpha_beta = alpha;
+            alpha_beta.mul_assign(&beta);
+            c.sub_assign(&alpha_beta);
+            c.sub_assign(&acc);
+            c.mul_assign(&delta.inverse().unwrap());
+            let proof = Proof::<DummyEngine> { a, b, c };
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let vk_var =
+                AllocatedVerifyingKey::alloc(cs.namespace(|| "vk"), &pairing, Some(&vk), 2)
+                    .unwrap();
+            let proof_var =
+                AllocatedProof::alloc(cs.namespace(|| "proof"), &pairing, Some(&proof)).unwrap();
+            let input_vars: Vec<Vec<Boolean>> = inputs
+                .iter()
+                .enumerate()
+                .map(|(i, input)| alloc_input(cs.namespace(|| format!("input {}", i)), *input))
+                .collect();
+
+            verify_proof(
+                cs.namespace(|| "verify"),
+                &pairing,
+                &vk_var,
+                &proof_var,
+                &input_vars,
+            )
+            .unwrap();
+            assert!(cs.is_satisfied());
+
+            // A different public input
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let vk_var =
+                AllocatedVerifyingKey::alloc(cs.namespace(|| "vk"), &pairing, Some(&vk), 2)
+                    .unwrap();
+            let proof_var =
+                AllocatedProof::alloc(cs.namespace(|| "proof"), &pairing, Some(&proof)).unwrap();
+            let mut wrong_input = inputs[1];
+            wrong_input.add_assign(&DummyFr::one());
+            let input_vars = vec![
+                alloc_input(cs.namespace(|| "input 0"), inputs[0]),
+                alloc_input(cs.namespace(|| "input 1"), wrong_input),
+            ];
+
+            verify_proof(
+                cs.namespace(|| "verify"),
+                &pairing,
+                &vk_var,
+                &proof_var,
+                &input_vars,
+            )
+            .unwrap();
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_verify_proof_malformed_key() {
+        let pairing = DummyPairing::new();
+        let vk = VerifyingKey::<DummyEngine> {
+            alpha_g1: DummyFr::one(),
+            beta_g1: DummyFr::one(),
+            beta_g2: DummyFr::one(),
+            gamma_g2: DummyFr::one(),
+            delta_g1: DummyFr::one(),
+            delta_g2: DummyFr::one(),
+            ic: vec![DummyFr::one(); 2],
+        };
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        assert!(AllocatedVerifyingKey::alloc(&mut cs, &pairing, Some(&vk), 2).is_err());
+    }
+}
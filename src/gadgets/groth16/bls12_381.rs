@@ -0,0 +1,1854 @@
+//! A [`PairingGadget`] for BLS12-381 in a circuit over any scalar field,
+//! emulating the base field of the curve with non-native arithmetic.
+//!
+//! Fq12 is represented as `Fq[w] / (w^12 - 2 w^6 + 2)`, which flattens the
+//! usual tower `Fq2[v, w] / (u^2 + 1, v^3 - (u + 1), w^2 - v)` with
+//! `u = w^6 - 1` and `v = w^2`. Points of G2 are untwisted into E(Fq12) by
+//! `(x, y) -> (x / w^2, y / w^3)`, so that the Miller loop only deals with
+//! affine points over Fq12, whose slopes are witnessed by the prover.
+//!
+//! Every multiplication in Fq12 costs 144 non-native products, and the hard
+//! part of the final exponentiation is a square-and-multiply over a
+//! 1269-bit exponent: a pairing check costs on the order of 10^8
+//! constraints. This backend is a reference for BLS12-381 rather than a
+//! practical way to recurse, which rather calls for a curve cycle.
+
+use ff::ScalarEngine;
+use groupy::{CurveAffine, EncodedPoint};
+use num_bigint::BigUint;
+use num_traits::{Num, One, Zero};
+
+use super::PairingGadget;
+use crate::bls::{Bls12, G1Affine, G1Uncompressed, G2Affine};
+use crate::gadgets::boolean::Boolean;
+use crate::gadgets::nonnative::{NonNativeElement, NonNativeParams};
+use crate::{ConstraintSystem, SynthesisError};
+
+/// The modulus of the base field.
+const FQ_MODULUS: &str = "1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab";
+
+/// The order of G1 and G2.
+const R_MODULUS: &str = "73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001";
+
+/// The absolute value of the parameter of the curve, which is negative.
+const BLS_X: u64 = 0xd201_0000_0001_0000;
+
+/// The coefficient `3 b` of the complete addition formulas on G1.
+const G1_B3: u64 = 12;
+
+/// An element of Fq12, by its coefficients in the basis `1, w, ..., w^11`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Fq12Value(Vec<BigUint>);
+
+impl Fq12Value {
+    fn from_fq(value: BigUint) -> Self {
+        let mut coeffs = vec![BigUint::zero(); 12];
+        coeffs[0] = value;
+        Fq12Value(coeffs)
+    }
+
+    /// Returns `c0 + c1 u`.
+    fn from_fq2(c0: &BigUint, c1: &BigUint, q: &BigUint) -> Self {
+        let mut coeffs = vec![BigUint::zero(); 12];
+        coeffs[0] = (c0 + q - c1) % q;
+        coeffs[6] = c1.clone();
+        Fq12Value(coeffs)
+    }
+
+    fn one() -> Self {
+        Self::from_fq(BigUint::one())
+    }
+
+    /// Returns `w^k`, for `k < 12`.
+    fn w_pow(k: usize) -> Self {
+        let mut coeffs = vec![BigUint::zero(); 12];
+        coeffs[k] = BigUint::one();
+        Fq12Value(coeffs)
+    }
+
+    fn add(&self, other: &Self, q: &BigUint) -> Self {
+        Fq12Value(
+            self.0
+                .iter()
+                .zip(other.0.iter())
+                .map(|(a, b)| (a + b) % q)
+                .collect(),
+        )
+    }
+
+    fn mul(&self, other: &Self, q: &BigUint) -> Self {
+        let mut coeffs = vec![BigUint::zero(); 23];
+        for (i, a) in self.0.iter().enumerate() {
+            for (j, b) in other.0.iter().enumerate() {
+                coeffs[i + j] += a * b;
+            }
+        }
+
+        // w^k = 2 w^(k - 6) - 2 w^(k - 12)
+        for k in (12..23).rev() {
+            let twice = (&coeffs[k] << 1) % q;
+            coeffs[k - 6] += &twice;
+            coeffs[k - 12] += q - twice;
+        }
+        coeffs.truncate(12);
+
+        Fq12Value(coeffs.into_iter().map(|coeff| coeff % q).collect())
+    }
+
+    fn pow(&self, exponent: &BigUint, q: &BigUint) -> Self {
+        let mut res = Self::one();
+        for i in (0..exponent.bits()).rev() {
+            res = res.mul(&res, q);
+            if exponent.bit(i) {
+                res = res.mul(self, q);
+            }
+        }
+
+        res
+    }
+
+    /// Returns the inverse of the element, by solving the linear system of
+    /// the multiplication by the element.
+    fn inverse(&self, q: &BigUint) -> Option<Self> {
+        let columns: Vec<Fq12Value> = (0..12).map(|j| self.mul(&Self::w_pow(j), q)).collect();
+        let mut rows: Vec<Vec<BigUint>> = (0..12)
+            .map(|i| {
+                let mut row: Vec<BigUint> = columns.iter().map(|c| c.0[i].clone()).collect();
+                row.push(if i == 0 {
+                    BigUint::one()
+                } else {
+                    BigUint::zero()
+                });
+                row
+            })
+            .collect();
+
+        for col in 0..12 {
+            let pivot = (col..12).find(|i| !rows[*i][col].is_zero())?;
+            rows.swap(col, pivot);
+
+            let inverse = fq_inverse(&rows[col][col], q);
+            for value in rows[col].iter_mut() {
+                *value = &*value * &inverse % q;
+            }
+
+            for i in 0..12 {
+                if i == col || rows[i][col].is_zero() {
+                    continue;
+                }
+                let factor = rows[i][col].clone();
+                for j in col..13 {
+                    let sub = &factor * &rows[col][j] % q;
+                    rows[i][j] = (&rows[i][j] + q - sub) % q;
+                }
+            }
+        }
+
+        Some(Fq12Value(
+            rows.into_iter().map(|mut row| row.pop().unwrap()).collect(),
+        ))
+    }
+
+    /// Returns the images of `1, w, ..., w^11` by `x -> x^exponent`, which
+    /// must be a power of q, so that this map is linear over Fq.
+    fn frobenius_images(exponent: &BigUint, q: &BigUint) -> Vec<Self> {
+        let image = Self::w_pow(1).pow(exponent, q);
+        (0..12)
+            .scan(Self::one(), |power, _| {
+                let current = power.clone();
+                *power = power.mul(&image, q);
+                Some(current)
+            })
+            .collect()
+    }
+}
+
+fn fq_inverse(value: &BigUint, q: &BigUint) -> BigUint {
+    value.modpow(&(q - 2u64), q)
+}
+
+/// Returns the affine coordinates of a point of G1, which must not be the
+/// identity.
+fn g1_coordinates(p: &G1Affine) -> (BigUint, BigUint) {
+    let mut bytes = p.into_uncompressed().as_ref().to_vec();
+    // Clear the flags
+    bytes[0] &= 0x1f;
+
+    (
+        BigUint::from_bytes_be(&bytes[..48]),
+        BigUint::from_bytes_be(&bytes[48..]),
+    )
+}
+
+fn g1_from_coordinates(x: &BigUint, y: &BigUint) -> Option<G1Affine> {
+    let mut repr = G1Uncompressed::empty();
+    for (coordinate, bytes) in [x, y].iter().zip(repr.as_mut().chunks_mut(48)) {
+        let coordinate = coordinate.to_bytes_be();
+        bytes[48 - coordinate.len()..].copy_from_slice(&coordinate);
+    }
+
+    repr.into_affine().ok()
+}
+
+/// Returns the coordinates `x.c0, x.c1, y.c0, y.c1` of a point of G2, which
+/// must not be the identity.
+fn g2_coordinates(p: &G2Affine) -> Vec<BigUint> {
+    let mut bytes = p.into_uncompressed().as_ref().to_vec();
+    // Clear the flags
+    bytes[0] &= 0x1f;
+
+    // Each coordinate is serialized as c1 followed by c0
+    [48, 0, 144, 96]
+        .iter()
+        .map(|offset| BigUint::from_bytes_be(&bytes[*offset..*offset + 48]))
+        .collect()
+}
+
+/// A point of G1 in projective coordinates, over the non-native base
+/// field.
+pub struct G1Var<E: ScalarEngine> {
+    x: NonNativeElement<E>,
+    y: NonNativeElement<E>,
+    z: NonNativeElement<E>,
+}
+
+impl<E: ScalarEngine> Clone for G1Var<E> {
+    fn clone(&self) -> Self {
+        G1Var {
+            x: self.x.clone(),
+            y: self.y.clone(),
+            z: self.z.clone(),
+        }
+    }
+}
+
+impl<E: ScalarEngine> G1Var<E> {
+    pub fn get_value(&self, params: &Bls12NonNative) -> Option<G1Affine> {
+        let q = params.fq.modulus();
+        let x = self.x.get_value(&params.fq)?;
+        let y = self.y.get_value(&params.fq)?;
+        let z = self.z.get_value(&params.fq)?;
+
+        if z.is_zero() {
+            return Some(G1Affine::zero());
+        }
+
+        let z_inverse = fq_inverse(&z, q);
+        g1_from_coordinates(&(x * &z_inverse % q), &(y * &z_inverse % q))
+    }
+
+    /// Returns `a` if `condition` is true, and `b` otherwise.
+    fn conditionally_select<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        Ok(G1Var {
+            x: NonNativeElement::conditionally_select(cs.namespace(|| "x"), &a.x, &b.x, condition)?,
+            y: NonNativeElement::conditionally_select(cs.namespace(|| "y"), &a.y, &b.y, condition)?,
+            z: NonNativeElement::conditionally_select(cs.namespace(|| "z"), &a.z, &b.z, condition)?,
+        })
+    }
+}
+
+/// A point of G2, untwisted into E(Fq12), in affine coordinates.
+pub struct G2Var<E: ScalarEngine> {
+    x: Fq12Var<E>,
+    y: Fq12Var<E>,
+}
+
+impl<E: ScalarEngine> Clone for G2Var<E> {
+    fn clone(&self) -> Self {
+        G2Var {
+            x: self.x.clone(),
+            y: self.y.clone(),
+        }
+    }
+}
+
+/// An element of Fq12, by its coefficients in the basis `1, w, ..., w^11`.
+struct Fq12Var<E: ScalarEngine> {
+    coeffs: Vec<NonNativeElement<E>>,
+}
+
+impl<E: ScalarEngine> Clone for Fq12Var<E> {
+    fn clone(&self) -> Self {
+        Fq12Var {
+            coeffs: self.coeffs.clone(),
+        }
+    }
+}
+
+impl<E: ScalarEngine> Fq12Var<E> {
+    fn alloc<CS>(
+        mut cs: CS,
+        value: Option<&Fq12Value>,
+        params: &NonNativeParams,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut coeffs = Vec::with_capacity(12);
+        for i in 0..12 {
+            coeffs.push(NonNativeElement::alloc(
+                cs.namespace(|| format!("coefficient {}", i)),
+                value.map(|value| value.0[i].clone()),
+                params,
+            )?);
+        }
+
+        Ok(Fq12Var { coeffs })
+    }
+
+    fn constant(value: &Fq12Value, params: &NonNativeParams) -> Self {
+        Fq12Var {
+            coeffs: value
+                .0
+                .iter()
+                .map(|coeff| NonNativeElement::constant(coeff, params))
+                .collect(),
+        }
+    }
+
+    /// Embeds an element of Fq.
+    fn from_fq(value: &NonNativeElement<E>, params: &NonNativeParams) -> Self {
+        let mut res = Self::constant(&Fq12Value::from_fq(BigUint::zero()), params);
+        res.coeffs[0] = value.clone();
+        res
+    }
+
+    /// Embeds `c0 + c1 u`, with `u = w^6 - 1`.
+    fn from_fq2(
+        c0: &NonNativeElement<E>,
+        c1: &NonNativeElement<E>,
+        params: &NonNativeParams,
+    ) -> Self {
+        let mut res = Self::constant(&Fq12Value::from_fq(BigUint::zero()), params);
+        res.coeffs[0] = c0.sub(c1, params);
+        res.coeffs[6] = c1.clone();
+        res
+    }
+
+    fn get_value(&self, params: &NonNativeParams) -> Option<Fq12Value> {
+        self.coeffs
+            .iter()
+            .map(|coeff| coeff.get_value(params))
+            .collect::<Option<Vec<_>>>()
+            .map(Fq12Value)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Fq12Var {
+            coeffs: self
+                .coeffs
+                .iter()
+                .zip(other.coeffs.iter())
+                .map(|(a, b)| a.add(b))
+                .collect(),
+        }
+    }
+
+    fn sub(&self, other: &Self, params: &NonNativeParams) -> Self {
+        Fq12Var {
+            coeffs: self
+                .coeffs
+                .iter()
+                .zip(other.coeffs.iter())
+                .map(|(a, b)| a.sub(b, params))
+                .collect(),
+        }
+    }
+
+    fn reduce<CS>(&self, mut cs: CS, params: &NonNativeParams) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut coeffs = Vec::with_capacity(12);
+        for (i, coeff) in self.coeffs.iter().enumerate() {
+            coeffs.push(coeff.reduce(cs.namespace(|| format!("coefficient {}", i)), params)?);
+        }
+
+        Ok(Fq12Var { coeffs })
+    }
+
+    /// Multiplies the elements, without reducing the coefficients of the
+    /// product.
+    fn mul_without_reduction<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        params: &NonNativeParams,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut products: Vec<Option<NonNativeElement<E>>> = (0..23).map(|_| None).collect();
+        for (i, a) in self.coeffs.iter().enumerate() {
+            for (j, b) in other.coeffs.iter().enumerate() {
+                let product =
+                    a.mul_without_reduction(cs.namespace(|| format!("product {} {}", i, j)), b)?;
+                products[i + j] = Some(match products[i + j].take() {
+                    Some(sum) => sum.add(&product),
+                    None => product,
+                });
+            }
+        }
+
+        // w^k = 2 w^(k - 6) - 2 w^(k - 12)
+        let mut coeffs: Vec<NonNativeElement<E>> =
+            products.into_iter().map(|coeff| coeff.unwrap()).collect();
+        for k in (12..23).rev() {
+            let twice = coeffs[k].add(&coeffs[k]);
+            coeffs[k - 6] = coeffs[k - 6].add(&twice);
+            coeffs[k - 12] = coeffs[k - 12].sub(&twice, params);
+        }
+        coeffs.truncate(12);
+
+        Ok(Fq12Var { coeffs })
+    }
+
+    fn mul<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        params: &NonNativeParams,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        self.mul_without_reduction(cs.namespace(|| "product"), other, params)?
+            .reduce(cs.namespace(|| "reduction"), params)
+    }
+
+    /// Applies the Fq-linear map sending `w^j` to `images[j]`, and reduces
+    /// the result. This does not need any product.
+    fn apply_linear_map<CS>(
+        &self,
+        mut cs: CS,
+        images: &[Fq12Value],
+        params: &NonNativeParams,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut coeffs = Vec::with_capacity(12);
+        for i in 0..12 {
+            let mut coeff: Option<NonNativeElement<E>> = None;
+            for (value, image) in self.coeffs.iter().zip(images.iter()) {
+                if image.0[i].is_zero() {
+                    continue;
+                }
+                let term = value.mul_by_constant_without_reduction(&image.0[i], params);
+                coeff = Some(match coeff {
+                    Some(coeff) => coeff.add(&term),
+                    None => term,
+                });
+            }
+
+            coeffs.push(match coeff {
+                Some(coeff) => {
+                    coeff.reduce(cs.namespace(|| format!("coefficient {}", i)), params)?
+                }
+                None => NonNativeElement::constant(&BigUint::zero(), params),
+            });
+        }
+
+        Ok(Fq12Var { coeffs })
+    }
+
+    fn mul_by_constant<CS>(
+        &self,
+        cs: CS,
+        constant: &Fq12Value,
+        params: &NonNativeParams,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let q = params.modulus();
+        let images: Vec<Fq12Value> = (0..12)
+            .map(|j| constant.mul(&Fq12Value::w_pow(j), q))
+            .collect();
+
+        self.apply_linear_map(cs, &images, params)
+    }
+
+    /// Allocates the inverse of the element, which enforces that the element
+    /// is not zero.
+    fn inverse<CS>(&self, mut cs: CS, params: &NonNativeParams) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let inverse_value = match self.get_value(params) {
+            Some(value) => Some(
+                value
+                    .inverse(params.modulus())
+                    .ok_or(SynthesisError::DivisionByZero)?,
+            ),
+            None => None,
+        };
+        let inverse = Fq12Var::alloc(cs.namespace(|| "inverse"), inverse_value.as_ref(), params)?;
+
+        let product = self.mul_without_reduction(cs.namespace(|| "product"), &inverse, params)?;
+        let one = Fq12Var::constant(&Fq12Value::one(), params);
+        Fq12Var::enforce_equal(cs.namespace(|| "inverse check"), &product, &one, params)?;
+
+        Ok(inverse)
+    }
+
+    /// Enforces that the elements are equal.
+    fn enforce_equal<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        params: &NonNativeParams,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        for (i, (a, b)) in a.coeffs.iter().zip(b.coeffs.iter()).enumerate() {
+            NonNativeElement::enforce_equal(
+                cs.namespace(|| format!("coefficient {}", i)),
+                a,
+                b,
+                params,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The pairing of BLS12-381, over non-native arithmetic with 6 limbs of
+/// 64 bits for the base field.
+///
+/// Points are checked to be on the curve and, unless built by
+/// [`without_subgroup_checks`](Self::without_subgroup_checks), to be in the
+/// prime order subgroup when allocated. The identity cannot be allocated.
+pub struct Bls12NonNative {
+    fq: NonNativeParams,
+    subgroup_checks: bool,
+    // The order of G1 and G2
+    r: BigUint,
+    // x -> x^q over Fq12
+    frobenius_1: Vec<Fq12Value>,
+    // x -> x^(q^2) and x -> x^(q^6) over Fq12
+    frobenius_2: Vec<Fq12Value>,
+    frobenius_6: Vec<Fq12Value>,
+    // 1 / w^2 and 1 / w^3
+    untwist_x: Fq12Value,
+    untwist_y: Fq12Value,
+    // (q^4 - q^2 + 1) / r
+    hard_exponent: BigUint,
+}
+
+impl Bls12NonNative {
+    /// Returns the pairing gadget, which checks that allocated points are
+    /// in the prime order subgroups.
+    pub fn new() -> Self {
+        Self::with_subgroup_checks(true)
+    }
+
+    /// Returns the pairing gadget without the subgroup checks of allocated
+    /// points, which cost millions of constraints per point. The points must
+    /// then be known to be in the prime order subgroups by other means, as
+    /// the pairing check is not sound otherwise.
+    pub fn without_subgroup_checks() -> Self {
+        Self::with_subgroup_checks(false)
+    }
+
+    fn with_subgroup_checks(subgroup_checks: bool) -> Self {
+        let q = BigUint::from_str_radix(FQ_MODULUS, 16).unwrap();
+        let r = BigUint::from_str_radix(R_MODULUS, 16).unwrap();
+
+        let q2 = &q * &q;
+        let q4 = &q2 * &q2;
+        let q6 = &q4 * &q2;
+        let hard_exponent = (q4 + 1u64 - &q2) / &r;
+
+        let untwist_x = Fq12Value::w_pow(2).inverse(&q).unwrap();
+        let untwist_y = Fq12Value::w_pow(3).inverse(&q).unwrap();
+
+        Bls12NonNative {
+            subgroup_checks,
+            r,
+            frobenius_1: Fq12Value::frobenius_images(&q, &q),
+            frobenius_2: Fq12Value::frobenius_images(&q2, &q),
+            frobenius_6: Fq12Value::frobenius_images(&q6, &q),
+            untwist_x,
+            untwist_y,
+            hard_exponent,
+            fq: NonNativeParams::new(q, 64, 6),
+        }
+    }
+
+    /// Returns the parameters of the non-native base field.
+    pub fn fq_params(&self) -> &NonNativeParams {
+        &self.fq
+    }
+
+    fn g1_identity<E: ScalarEngine>(&self) -> G1Var<E> {
+        G1Var {
+            x: NonNativeElement::constant(&BigUint::zero(), &self.fq),
+            y: NonNativeElement::constant(&BigUint::one(), &self.fq),
+            z: NonNativeElement::constant(&BigUint::zero(), &self.fq),
+        }
+    }
+
+    /// Returns the affine coordinates of a point, which must not be the
+    /// identity.
+    fn g1_to_affine<E, CS>(
+        &self,
+        mut cs: CS,
+        p: &G1Var<E>,
+    ) -> Result<(NonNativeElement<E>, NonNativeElement<E>), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let fq = &self.fq;
+
+        let z_inverse_value = match p.z.get_value(fq) {
+            Some(ref z) if z.is_zero() => return Err(SynthesisError::UnexpectedIdentity),
+            Some(z) => Some(fq_inverse(&z, fq.modulus())),
+            None => None,
+        };
+        let z_inverse = NonNativeElement::alloc(cs.namespace(|| "z inverse"), z_inverse_value, fq)?;
+
+        let product =
+            p.z.mul_without_reduction(cs.namespace(|| "z * z inverse"), &z_inverse)?;
+        let one = NonNativeElement::constant(&BigUint::one(), fq);
+        NonNativeElement::enforce_equal(cs.namespace(|| "inverse check"), &product, &one, fq)?;
+
+        let x = p.x.mul(cs.namespace(|| "x"), &z_inverse, fq)?;
+        let y = p.y.mul(cs.namespace(|| "y"), &z_inverse, fq)?;
+
+        Ok((x, y))
+    }
+
+    /// Computes the tangent at `t` evaluated at `p`, and the double of `t`.
+    fn doubling_step<E, CS>(
+        &self,
+        mut cs: CS,
+        t: &G2Var<E>,
+        p: &(Fq12Var<E>, Fq12Var<E>),
+    ) -> Result<(Fq12Var<E>, G2Var<E>), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let (lambda, doubled) = self.g2_double(&mut cs, t)?;
+        let line = self.line(&mut cs, &lambda, t, p)?;
+
+        Ok((line, doubled))
+    }
+
+    /// Computes the line through `t` and `r` evaluated at `p`, and the sum of
+    /// `t` and `r`.
+    fn addition_step<E, CS>(
+        &self,
+        mut cs: CS,
+        t: &G2Var<E>,
+        r: &G2Var<E>,
+        p: &(Fq12Var<E>, Fq12Var<E>),
+    ) -> Result<(Fq12Var<E>, G2Var<E>), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let (lambda, sum) = self.g2_add(&mut cs, t, r)?;
+        let line = self.line(&mut cs, &lambda, t, p)?;
+
+        Ok((line, sum))
+    }
+
+    /// Computes the slope of the tangent at `t`, and the double of `t`.
+    fn g2_double<E, CS>(
+        &self,
+        mut cs: CS,
+        t: &G2Var<E>,
+    ) -> Result<(Fq12Var<E>, G2Var<E>), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let fq = &self.fq;
+        let q = fq.modulus();
+
+        let x_squared = t.x.mul(cs.namespace(|| "x^2"), &t.x, fq)?;
+        let three_x_squared = x_squared.add(&x_squared).add(&x_squared);
+        let two_y = t.y.add(&t.y);
+
+        // lambda = 3 x^2 / (2 y)
+        let lambda_value = match (three_x_squared.get_value(fq), two_y.get_value(fq)) {
+            (Some(numerator), Some(denominator)) => {
+                let inverse = denominator
+                    .inverse(q)
+                    .ok_or(SynthesisError::DivisionByZero)?;
+                Some(numerator.mul(&inverse, q))
+            }
+            _ => None,
+        };
+        let lambda = Fq12Var::alloc(cs.namespace(|| "lambda"), lambda_value.as_ref(), fq)?;
+
+        let product = lambda.mul_without_reduction(cs.namespace(|| "lambda * 2y"), &two_y, fq)?;
+        Fq12Var::enforce_equal(
+            cs.namespace(|| "lambda check"),
+            &product,
+            &three_x_squared,
+            fq,
+        )?;
+
+        let doubled = self.third_point(cs, &lambda, t, &t.x)?;
+
+        Ok((lambda, doubled))
+    }
+
+    /// Computes the slope of the line through `t` and `r`, and the sum of
+    /// `t` and `r`. If `t = r`, the slope is not constrained.
+    fn g2_add<E, CS>(
+        &self,
+        mut cs: CS,
+        t: &G2Var<E>,
+        r: &G2Var<E>,
+    ) -> Result<(Fq12Var<E>, G2Var<E>), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let fq = &self.fq;
+        let q = fq.modulus();
+
+        let dx = r.x.sub(&t.x, fq);
+        let dy = r.y.sub(&t.y, fq);
+
+        // lambda = (y_r - y_t) / (x_r - x_t)
+        let lambda_value = match (dy.get_value(fq), dx.get_value(fq)) {
+            (Some(numerator), Some(denominator)) => {
+                let inverse = denominator
+                    .inverse(q)
+                    .ok_or(SynthesisError::DivisionByZero)?;
+                Some(numerator.mul(&inverse, q))
+            }
+            _ => None,
+        };
+        let lambda = Fq12Var::alloc(cs.namespace(|| "lambda"), lambda_value.as_ref(), fq)?;
+
+        let product = lambda.mul_without_reduction(cs.namespace(|| "lambda * dx"), &dx, fq)?;
+        Fq12Var::enforce_equal(cs.namespace(|| "lambda check"), &product, &dy, fq)?;
+
+        let sum = self.third_point(cs, &lambda, t, &r.x)?;
+
+        Ok((lambda, sum))
+    }
+
+    /// Given the slope of the line through `t` and a point of abscissa
+    /// `other_x`, computes the third point of the line, negated.
+    fn third_point<E, CS>(
+        &self,
+        mut cs: CS,
+        lambda: &Fq12Var<E>,
+        t: &G2Var<E>,
+        other_x: &Fq12Var<E>,
+    ) -> Result<G2Var<E>, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let fq = &self.fq;
+
+        // x3 = lambda^2 - x_t - other_x
+        let lambda_squared =
+            lambda.mul_without_reduction(cs.namespace(|| "lambda^2"), lambda, fq)?;
+        let x = lambda_squared
+            .sub(&t.x.add(other_x), fq)
+            .reduce(cs.namespace(|| "x"), fq)?;
+
+        // y3 = lambda (x_t - x3) - y_t
+        let dx = t.x.sub(&x, fq);
+        let y = lambda
+            .mul_without_reduction(cs.namespace(|| "lambda * (x_t - x3)"), &dx, fq)?
+            .sub(&t.y, fq)
+            .reduce(cs.namespace(|| "y"), fq)?;
+
+        Ok(G2Var { x, y })
+    }
+
+    /// Evaluates at `p` the line of slope `lambda` through `t`. Vertical
+    /// lines are omitted, as they are eliminated by the final
+    /// exponentiation.
+    fn line<E, CS>(
+        &self,
+        mut cs: CS,
+        lambda: &Fq12Var<E>,
+        t: &G2Var<E>,
+        p: &(Fq12Var<E>, Fq12Var<E>),
+    ) -> Result<Fq12Var<E>, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let fq = &self.fq;
+
+        // line = (y_p - y_t) - lambda (x_p - x_t)
+        let dx = p.0.sub(&t.x, fq);
+        let slope_term = lambda.mul(cs.namespace(|| "lambda * (x_p - x_t)"), &dx, fq)?;
+
+        Ok(p.1.sub(&t.y, fq).sub(&slope_term, fq))
+    }
+
+    /// Enforces that `f^((q^12 - 1) / r)` is one.
+    fn enforce_final_exponentiation_is_one<E, CS>(
+        &self,
+        mut cs: CS,
+        f: &Fq12Var<E>,
+    ) -> Result<(), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let fq = &self.fq;
+
+        let f_inverse = f.inverse(cs.namespace(|| "f inverse"), fq)?;
+
+        // Easy part: f^((q^6 - 1) (q^2 + 1))
+        let f = f
+            .apply_linear_map(cs.namespace(|| "f^(q^6)"), &self.frobenius_6, fq)?
+            .mul(cs.namespace(|| "f^(q^6 - 1)"), &f_inverse, fq)?;
+        let f = f
+            .apply_linear_map(cs.namespace(|| "f^((q^6 - 1) q^2)"), &self.frobenius_2, fq)?
+            .mul(cs.namespace(|| "f^((q^6 - 1) (q^2 + 1))"), &f, fq)?;
+
+        // Hard part: f^((q^4 - q^2 + 1) / r)
+        let mut result = f.clone();
+        for i in (0..self.hard_exponent.bits() - 1).rev() {
+            result = result.mul(cs.namespace(|| format!("square {}", i)), &result, fq)?;
+            if self.hard_exponent.bit(i) {
+                result = result.mul(cs.namespace(|| format!("multiply {}", i)), &f, fq)?;
+            }
+        }
+
+        let one = Fq12Var::constant(&Fq12Value::one(), fq);
+        Fq12Var::enforce_equal(cs.namespace(|| "result check"), &result, &one, fq)
+    }
+
+    /// Computes the product of the Miller loops of `pairs` over the bits of
+    /// `x` down to `last_bit`, which is `0` for the full loop. Returns `None`
+    /// if there are no pairs.
+    fn miller_loop<E, CS>(
+        &self,
+        mut cs: CS,
+        pairs: &[(G1Var<E>, G2Var<E>)],
+        last_bit: usize,
+    ) -> Result<Option<Fq12Var<E>>, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let fq = &self.fq;
+
+        let mut points = Vec::with_capacity(pairs.len());
+        for (i, (p, _)) in pairs.iter().enumerate() {
+            let (x, y) = self.g1_to_affine(cs.namespace(|| format!("affine {}", i)), p)?;
+            points.push((Fq12Var::from_fq(&x, fq), Fq12Var::from_fq(&y, fq)));
+        }
+
+        let mut ts: Vec<G2Var<E>> = pairs.iter().map(|(_, q)| q.clone()).collect();
+        let mut f: Option<Fq12Var<E>> = None;
+
+        for i in (last_bit..63).rev() {
+            let mut cs = cs.namespace(|| format!("miller loop bit {}", i));
+
+            f = match f {
+                Some(f) => Some(f.mul(cs.namespace(|| "square"), &f, fq)?),
+                None => None,
+            };
+
+            for (j, p) in points.iter().enumerate() {
+                let (line, t) =
+                    self.doubling_step(cs.namespace(|| format!("doubling {}", j)), &ts[j], p)?;
+                ts[j] = t;
+                f = Some(match f {
+                    Some(f) => f.mul(cs.namespace(|| format!("doubling line {}", j)), &line, fq)?,
+                    None => line,
+                });
+            }
+
+            if (BLS_X >> i) & 1 == 1 {
+                for (j, (p, (_, q))) in points.iter().zip(pairs.iter()).enumerate() {
+                    let (line, t) = self.addition_step(
+                        cs.namespace(|| format!("addition {}", j)),
+                        &ts[j],
+                        q,
+                        p,
+                    )?;
+                    ts[j] = t;
+                    f = Some(match f {
+                        Some(f) => {
+                            f.mul(cs.namespace(|| format!("addition line {}", j)), &line, fq)?
+                        }
+                        None => line,
+                    });
+                }
+            }
+        }
+
+        Ok(f)
+    }
+
+    /// Enforces that a point of G1 is in the prime order subgroup, by
+    /// checking that `[r] p` is the identity with the complete formulas.
+    fn enforce_g1_subgroup<E, CS>(&self, mut cs: CS, p: &G1Var<E>) -> Result<(), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let mut acc = p.clone();
+        for i in (0..self.r.bits() - 1).rev() {
+            acc = PairingGadget::<E>::g1_add(
+                self,
+                cs.namespace(|| format!("doubling {}", i)),
+                &acc,
+                &acc,
+            )?;
+            if self.r.bit(i) {
+                acc = PairingGadget::<E>::g1_add(
+                    self,
+                    cs.namespace(|| format!("addition {}", i)),
+                    &acc,
+                    p,
+                )?;
+            }
+        }
+
+        // The identity is the only point of the curve with z = 0
+        let zero = NonNativeElement::constant(&BigUint::zero(), &self.fq);
+        NonNativeElement::enforce_equal(cs.namespace(|| "identity check"), &acc.z, &zero, &self.fq)
+    }
+
+    /// Enforces that an untwisted point of G2 is in the prime order
+    /// subgroup, by checking that `psi(q) = [x] q` as in Scott, "A note on
+    /// group membership tests for G1, G2 and GT on BLS pairing-friendly
+    /// curves" (ePrint 2021/1130). On untwisted points, the endomorphism
+    /// `psi` is the Frobenius.
+    fn enforce_g2_subgroup<E, CS>(&self, mut cs: CS, q: &G2Var<E>) -> Result<(), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let fq = &self.fq;
+
+        let mut t = q.clone();
+        for i in (0..63).rev() {
+            t = self
+                .g2_double(cs.namespace(|| format!("doubling {}", i)), &t)?
+                .1;
+
+            if (BLS_X >> i) & 1 == 1 {
+                let mut cs = cs.namespace(|| format!("addition {}", i));
+
+                // The slope of the addition is only determined by distinct
+                // abscissas
+                t.x.sub(&q.x, fq)
+                    .inverse(cs.namespace(|| "distinct abscissas"), fq)?;
+                t = self.g2_add(cs, &t, q)?.1;
+            }
+        }
+
+        // As x is negative, psi(q) = -[|x|] q
+        let psi_x =
+            q.x.apply_linear_map(cs.namespace(|| "frobenius x"), &self.frobenius_1, fq)?;
+        let psi_y =
+            q.y.apply_linear_map(cs.namespace(|| "frobenius y"), &self.frobenius_1, fq)?;
+        let zero = Fq12Var::constant(&Fq12Value::from_fq(BigUint::zero()), fq);
+        Fq12Var::enforce_equal(cs.namespace(|| "x check"), &psi_x, &t.x, fq)?;
+        Fq12Var::enforce_equal(cs.namespace(|| "y check"), &psi_y.add(&t.y), &zero, fq)
+    }
+}
+
+impl Default for Bls12NonNative {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: ScalarEngine> PairingGadget<E> for Bls12NonNative {
+    type Engine = Bls12;
+    type G1 = G1Var<E>;
+    type G2 = G2Var<E>;
+
+    fn alloc_g1<CS>(&self, mut cs: CS, value: Option<G1Affine>) -> Result<G1Var<E>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let fq = &self.fq;
+
+        let coordinates = match value {
+            Some(ref p) if p.is_zero() => return Err(SynthesisError::UnexpectedIdentity),
+            Some(ref p) => Some(g1_coordinates(p)),
+            None => None,
+        };
+
+        let x = NonNativeElement::alloc(
+            cs.namespace(|| "x"),
+            coordinates.as_ref().map(|c| c.0.clone()),
+            fq,
+        )?;
+        let y = NonNativeElement::alloc(
+            cs.namespace(|| "y"),
+            coordinates.as_ref().map(|c| c.1.clone()),
+            fq,
+        )?;
+
+        // y^2 = x^3 + 4
+        let y_squared = y.mul_without_reduction(cs.namespace(|| "y^2"), &y)?;
+        let x_squared = x.mul(cs.namespace(|| "x^2"), &x, fq)?;
+        let x_cubed = x_squared.mul_without_reduction(cs.namespace(|| "x^3"), &x)?;
+        let b = NonNativeElement::constant(&BigUint::from(4u64), fq);
+        NonNativeElement::enforce_equal(
+            cs.namespace(|| "on curve check"),
+            &y_squared,
+            &x_cubed.add(&b),
+            fq,
+        )?;
+
+        let z = NonNativeElement::constant(&BigUint::one(), fq);
+        let p = G1Var { x, y, z };
+
+        if self.subgroup_checks {
+            self.enforce_g1_subgroup(cs.namespace(|| "subgroup check"), &p)?;
+        }
+
+        Ok(p)
+    }
+
+    fn alloc_g2<CS>(&self, mut cs: CS, value: Option<G2Affine>) -> Result<G2Var<E>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let fq = &self.fq;
+
+        let coordinates = match value {
+            Some(ref p) if p.is_zero() => return Err(SynthesisError::UnexpectedIdentity),
+            Some(ref p) => Some(g2_coordinates(p)),
+            None => None,
+        };
+
+        let mut coords = Vec::with_capacity(4);
+        for (i, name) in ["x.c0", "x.c1", "y.c0", "y.c1"].iter().enumerate() {
+            coords.push(NonNativeElement::alloc(
+                cs.namespace(|| *name),
+                coordinates.as_ref().map(|c| c[i].clone()),
+                fq,
+            )?);
+        }
+
+        let x = Fq12Var::from_fq2(&coords[0], &coords[1], fq).mul_by_constant(
+            cs.namespace(|| "untwist x"),
+            &self.untwist_x,
+            fq,
+        )?;
+        let y = Fq12Var::from_fq2(&coords[2], &coords[3], fq).mul_by_constant(
+            cs.namespace(|| "untwist y"),
+            &self.untwist_y,
+            fq,
+        )?;
+
+        // y^2 = x^3 + 4
+        let y_squared = y.mul_without_reduction(cs.namespace(|| "y^2"), &y, fq)?;
+        let x_squared = x.mul(cs.namespace(|| "x^2"), &x, fq)?;
+        let x_cubed = x_squared.mul_without_reduction(cs.namespace(|| "x^3"), &x, fq)?;
+        let b = Fq12Var::constant(&Fq12Value::from_fq(BigUint::from(4u64)), fq);
+        Fq12Var::enforce_equal(
+            cs.namespace(|| "on curve check"),
+            &y_squared,
+            &x_cubed.add(&b),
+            fq,
+        )?;
+
+        let p = G2Var { x, y };
+
+        if self.subgroup_checks {
+            self.enforce_g2_subgroup(cs.namespace(|| "subgroup check"), &p)?;
+        }
+
+        Ok(p)
+    }
+
+    /// Adds the points with the complete formulas of Renes, Costello and
+    /// Batina for short Weierstrass curves with `a = 0`.
+    fn g1_add<CS>(&self, mut cs: CS, a: &G1Var<E>, b: &G1Var<E>) -> Result<G1Var<E>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let fq = &self.fq;
+        let b3 = BigUint::from(G1_B3);
+
+        let t0 = a.x.mul(cs.namespace(|| "x1 * x2"), &b.x, fq)?;
+        let t1 = a.y.mul(cs.namespace(|| "y1 * y2"), &b.y, fq)?;
+        let t2 = a.z.mul(cs.namespace(|| "z1 * z2"), &b.z, fq)?;
+        let t3 =
+            a.x.mul_without_reduction(cs.namespace(|| "x1 * y2"), &b.y)?
+                .add(&b.x.mul_without_reduction(cs.namespace(|| "x2 * y1"), &a.y)?)
+                .reduce(cs.namespace(|| "x1 * y2 + x2 * y1"), fq)?;
+        let t4 =
+            a.y.mul_without_reduction(cs.namespace(|| "y1 * z2"), &b.z)?
+                .add(&b.y.mul_without_reduction(cs.namespace(|| "y2 * z1"), &a.z)?)
+                .reduce(cs.namespace(|| "y1 * z2 + y2 * z1"), fq)?;
+        let t5 =
+            a.x.mul_without_reduction(cs.namespace(|| "x1 * z2"), &b.z)?
+                .add(&b.x.mul_without_reduction(cs.namespace(|| "x2 * z1"), &a.z)?)
+                .reduce(cs.namespace(|| "x1 * z2 + x2 * z1"), fq)?;
+
+        // u = t1 + 3b t2, v = t1 - 3b t2
+        let b3_t2 = t2.mul_by_constant_without_reduction(&b3, fq);
+        let u = t1.add(&b3_t2).reduce(cs.namespace(|| "u"), fq)?;
+        let v = t1.sub(&b3_t2, fq).reduce(cs.namespace(|| "v"), fq)?;
+
+        let t4_t5 = t4.mul(cs.namespace(|| "t4 * t5"), &t5, fq)?;
+        let t0_t5 = t0.mul(cs.namespace(|| "t0 * t5"), &t5, fq)?;
+        let t0_t3 = t0.mul(cs.namespace(|| "t0 * t3"), &t3, fq)?;
+
+        // x3 = t3 v - 3b t4 t5
+        let t3_v = t3.mul_without_reduction(cs.namespace(|| "t3 * v"), &v)?;
+        let x = t3_v
+            .sub(&t4_t5.mul_by_constant_without_reduction(&b3, fq), fq)
+            .reduce(cs.namespace(|| "x3"), fq)?;
+
+        // y3 = u v + 9b t0 t5
+        let y = u
+            .mul_without_reduction(cs.namespace(|| "u * v"), &v)?
+            .add(&t0_t5.mul_by_constant_without_reduction(&(&b3 * 3u64), fq))
+            .reduce(cs.namespace(|| "y3"), fq)?;
+
+        // z3 = t4 u + 3 t0 t3
+        let z = t4
+            .mul_without_reduction(cs.namespace(|| "t4 * u"), &u)?
+            .add(&t0_t3.mul_by_constant_without_reduction(&BigUint::from(3u64), fq))
+            .reduce(cs.namespace(|| "z3"), fq)?;
+
+        Ok(G1Var { x, y, z })
+    }
+
+    fn g1_negate<CS>(&self, _cs: CS, a: &G1Var<E>) -> Result<G1Var<E>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let zero = NonNativeElement::constant(&BigUint::zero(), &self.fq);
+
+        Ok(G1Var {
+            x: a.x.clone(),
+            y: zero.sub(&a.y, &self.fq),
+            z: a.z.clone(),
+        })
+    }
+
+    fn g1_mul<CS>(
+        &self,
+        mut cs: CS,
+        base: &G1Var<E>,
+        scalar: &[Boolean],
+    ) -> Result<G1Var<E>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut acc = self.g1_identity();
+        for (i, bit) in scalar.iter().enumerate().rev() {
+            let doubled = PairingGadget::<E>::g1_add(
+                self,
+                cs.namespace(|| format!("doubling {}", i)),
+                &acc,
+                &acc,
+            )?;
+            let sum = PairingGadget::<E>::g1_add(
+                self,
+                cs.namespace(|| format!("addition {}", i)),
+                &doubled,
+                base,
+            )?;
+            acc = G1Var::conditionally_select(
+                cs.namespace(|| format!("selection {}", i)),
+                &sum,
+                &doubled,
+                bit,
+            )?;
+        }
+
+        Ok(acc)
+    }
+
+    /// Computes the product of the optimal ate pairings with a single
+    /// Miller loop, and enforces that its final exponentiation is one. The
+    /// Miller loop runs over the absolute value of the parameter of the
+    /// curve: the conjugation it would need for the negative parameter does
+    /// not change whether the result is one.
+    fn enforce_pairing_product_is_one<CS>(
+        &self,
+        mut cs: CS,
+        pairs: &[(G1Var<E>, G2Var<E>)],
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let f = self.miller_loop(&mut cs, pairs, 0)?;
+
+        match f {
+            Some(f) => self
+                .enforce_final_exponentiation_is_one(cs.namespace(|| "final exponentiation"), &f),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bls::{Engine, Fq, Fq12, Fq2, Fr, G2Uncompressed};
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::test::*;
+    use crate::util_cs::bench_cs::BenchCS;
+    use crate::util_cs::sat_cs::SatCS;
+    use ff::{Field, PrimeField};
+    use groupy::CurveProjective;
+
+    fn untwist(p: &G2Affine, params: &Bls12NonNative) -> (Fq12Value, Fq12Value) {
+        let q = params.fq.modulus();
+        let c = g2_coordinates(p);
+
+        (
+            Fq12Value::from_fq2(&c[0], &c[1], q).mul(&params.untwist_x, q),
+            Fq12Value::from_fq2(&c[2], &c[3], q).mul(&params.untwist_y, q),
+        )
+    }
+
+    /// Returns the point of G1 of the given coordinates, without checking
+    /// that it is in the subgroup.
+    fn g1_unchecked(x: &BigUint, y: &BigUint) -> G1Affine {
+        let mut repr = G1Uncompressed::empty();
+        for (coordinate, bytes) in [x, y].iter().zip(repr.as_mut().chunks_mut(48)) {
+            let coordinate = coordinate.to_bytes_be();
+            bytes[48 - coordinate.len()..].copy_from_slice(&coordinate);
+        }
+
+        repr.into_affine_unchecked().unwrap()
+    }
+
+    /// Returns the point of G2 of coordinates `x.c0, x.c1, y.c0, y.c1`,
+    /// without checking that it is in the subgroup.
+    fn g2_unchecked(coordinates: &[BigUint]) -> G2Affine {
+        let mut repr = G2Uncompressed::empty();
+        // Each coordinate is serialized as c1 followed by c0
+        for (i, bytes) in [1, 0, 3, 2].iter().zip(repr.as_mut().chunks_mut(48)) {
+            let coordinate = coordinates[*i].to_bytes_be();
+            bytes[48 - coordinate.len()..].copy_from_slice(&coordinate);
+        }
+
+        repr.into_affine_unchecked().unwrap()
+    }
+
+    /// Returns a square root of an element of Fq2 embedded in Fq12, with
+    /// Algorithm 9 of Adj and Rodríguez-Henríquez, "Square root computation
+    /// over even extension fields", as q = 3 mod 4.
+    fn fq2_sqrt(a: &Fq12Value, q: &BigUint) -> Option<Fq12Value> {
+        let a1 = a.pow(&((q - 3u64) / 4u64), q);
+        let alpha = a1.mul(&a1, q).mul(a, q);
+        let x0 = a1.mul(a, q);
+
+        let x = if alpha == Fq12Value::from_fq(q - 1u64) {
+            // x0 u
+            Fq12Value::from_fq2(&BigUint::zero(), &BigUint::one(), q).mul(&x0, q)
+        } else {
+            alpha
+                .add(&Fq12Value::one(), q)
+                .pow(&((q - 1u64) / 2u64), q)
+                .mul(&x0, q)
+        };
+
+        if x.mul(&x, q) == *a {
+            Some(x)
+        } else {
+            None
+        }
+    }
+
+    fn fq_to_biguint(value: &Fq) -> BigUint {
+        let repr = value.into_repr();
+        let limbs: &[u64] = repr.as_ref();
+        limbs
+            .iter()
+            .rev()
+            .fold(BigUint::zero(), |acc, limb| (acc << 64) + limb)
+    }
+
+    #[cfg(feature = "blst")]
+    fn fq2_coefficients(value: &Fq2) -> [Fq; 2] {
+        [value.c0(), value.c1()]
+    }
+
+    #[cfg(feature = "pairing")]
+    fn fq2_coefficients(value: &Fq2) -> [Fq; 2] {
+        [value.c0, value.c1]
+    }
+
+    /// Returns the coefficients of `1, v, v^2` in both halves of an element
+    /// `c0 + c1 w` of Fq12.
+    #[cfg(feature = "blst")]
+    fn fq12_coefficients(value: &Fq12) -> [[Fq2; 3]; 2] {
+        let (c0, c1) = (value.c0(), value.c1());
+        [[c0.c0(), c0.c1(), c0.c2()], [c1.c0(), c1.c1(), c1.c2()]]
+    }
+
+    #[cfg(feature = "pairing")]
+    fn fq12_coefficients(value: &Fq12) -> [[Fq2; 3]; 2] {
+        let (c0, c1) = (value.c0, value.c1);
+        [[c0.c0, c0.c1, c0.c2], [c1.c0, c1.c1, c1.c2]]
+    }
+
+    /// Converts an element of the tower `Fq2[v, w]` of the library, with
+    /// `v = w^2`.
+    fn from_tower(value: &Fq12, q: &BigUint) -> Fq12Value {
+        let mut res = Fq12Value::from_fq(BigUint::zero());
+        for (i, half) in fq12_coefficients(value).iter().enumerate() {
+            for (j, coeff) in half.iter().enumerate() {
+                let [c0, c1] = fq2_coefficients(coeff);
+                let coeff = Fq12Value::from_fq2(&fq_to_biguint(&c0), &fq_to_biguint(&c1), q);
+                res = res.add(&coeff.mul(&Fq12Value::w_pow(i + 2 * j), q), q);
+            }
+        }
+
+        res
+    }
+
+    /// The Miller loop of `enforce_pairing_product_is_one`, computed
+    /// natively.
+    fn miller_loop(
+        pairs: &[(G1Affine, G2Affine)],
+        params: &Bls12NonNative,
+        last_bit: usize,
+    ) -> Fq12Value {
+        let q = params.fq.modulus();
+        let minus_one = Fq12Value::from_fq(q - 1u64);
+        let three = Fq12Value::from_fq(BigUint::from(3u64));
+        let sub = |a: &Fq12Value, b: &Fq12Value| a.add(&b.mul(&minus_one, q), q);
+
+        // The line of slope lambda through t evaluated at p, and the third
+        // point of the line, negated
+        let step = |lambda: &Fq12Value,
+                    t: &(Fq12Value, Fq12Value),
+                    other_x: &Fq12Value,
+                    p: &(Fq12Value, Fq12Value)| {
+            let x = sub(&sub(&lambda.mul(lambda, q), &t.0), other_x);
+            let y = sub(&lambda.mul(&sub(&t.0, &x), q), &t.1);
+            let line = sub(&sub(&p.1, &t.1), &lambda.mul(&sub(&p.0, &t.0), q));
+            (line, (x, y))
+        };
+
+        let points: Vec<_> = pairs
+            .iter()
+            .map(|(p, _)| {
+                let (x, y) = g1_coordinates(p);
+                (Fq12Value::from_fq(x), Fq12Value::from_fq(y))
+            })
+            .collect();
+        let rs: Vec<_> = pairs.iter().map(|(_, r)| untwist(r, params)).collect();
+
+        let mut ts = rs.clone();
+        let mut f = Fq12Value::one();
+        for i in (last_bit..63).rev() {
+            f = f.mul(&f, q);
+
+            for (p, t) in points.iter().zip(ts.iter_mut()) {
+                let lambda =
+                    t.0.mul(&t.0, q)
+                        .mul(&three, q)
+                        .mul(&t.1.add(&t.1, q).inverse(q).unwrap(), q);
+                let (line, doubled) = step(&lambda, t, &t.0, p);
+                f = f.mul(&line, q);
+                *t = doubled;
+            }
+
+            if (BLS_X >> i) & 1 == 1 {
+                for ((p, r), t) in points.iter().zip(rs.iter()).zip(ts.iter_mut()) {
+                    let lambda = sub(&r.1, &t.1).mul(&sub(&r.0, &t.0).inverse(q).unwrap(), q);
+                    let (line, sum) = step(&lambda, t, &r.0, p);
+                    f = f.mul(&line, q);
+                    *t = sum;
+                }
+            }
+        }
+
+        f
+    }
+
+    /// Returns `f^((q^12 - 1) / r)`.
+    fn final_exponentiation(f: &Fq12Value, params: &Bls12NonNative) -> Fq12Value {
+        let q = params.fq.modulus();
+        let r = BigUint::from_str_radix(R_MODULUS, 16).unwrap();
+
+        f.pow(&((q.pow(12) - 1u64) / r), q)
+    }
+
+    #[test]
+    fn test_fq12_native() {
+        let params = Bls12NonNative::new();
+        let q = params.fq.modulus();
+
+        // w^12 = 2 w^6 - 2
+        let w6 = Fq12Value::w_pow(6);
+        let mut expected = vec![BigUint::zero(); 12];
+        expected[0] = q - 2u64;
+        expected[6] = BigUint::from(2u64);
+        assert_eq!(w6.mul(&w6, q), Fq12Value(expected));
+
+        // u^2 = -1 and v^3 = u + 1, with v = w^2
+        let u = Fq12Value::from_fq2(&BigUint::zero(), &BigUint::one(), q);
+        assert_eq!(u.mul(&u, q), Fq12Value::from_fq(q - 1u64),);
+        let v = Fq12Value::w_pow(2);
+        assert_eq!(
+            v.mul(&v, q).mul(&v, q),
+            Fq12Value::from_fq2(&BigUint::one(), &BigUint::one(), q)
+        );
+
+        let a = Fq12Value(
+            (1..=12u64)
+                .map(|i| BigUint::from(i * i * 1_000_003))
+                .collect(),
+        );
+        assert_eq!(a.mul(&a.inverse(q).unwrap(), q), Fq12Value::one());
+
+        // The Frobenius maps are multiplicative, and of the right order
+        let b = a.add(&u, q);
+        let frobenius = |x: &Fq12Value, images: &[Fq12Value]| {
+            x.0.iter()
+                .zip(images.iter())
+                .fold(Fq12Value::from_fq(BigUint::zero()), |acc, (c, image)| {
+                    acc.add(&image.mul(&Fq12Value::from_fq(c.clone()), q), q)
+                })
+        };
+        assert_eq!(
+            frobenius(&a.mul(&b, q), &params.frobenius_2),
+            frobenius(&a, &params.frobenius_2).mul(&frobenius(&b, &params.frobenius_2), q)
+        );
+        assert_eq!(
+            frobenius(&frobenius(&a, &params.frobenius_6), &params.frobenius_6),
+            a
+        );
+        assert_ne!(frobenius(&a, &params.frobenius_6), a);
+
+        // The untwisted generator of G2 is on the curve
+        let (x, y) = untwist(&G2Affine::one(), &params);
+        assert_eq!(
+            y.mul(&y, q),
+            x.mul(&x, q)
+                .mul(&x, q)
+                .add(&Fq12Value::from_fq(BigUint::from(4u64)), q)
+        );
+    }
+
+    #[test]
+    fn test_fq12_arithmetic() {
+        let params = Bls12NonNative::new();
+        let fq = &params.fq;
+        let q = fq.modulus();
+
+        let a = Fq12Value((1..=12u64).map(|i| q - BigUint::from(i * 7919)).collect());
+        let b = Fq12Value((1..=12u64).map(|i| BigUint::from(i).pow(40)).collect());
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let a_var = Fq12Var::alloc(cs.namespace(|| "a"), Some(&a), fq).unwrap();
+        let b_var = Fq12Var::alloc(cs.namespace(|| "b"), Some(&b), fq).unwrap();
+
+        let product = a_var.mul(cs.namespace(|| "a * b"), &b_var, fq).unwrap();
+        assert_eq!(product.get_value(fq), Some(a.mul(&b, q)));
+
+        let frobenius = a_var
+            .apply_linear_map(cs.namespace(|| "frobenius"), &params.frobenius_2, fq)
+            .unwrap();
+        assert_eq!(frobenius.get_value(fq), Some(a.pow(&(q * q), q)));
+
+        let scaled = a_var
+            .mul_by_constant(cs.namespace(|| "a * b constant"), &b, fq)
+            .unwrap();
+        Fq12Var::enforce_equal(cs.namespace(|| "equality"), &product, &scaled, fq).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        cs.set("a/coefficient 3/limb 2/num", Fr::one());
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_g1_arithmetic() {
+        let params = Bls12NonNative::without_subgroup_checks();
+        let g = G1Affine::one();
+        let mut g2 = g.into_projective();
+        g2.double();
+        let g2 = g2.into_affine();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let g_var =
+            PairingGadget::<Bls12>::alloc_g1(&params, cs.namespace(|| "g"), Some(g)).unwrap();
+        let g2_var =
+            PairingGadget::<Bls12>::alloc_g1(&params, cs.namespace(|| "2g"), Some(g2)).unwrap();
+
+        let sum =
+            PairingGadget::<Bls12>::g1_add(&params, cs.namespace(|| "g + 2g"), &g_var, &g2_var)
+                .unwrap();
+        let mut expected = g2.into_projective();
+        expected.add_assign_mixed(&g);
+        assert_eq!(sum.get_value(&params), Some(expected.into_affine()));
+
+        let doubled =
+            PairingGadget::<Bls12>::g1_add(&params, cs.namespace(|| "g + g"), &g_var, &g_var)
+                .unwrap();
+        assert_eq!(doubled.get_value(&params), Some(g2));
+
+        let neg =
+            PairingGadget::<Bls12>::g1_negate(&params, cs.namespace(|| "-g"), &g_var).unwrap();
+        let zero = PairingGadget::<Bls12>::g1_add(&params, cs.namespace(|| "g - g"), &g_var, &neg)
+            .unwrap();
+        assert_eq!(zero.get_value(&params), Some(G1Affine::zero()));
+
+        // 5 g
+        let scalar: Vec<Boolean> = [true, false, true]
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b)).unwrap(),
+                )
+            })
+            .collect();
+        let product =
+            PairingGadget::<Bls12>::g1_mul(&params, cs.namespace(|| "5g"), &g_var, &scalar)
+                .unwrap();
+        assert_eq!(
+            product.get_value(&params),
+            Some(g.mul(Fr::from_str("5").unwrap()).into_affine())
+        );
+
+        assert!(cs.is_satisfied());
+
+        // The identity cannot be allocated
+        assert!(PairingGadget::<Bls12>::alloc_g1(
+            &params,
+            cs.namespace(|| "zero"),
+            Some(G1Affine::zero())
+        )
+        .is_err());
+
+        // Points off the curve are rejected
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        PairingGadget::<Bls12>::alloc_g1(&params, cs.namespace(|| "g"), Some(g)).unwrap();
+        cs.set("g/y/limb 0/num", Fr::one());
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_miller_loop_steps() {
+        let params = Bls12NonNative::without_subgroup_checks();
+        let fq = &params.fq;
+        let q = fq.modulus();
+
+        let g = G2Affine::one();
+        let mut g2 = g.into_projective();
+        g2.double();
+        let mut g3 = g2;
+        g3.add_assign_mixed(&g);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let g_var =
+            PairingGadget::<Bls12>::alloc_g2(&params, cs.namespace(|| "g"), Some(g)).unwrap();
+        let p_var =
+            PairingGadget::<Bls12>::alloc_g1(&params, cs.namespace(|| "p"), Some(G1Affine::one()))
+                .unwrap();
+        let (x, y) = params
+            .g1_to_affine(cs.namespace(|| "affine"), &p_var)
+            .unwrap();
+        let p = (Fq12Var::from_fq(&x, fq), Fq12Var::from_fq(&y, fq));
+
+        let (line, doubled) = params
+            .doubling_step(cs.namespace(|| "doubling"), &g_var, &p)
+            .unwrap();
+        let (x2, y2) = untwist(&g2.into_affine(), &params);
+        assert_eq!(doubled.x.get_value(fq), Some(x2));
+        assert_eq!(doubled.y.get_value(fq), Some(y2));
+
+        // The tangent vanishes at its point of contact
+        let (gx, gy) = untwist(&g, &params);
+        let (px, py) = g1_coordinates(&G1Affine::one());
+        let lambda = gx
+            .mul(&gx, q)
+            .mul(&Fq12Value::from_fq(BigUint::from(3u64)), q)
+            .mul(&gy.add(&gy, q).inverse(q).unwrap(), q);
+        let expected_line = Fq12Value::from_fq(py)
+            .add(&gy.mul(&Fq12Value::from_fq(q - 1u64), q), q)
+            .add(
+                &lambda
+                    .mul(
+                        &Fq12Value::from_fq(px).add(&gx.mul(&Fq12Value::from_fq(q - 1u64), q), q),
+                        q,
+                    )
+                    .mul(&Fq12Value::from_fq(q - 1u64), q),
+                q,
+            );
+        assert_eq!(line.get_value(fq), Some(expected_line));
+
+        let (_, sum) = params
+            .addition_step(cs.namespace(|| "addition"), &doubled, &g_var, &p)
+            .unwrap();
+        let (x3, y3) = untwist(&g3.into_affine(), &params);
+        assert_eq!(sum.x.get_value(fq), Some(x3));
+        assert_eq!(sum.y.get_value(fq), Some(y3));
+
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_miller_loop_top_bits() {
+        // The top bits of x include additions, and the full loop is only run
+        // by the ignored test_pairing_product
+        let last_bit = 60;
+        let params = Bls12NonNative::without_subgroup_checks();
+
+        let mut p2 = G1Affine::one().into_projective();
+        p2.double();
+        let mut q3 = G2Affine::one().into_projective();
+        q3.double();
+        q3.add_assign_mixed(&G2Affine::one());
+        let pairs = [
+            (G1Affine::one(), q3.into_affine()),
+            (p2.into_affine(), G2Affine::one()),
+        ];
+
+        let mut cs = SatCS::<Bls12>::new();
+        let mut pair_vars = Vec::new();
+        for (i, (p, q)) in pairs.iter().enumerate() {
+            let p = PairingGadget::<Bls12>::alloc_g1(
+                &params,
+                cs.namespace(|| format!("p {}", i)),
+                Some(*p),
+            )
+            .unwrap();
+            let q = PairingGadget::<Bls12>::alloc_g2(
+                &params,
+                cs.namespace(|| format!("q {}", i)),
+                Some(*q),
+            )
+            .unwrap();
+            pair_vars.push((p, q));
+        }
+
+        let f = params
+            .miller_loop(cs.namespace(|| "miller loop"), &pair_vars, last_bit)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            f.get_value(&params.fq),
+            Some(miller_loop(&pairs, &params, last_bit))
+        );
+        assert!(cs.is_satisfied());
+
+        let mut bench = BenchCS::<Bls12>::new();
+        let mut pair_vars = Vec::new();
+        for i in 0..pairs.len() {
+            let p = PairingGadget::<Bls12>::alloc_g1(
+                &params,
+                bench.namespace(|| format!("p {}", i)),
+                None,
+            )
+            .unwrap();
+            let q = PairingGadget::<Bls12>::alloc_g2(
+                &params,
+                bench.namespace(|| format!("q {}", i)),
+                None,
+            )
+            .unwrap();
+            pair_vars.push((p, q));
+        }
+        params
+            .miller_loop(bench.namespace(|| "miller loop"), &pair_vars, last_bit)
+            .unwrap();
+        assert_eq!(bench.num_constraints(), cs.num_constraints());
+    }
+
+    #[test]
+    fn test_alloc_without_values() {
+        let params = Bls12NonNative::without_subgroup_checks();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        PairingGadget::<Bls12>::alloc_g1(&params, cs.namespace(|| "g1"), Some(G1Affine::one()))
+            .unwrap();
+        PairingGadget::<Bls12>::alloc_g2(&params, cs.namespace(|| "g2"), Some(G2Affine::one()))
+            .unwrap();
+        assert!(cs.is_satisfied());
+
+        let mut bench = BenchCS::<Bls12>::new();
+        PairingGadget::<Bls12>::alloc_g1(&params, bench.namespace(|| "g1"), None).unwrap();
+        PairingGadget::<Bls12>::alloc_g2(&params, bench.namespace(|| "g2"), None).unwrap();
+        assert_eq!(bench.num_constraints(), cs.num_constraints());
+    }
+
+    #[test]
+    fn test_g1_subgroup_check() {
+        let params = Bls12NonNative::new();
+        let q = params.fq.modulus();
+
+        let mut cs = SatCS::<Bls12>::new();
+        PairingGadget::<Bls12>::alloc_g1(&params, cs.namespace(|| "g"), Some(G1Affine::one()))
+            .unwrap();
+        assert!(cs.is_satisfied());
+
+        let mut bench = BenchCS::<Bls12>::new();
+        PairingGadget::<Bls12>::alloc_g1(&params, bench.namespace(|| "g"), None).unwrap();
+        assert_eq!(bench.num_constraints(), cs.num_constraints());
+
+        // A point of the curve outside of the subgroup, as the cofactor of
+        // G1 is not one
+        let p = (0u64..)
+            .find_map(|x| {
+                let x = BigUint::from(x);
+                let rhs = (&x * &x * &x + 4u64) % q;
+                let y = rhs.modpow(&((q + 1u64) / 4u64), q);
+                if &y * &y % q == rhs {
+                    Some(g1_unchecked(&x, &y))
+                } else {
+                    None
+                }
+            })
+            .unwrap();
+
+        let mut cs = SatCS::<Bls12>::new();
+        PairingGadget::<Bls12>::alloc_g1(&params, cs.namespace(|| "p"), Some(p)).unwrap();
+        assert!(!cs.is_satisfied());
+        assert!(cs.failures()[0]
+            .path
+            .starts_with("p/subgroup check/identity check"));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_g2_subgroup_check() {
+        // Millions of constraints per point, so only run with --ignored, in
+        // release mode
+        let params = Bls12NonNative::new();
+        let q = params.fq.modulus();
+
+        let mut cs = SatCS::<Bls12>::new();
+        PairingGadget::<Bls12>::alloc_g2(&params, cs.namespace(|| "g"), Some(G2Affine::one()))
+            .unwrap();
+        assert!(cs.is_satisfied());
+
+        let mut bench = BenchCS::<Bls12>::new();
+        PairingGadget::<Bls12>::alloc_g2(&params, bench.namespace(|| "g"), None).unwrap();
+        assert_eq!(bench.num_constraints(), cs.num_constraints());
+
+        // A point of the twist outside of the subgroup, with x in Fq
+        let b = Fq12Value::from_fq2(&BigUint::from(4u64), &BigUint::from(4u64), q);
+        let p = (0u64..)
+            .find_map(|x| {
+                let x = BigUint::from(x);
+                let x_value = Fq12Value::from_fq(x.clone());
+                let rhs = x_value.mul(&x_value, q).mul(&x_value, q).add(&b, q);
+                let y = fq2_sqrt(&rhs, q)?;
+
+                // y = c0 + c1 u = (c0 - c1) + c1 w^6
+                let c1 = y.0[6].clone();
+                let c0 = (&y.0[0] + &c1) % q;
+                Some(g2_unchecked(&[x, BigUint::zero(), c0, c1]))
+            })
+            .unwrap();
+
+        let mut cs = SatCS::<Bls12>::new();
+        PairingGadget::<Bls12>::alloc_g2(&params, cs.namespace(|| "p"), Some(p)).unwrap();
+        assert!(!cs.is_satisfied());
+        assert!(cs.failures()[0].path.starts_with("p/subgroup check/"));
+    }
+
+    #[test]
+    fn test_pairing_native() {
+        let params = Bls12NonNative::new();
+        let q = params.fq.modulus();
+
+        let p = G1Affine::one();
+        let g = G2Affine::one();
+        let f = miller_loop(&[(p, g)], &params, 0);
+        let e = final_exponentiation(&f, &params);
+
+        // The Miller loop runs over |x| rather than x, which inverts the
+        // pairing, and the library raises it to 3 (q^12 - 1) / r
+        let expected = from_tower(&Bls12::pairing(p, g), q);
+        assert_eq!(
+            e.pow(&BigUint::from(3u64), q).mul(&expected, q),
+            Fq12Value::one()
+        );
+
+        // The easy and hard parts of the final exponentiation of the circuit
+        let easy = f.pow(&(q.pow(6) - 1u64), q).pow(&(q.pow(2) + 1u64), q);
+        assert_eq!(easy.pow(&params.hard_exponent, q), e);
+
+        // e(p, g) e(-p, g) = 1, but e(p, g) e(-2p, g) isn't
+        let mut neg = p;
+        neg.negate();
+        let f = miller_loop(&[(p, g), (neg, g)], &params, 0);
+        assert_eq!(final_exponentiation(&f, &params), Fq12Value::one());
+
+        let mut neg_double = p.into_projective();
+        neg_double.double();
+        neg_double.negate();
+        let f = miller_loop(&[(p, g), (neg_double.into_affine(), g)], &params, 0);
+        assert_ne!(final_exponentiation(&f, &params), Fq12Value::one());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_pairing_product() {
+        // On the order of 10^8 constraints, so only run with --ignored, in
+        // release mode
+        let params = Bls12NonNative::new();
+
+        let p = G1Affine::one();
+        let g = G2Affine::one();
+        let mut neg = p;
+        neg.negate();
+        let mut neg_double = p.into_projective();
+        neg_double.double();
+        neg_double.negate();
+
+        for (other, satisfied) in [(neg, true), (neg_double.into_affine(), false)].iter() {
+            let mut cs = SatCS::<Bls12>::new();
+
+            let mut pairs = Vec::new();
+            for (i, p) in [p, *other].iter().enumerate() {
+                let p_var = PairingGadget::<Bls12>::alloc_g1(
+                    &params,
+                    cs.namespace(|| format!("p {}", i)),
+                    Some(*p),
+                )
+                .unwrap();
+                let g_var = PairingGadget::<Bls12>::alloc_g2(
+                    &params,
+                    cs.namespace(|| format!("g {}", i)),
+                    Some(g),
+                )
+                .unwrap();
+                pairs.push((p_var, g_var));
+            }
+
+            PairingGadget::<Bls12>::enforce_pairing_product_is_one(
+                &params,
+                cs.namespace(|| "pairing check"),
+                &pairs,
+            )
+            .unwrap();
+            assert_eq!(cs.is_satisfied(), *satisfied);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! A common interface to the hash gadgets, each paired with a native
+//! implementation of the same function, so that gadgets can be written
+//! for any hash and checked against the native hash.
+
+use blake2s_simd::Params as Blake2sParams;
+use ff::ScalarEngine;
+use sha2::Digest;
+
+use super::bits::{Bits, Bytes, Endianness};
+use crate::{ConstraintSystem, SynthesisError};
+
+/// A hash function, computed either natively or in a circuit.
+///
+/// The inputs and digests of the gadget are strings of bytes, which keep
+/// track of the bit order expected by the underlying gadget: any input is
+/// accepted, and the digest has the same bytes as the native digest.
+pub trait CircuitHasher {
+    /// The length of the digests, in bytes.
+    const DIGEST_LEN: usize;
+
+    /// Hashes `input` natively.
+    fn hash(&self, input: &[u8]) -> Vec<u8>;
+
+    /// Hashes `input` in the circuit.
+    fn hash_circuit<E, CS>(&self, cs: CS, input: &Bytes) -> Result<Bytes, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>;
+}
+
+/// SHA-256, through the [`sha256`](super::sha256::sha256) gadget.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256;
+
+impl CircuitHasher for Sha256 {
+    const DIGEST_LEN: usize = 32;
+
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        sha2::Sha256::digest(input).to_vec()
+    }
+
+    fn hash_circuit<E, CS>(&self, cs: CS, input: &Bytes) -> Result<Bytes, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let input = input.clone().with_endianness(Endianness::Big);
+        let digest = super::sha256::sha256(cs, input.bits().bits())?;
+
+        Ok(Bytes::from_bits(Bits::new(digest, Endianness::Big)))
+    }
+}
+
+/// BLAKE2s with a 32-byte digest and the given personalization, through
+/// the [`blake2s`](super::blake2s::blake2s) gadget.
+#[derive(Clone, Copy, Debug)]
+pub struct Blake2s {
+    pub personalization: [u8; 8],
+}
+
+impl Blake2s {
+    pub fn new(personalization: &[u8; 8]) -> Self {
+        Blake2s {
+            personalization: *personalization,
+        }
+    }
+}
+
+impl CircuitHasher for Blake2s {
+    const DIGEST_LEN: usize = 32;
+
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        Blake2sParams::new()
+            .hash_length(Self::DIGEST_LEN)
+            .personal(&self.personalization)
+            .hash(input)
+            .as_bytes()
+            .to_vec()
+    }
+
+    fn hash_circuit<E, CS>(&self, cs: CS, input: &Bytes) -> Result<Bytes, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let input = input.clone().with_endianness(Endianness::Little);
+        let digest = super::blake2s::blake2s(cs, input.bits().bits(), &self.personalization)?;
+
+        Ok(Bytes::from_bits(Bits::new(digest, Endianness::Little)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bls::Bls12;
+    use crate::gadgets::test::*;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    // Checks that the gadget agrees with the native hash, whatever the bit
+    // order of the input.
+    fn check_agreement<H: CircuitHasher>(hasher: &H) {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for len in (0..4).chain(55..57).chain(63..66) {
+            let data: Vec<u8> = (0..len).map(|_| rng.next_u32() as u8).collect();
+            let expected = hasher.hash(&data);
+            assert_eq!(expected.len(), H::DIGEST_LEN);
+
+            for endianness in &[Endianness::Little, Endianness::Big] {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                let input =
+                    Bytes::alloc(cs.namespace(|| "input"), Some(&data[..]), len, *endianness)
+                        .unwrap();
+                let digest = hasher
+                    .hash_circuit(cs.namespace(|| "hash"), &input)
+                    .unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(digest.get_value(), Some(expected.clone()));
+            }
+
+            // Constant inputs are hashed without constraints
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input = Bytes::constant(&data, Endianness::Little);
+            let digest = hasher.hash_circuit(&mut cs, &input).unwrap();
+
+            assert_eq!(cs.num_constraints(), 0);
+            assert_eq!(digest.get_value(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_sha256() {
+        check_agreement(&Sha256);
+
+        assert_eq!(
+            Sha256.hash(b"abc"),
+            hex!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad").to_vec()
+        );
+    }
+
+    #[test]
+    fn test_blake2s() {
+        check_agreement(&Blake2s::new(b"12345678"));
+
+        assert_eq!(
+            Blake2s::new(&[0; 8]).hash(b"abc"),
+            hex!("508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982").to_vec()
+        );
+    }
+}
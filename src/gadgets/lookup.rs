@@ -5,7 +5,7 @@ use ff::{Field, ScalarEngine};
 use super::boolean::Boolean;
 use super::num::{AllocatedNum, Num};
 use super::*;
-use crate::ConstraintSystem;
+use crate::{ConstraintSystem, LinearCombination};
 
 // Synthesize the constants for each base pattern.
 fn synth<'a, E: ScalarEngine, I>(window_size: usize, constants: I, assignment: &mut [E::Fr])
@@ -27,6 +27,119 @@ where
     }
 }
 
+/// Performs a k-bit window table lookup, where k is the
+/// number of `bits`, in little-endian order. `table` has
+/// one row of equal width per window value, and a number
+/// is allocated for each column of the selected row.
+pub fn lookup<E: ScalarEngine, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    table: &[Vec<E::Fr>],
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+where
+    CS: ConstraintSystem<E>,
+{
+    let window_size = bits.len();
+    assert!(window_size > 0);
+    assert_eq!(table.len(), 1 << window_size);
+
+    let width = table[0].len();
+    assert!(table.iter().all(|row| row.len() == width));
+
+    // Calculate the index into `table`
+    let mut i = Some(0);
+    for (j, bit) in bits.iter().enumerate() {
+        i = match (i, bit.get_value()) {
+            (Some(tmp), Some(true)) => Some(tmp + (1 << j)),
+            (Some(tmp), Some(false)) => Some(tmp),
+            _ => None,
+        };
+    }
+
+    // Precompute the AND of every subset of the bits but the
+    // first, which is instead multiplied in by the lookup
+    // constraints. `products[mask]` covers `bits[j + 1]` for
+    // each bit `j` set in `mask`.
+    let mut products = Vec::with_capacity(1 << (window_size - 1));
+    products.push(Boolean::constant(true));
+    for mask in 1usize..(1 << (window_size - 1)) {
+        let lowest = mask.trailing_zeros() as usize;
+        let rest = mask & (mask - 1);
+
+        let product = if rest == 0 {
+            bits[lowest + 1].clone()
+        } else {
+            Boolean::and(
+                cs.namespace(|| format!("precomp {}", mask)),
+                &products[rest],
+                &bits[lowest + 1],
+            )?
+        };
+
+        products.push(product);
+    }
+
+    let one = CS::one();
+
+    let mut results = Vec::with_capacity(width);
+    for j in 0..width {
+        // Allocate the element resulting from the lookup
+        let res = AllocatedNum::alloc(cs.namespace(|| format!("result {}", j)), || {
+            Ok(table[*i.get()?][j])
+        })?;
+
+        // Compute the coefficients for the lookup constraint
+        let mut coeffs = vec![E::Fr::zero(); 1 << window_size];
+        synth::<E, _>(window_size, table.iter().map(|row| &row[j]), &mut coeffs);
+
+        // Split the terms on whether they involve the first bit
+        let mut lhs = LinearCombination::zero();
+        let mut rhs = LinearCombination::zero() + res.get_variable();
+        for (index, coeff) in coeffs.iter().enumerate() {
+            let product = products[index >> 1].lc::<E>(one, *coeff);
+
+            if index & 1 == 1 {
+                lhs = lhs + &product;
+            } else {
+                rhs = rhs - &product;
+            }
+        }
+
+        cs.enforce(
+            || format!("lookup {}", j),
+            |_| lhs,
+            |lc| lc + &bits[0].lc::<E>(one, E::Fr::one()),
+            |_| rhs,
+        );
+
+        results.push(res);
+    }
+
+    Ok(results)
+}
+
+/// Performs a 2-bit window table lookup. `bits` is in
+/// little-endian order.
+pub fn lookup2_xy<E: ScalarEngine, CS>(
+    cs: CS,
+    bits: &[Boolean],
+    coords: &[(E::Fr, E::Fr)],
+) -> Result<(AllocatedNum<E>, AllocatedNum<E>), SynthesisError>
+where
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(bits.len(), 2);
+    assert_eq!(coords.len(), 4);
+
+    let table: Vec<_> = coords.iter().map(|&(x, y)| vec![x, y]).collect();
+
+    let mut res = lookup(cs, bits, &table)?.into_iter();
+    let x = res.next().expect("table has two columns");
+    let y = res.next().expect("table has two columns");
+
+    Ok((x, y))
+}
+
 /// Performs a 3-bit window table lookup. `bits` is in
 /// little-endian order.
 pub fn lookup3_xy<E: ScalarEngine, CS>(
@@ -286,6 +399,118 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_lookup() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for window_size in 1..6usize {
+            for width in 1..4usize {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                let bit_values: Vec<bool> =
+                    (0..window_size).map(|_| rng.next_u32() % 2 != 0).collect();
+                let bits: Vec<Boolean> = bit_values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &b)| {
+                        Boolean::from(
+                            AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(b))
+                                .unwrap(),
+                        )
+                    })
+                    .collect();
+
+                let table: Vec<Vec<Fr>> = (0..(1 << window_size))
+                    .map(|_| (0..width).map(|_| Fr::random(&mut rng)).collect())
+                    .collect();
+
+                let res = lookup(cs.namespace(|| "lookup"), &bits, &table).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(cs.num_constraints(), (1 << (window_size - 1)) + width);
+
+                let index = bit_values
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, &b| (acc << 1) | (b as usize));
+
+                assert_eq!(res.len(), width);
+                for (j, num) in res.iter().enumerate() {
+                    assert_eq!(num.get_value().unwrap(), table[index][j]);
+                }
+
+                cs.set("lookup/result 0/num", table[index ^ 1][0]);
+                assert_eq!(cs.which_is_unsatisfied(), Some("lookup/lookup 0"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_constant_bits() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let table: Vec<Vec<Fr>> = (0..16).map(|_| vec![Fr::random(&mut rng)]).collect();
+
+        for index in 0..16 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let bits: Vec<Boolean> = (0..4)
+                .map(|i| Boolean::constant((index >> i) & 1 == 1))
+                .collect();
+
+            let res = lookup(&mut cs, &bits, &table).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(res[0].get_value().unwrap(), table[index][0]);
+        }
+    }
+
+    #[test]
+    fn test_lookup2_xy() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a_val = rng.next_u32() % 2 != 0;
+            let a = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "a"), Some(a_val)).unwrap());
+
+            let b_val = rng.next_u32() % 2 != 0;
+            let b = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "b"), Some(b_val)).unwrap());
+
+            let bits = vec![a, b];
+
+            let points: Vec<(Fr, Fr)> = (0..4)
+                .map(|_| (Fr::random(&mut rng), Fr::random(&mut rng)))
+                .collect();
+
+            let res = lookup2_xy(&mut cs, &bits, &points).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 4);
+
+            let mut index = 0;
+            if a_val {
+                index += 1
+            }
+            if b_val {
+                index += 2
+            }
+
+            assert_eq!(res.0.get_value().unwrap(), points[index].0);
+            assert_eq!(res.1.get_value().unwrap(), points[index].1);
+        }
+    }
+
     #[allow(clippy::needless_range_loop)]
     #[test]
     fn test_synth() {
@@ -0,0 +1,602 @@
+//! A read/write memory for circuits, checked offline.
+//!
+//! The operations are recorded in the order in which they are made, and
+//! their consistency is only enforced once all of them are known: a sorted
+//! copy of the operations, by address and then by time, is allocated as a
+//! witness, on which each read is checked against the previous operation
+//! on the same address. The sorted copy is shown to be a permutation of the
+//! operations by comparing the grand products
+//! `prod (gamma - key - alpha.value)` of both, where `key` encodes the
+//! address, time and kind of an operation, and the challenges `alpha` and
+//! `gamma` are derived by hashing all the values allocated for both.
+//!
+//! The memory itself is never materialized, so that each operation costs a
+//! constant number of constraints, whatever the size of the address space.
+//! This cost is dominated by the hash of the decomposition of its values
+//! into bits.
+
+use std::collections::BTreeMap;
+
+use ff::{Field, PrimeField, ScalarEngine};
+
+use super::bits::{Bits, Bytes, Endianness};
+use super::boolean::{AllocatedBit, Boolean};
+use super::hash::CircuitHasher;
+use super::num::AllocatedNum;
+use super::Assignment;
+use crate::{ConstraintSystem, LinearCombination, SynthesisError};
+
+/// The length of each challenge, in bits.
+const CHALLENGE_BITS: usize = 128;
+
+/// A memory of field elements, addressed by numbers of `addr_bytes` bytes,
+/// with all addresses initially holding zero.
+///
+/// The operations are only checked by [`finalize`](Memory::finalize),
+/// which must be called once the circuit is done with the memory.
+#[must_use = "the memory operations are only checked by `finalize`"]
+pub struct Memory<E: ScalarEngine> {
+    addr_bytes: usize,
+    contents: BTreeMap<<E::Fr as PrimeField>::Repr, E::Fr>,
+    operations: Vec<Operation<E>>,
+}
+
+struct Operation<E: ScalarEngine> {
+    addr: AllocatedNum<E>,
+    addr_bytes: Bytes,
+    value: AllocatedNum<E>,
+    is_write: bool,
+}
+
+/// An operation of the sorted copy, with its time as a witness.
+struct SortedOperation<E: ScalarEngine> {
+    addr: AllocatedNum<E>,
+    addr_bytes: Bytes,
+    time_bytes: Bytes,
+    key: AllocatedNum<E>,
+    value: AllocatedNum<E>,
+    is_write: Boolean,
+}
+
+impl<E: ScalarEngine> Memory<E> {
+    pub fn new(addr_bytes: usize) -> Self {
+        assert!(addr_bytes > 0);
+
+        Memory {
+            addr_bytes,
+            contents: BTreeMap::new(),
+            operations: vec![],
+        }
+    }
+
+    /// Returns the number of operations made so far.
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Reads the value at `addr`, which is enforced to be smaller than
+    /// `2^(8 addr_bytes)`.
+    pub fn read<CS>(
+        &mut self,
+        mut cs: CS,
+        addr: &AllocatedNum<E>,
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let addr_bytes = Bytes::from_num(
+            cs.namespace(|| "address range"),
+            addr,
+            self.addr_bytes,
+            Endianness::Little,
+        )?;
+
+        let contents = &self.contents;
+        let value = AllocatedNum::alloc(cs.namespace(|| "value"), || {
+            let addr = addr.get_value().get()?.into_repr();
+            Ok(contents.get(&addr).cloned().unwrap_or_else(E::Fr::zero))
+        })?;
+
+        self.operations.push(Operation {
+            addr: addr.clone(),
+            addr_bytes,
+            value: value.clone(),
+            is_write: false,
+        });
+
+        Ok(value)
+    }
+
+    /// Writes `value` at `addr`, which is enforced to be smaller than
+    /// `2^(8 addr_bytes)`.
+    pub fn write<CS>(
+        &mut self,
+        mut cs: CS,
+        addr: &AllocatedNum<E>,
+        value: &AllocatedNum<E>,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let addr_bytes = Bytes::from_num(
+            cs.namespace(|| "address range"),
+            addr,
+            self.addr_bytes,
+            Endianness::Little,
+        )?;
+
+        if let (Some(addr), Some(value)) = (addr.get_value(), value.get_value()) {
+            self.contents.insert(addr.into_repr(), value);
+        }
+
+        self.operations.push(Operation {
+            addr: addr.clone(),
+            addr_bytes,
+            value: value.clone(),
+            is_write: true,
+        });
+
+        Ok(())
+    }
+
+    /// Enforces that each read returned the value of the last write to the
+    /// same address, or zero if there was none, hashing the operations with
+    /// `hasher` to derive the challenges of the permutation check.
+    pub fn finalize<H, CS>(self, mut cs: CS, hasher: &H) -> Result<(), SynthesisError>
+    where
+        H: CircuitHasher,
+        CS: ConstraintSystem<E>,
+    {
+        assert!(H::DIGEST_LEN * 8 >= 2 * CHALLENGE_BITS);
+
+        let n = self.operations.len();
+        if n == 0 {
+            return Ok(());
+        }
+
+        let mut time_bytes = 1;
+        while (n - 1) >> (8 * time_bytes) != 0 {
+            time_bytes += 1;
+        }
+
+        let sorted = self.alloc_sorted(cs.namespace(|| "sorted"), time_bytes)?;
+
+        self.enforce_sorted(cs, hasher, &sorted, time_bytes)
+    }
+
+    /// Enforces that `sorted` is a permutation of the operations, sorted by
+    /// address and then by time, and that each of its reads returned the
+    /// value of the previous operation on the same address.
+    fn enforce_sorted<H, CS>(
+        &self,
+        mut cs: CS,
+        hasher: &H,
+        sorted: &[SortedOperation<E>],
+        time_bytes: usize,
+    ) -> Result<(), SynthesisError>
+    where
+        H: CircuitHasher,
+        CS: ConstraintSystem<E>,
+    {
+        // The keys must fit in the field, with an additional bit for the
+        // kind of the operation.
+        let key_bits = 8 * (self.addr_bytes + time_bytes);
+        assert!(key_bits < E::Fr::CAPACITY as usize);
+
+        // Each read returns the value of the previous operation if it is on
+        // the same address, and zero otherwise.
+        for (j, op) in sorted.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("consistency {}", j));
+
+            let previous = match j.checked_sub(1).map(|i| &sorted[i]) {
+                Some(previous_op) => {
+                    previous_op.key.enforce_less_than(
+                        cs.namespace(|| "order"),
+                        &op.key,
+                        key_bits,
+                    )?;
+
+                    let same_addr = AllocatedNum::equals(
+                        cs.namespace(|| "same address"),
+                        &previous_op.addr,
+                        &op.addr,
+                    )?;
+
+                    let previous = AllocatedNum::alloc(cs.namespace(|| "previous"), || {
+                        if *same_addr.get_value().get()? {
+                            Ok(*previous_op.value.get_value().get()?)
+                        } else {
+                            Ok(E::Fr::zero())
+                        }
+                    })?;
+
+                    // Constrain: same_addr * previous value = previous
+                    cs.enforce(
+                        || "previous constraint",
+                        |_| same_addr.lc(CS::one(), E::Fr::one()),
+                        |lc| lc + previous_op.value.get_variable(),
+                        |lc| lc + previous.get_variable(),
+                    );
+
+                    LinearCombination::zero() + previous.get_variable()
+                }
+                None => LinearCombination::zero(),
+            };
+
+            // Constrain: (value - previous) * (1 - is_write) = 0
+            cs.enforce(
+                || "read constraint",
+                |lc| lc + op.value.get_variable() - &previous,
+                |_| op.is_write.not().lc(CS::one(), E::Fr::one()),
+                |lc| lc,
+            );
+        }
+
+        // Derive the challenges from everything the prover chose
+        let mut transcript = vec![];
+        for (i, op) in self.operations.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("operation {} encoding", i));
+
+            transcript.extend(op.addr_bytes.bits().to_bits_le());
+            transcript.extend(value_bits(cs.namespace(|| "value"), &op.value)?);
+            transcript.extend(kind_bits(&Boolean::constant(op.is_write)));
+        }
+        for (j, op) in sorted.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("sorted {} encoding", j));
+
+            transcript.extend(op.addr_bytes.bits().to_bits_le());
+            transcript.extend(op.time_bytes.bits().to_bits_le());
+            transcript.extend(value_bits(cs.namespace(|| "value"), &op.value)?);
+            transcript.extend(kind_bits(&op.is_write));
+        }
+
+        let transcript = Bytes::from_bits(Bits::new(transcript, Endianness::Little));
+        let digest = hasher
+            .hash_circuit(cs.namespace(|| "challenge hash"), &transcript)?
+            .into_bits()
+            .to_bits_le();
+        let alpha = Bits::new(digest[..CHALLENGE_BITS].to_vec(), Endianness::Little)
+            .to_num(cs.namespace(|| "alpha"))?;
+        let gamma = Bits::new(
+            digest[CHALLENGE_BITS..2 * CHALLENGE_BITS].to_vec(),
+            Endianness::Little,
+        )
+        .to_num(cs.namespace(|| "gamma"))?;
+
+        // The keys are `is_write + 2 (time + 2^(8 time_bytes) addr)`
+        let mut addr_coeff = E::Fr::one();
+        for _ in 0..=8 * time_bytes {
+            addr_coeff.double();
+        }
+        let one = CS::one();
+
+        let keys = self.operations.iter().enumerate().map(|(i, op)| {
+            let constant = E::Fr::from_str(&(2 * i + op.is_write as usize).to_string()).unwrap();
+            let value = op.addr.get_value().map(|mut addr| {
+                addr.mul_assign(&addr_coeff);
+                addr.add_assign(&constant);
+                addr
+            });
+            let lc =
+                LinearCombination::zero() + (constant, one) + (addr_coeff, op.addr.get_variable());
+
+            (lc, value, &op.value)
+        });
+        let product = grand_product(cs.namespace(|| "product"), &alpha, &gamma, keys)?;
+
+        let two = E::Fr::from_str("2").unwrap();
+        let sorted_keys = sorted.iter().map(|op| {
+            let value = op
+                .key
+                .get_value()
+                .and_then(|key| Some((key, op.is_write.get_value()?)))
+                .map(|(mut key, is_write)| {
+                    key.double();
+                    if is_write {
+                        key.add_assign(&E::Fr::one());
+                    }
+                    key
+                });
+            let lc = op.is_write.lc(one, E::Fr::one()) + (two, op.key.get_variable());
+
+            (lc, value, &op.value)
+        });
+        let sorted_product = grand_product(
+            cs.namespace(|| "sorted product"),
+            &alpha,
+            &gamma,
+            sorted_keys,
+        )?;
+
+        cs.enforce(
+            || "permutation check",
+            |lc| lc + product.get_variable() - sorted_product.get_variable(),
+            |lc| lc + one,
+            |lc| lc,
+        );
+
+        Ok(())
+    }
+
+    /// Allocates the operations sorted by address and then by time.
+    fn alloc_sorted<CS>(
+        &self,
+        mut cs: CS,
+        time_bytes: usize,
+    ) -> Result<Vec<SortedOperation<E>>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let n = self.operations.len();
+
+        let order = self
+            .operations
+            .iter()
+            .map(|op| op.addr.get_value().map(|addr| addr.into_repr()))
+            .collect::<Option<Vec<_>>>()
+            .map(|addrs| {
+                let mut order: Vec<usize> = (0..n).collect();
+                order.sort_by_key(|&i| (addrs[i], i));
+                order
+            });
+
+        (0..n)
+            .map(|j| {
+                let mut cs = cs.namespace(|| format!("operation {}", j));
+                let i = order.as_ref().map(|order| order[j]);
+                let op = i.map(|i| &self.operations[i]);
+
+                let addr = AllocatedNum::alloc(cs.namespace(|| "address"), || {
+                    Ok(*op.get()?.addr.get_value().get()?)
+                })?;
+                let addr_bytes = Bytes::from_num(
+                    cs.namespace(|| "address range"),
+                    &addr,
+                    self.addr_bytes,
+                    Endianness::Little,
+                )?;
+
+                let time = AllocatedNum::alloc(cs.namespace(|| "time"), || {
+                    Ok(E::Fr::from_str(&i.get()?.to_string()).unwrap())
+                })?;
+                let time_bytes = Bytes::from_num(
+                    cs.namespace(|| "time range"),
+                    &time,
+                    time_bytes,
+                    Endianness::Little,
+                )?;
+
+                let key = time_bytes
+                    .bits()
+                    .concat(addr_bytes.bits())
+                    .to_num(cs.namespace(|| "key"))?;
+
+                let value = AllocatedNum::alloc(cs.namespace(|| "value"), || {
+                    Ok(*op.get()?.value.get_value().get()?)
+                })?;
+                let is_write =
+                    AllocatedBit::alloc(cs.namespace(|| "is write"), op.map(|op| op.is_write))?;
+
+                Ok(SortedOperation {
+                    addr,
+                    addr_bytes,
+                    time_bytes,
+                    key,
+                    value,
+                    is_write: Boolean::from(is_write),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Decomposes a value into whole bytes, for hashing.
+fn value_bits<E, CS>(cs: CS, value: &AllocatedNum<E>) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let mut bits = value.to_bits_le_strict(cs)?;
+    while bits.len() % 8 != 0 {
+        bits.push(Boolean::constant(false));
+    }
+
+    Ok(bits)
+}
+
+/// Encodes the kind of an operation as a byte, for hashing.
+fn kind_bits(is_write: &Boolean) -> Vec<Boolean> {
+    let mut bits = vec![Boolean::constant(false); 8];
+    bits[0] = is_write.clone();
+    bits
+}
+
+/// Computes `prod (gamma - key - alpha.value)` over the keys and values.
+fn grand_product<'a, E, CS, I>(
+    mut cs: CS,
+    alpha: &AllocatedNum<E>,
+    gamma: &AllocatedNum<E>,
+    keys: I,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+    I: Iterator<Item = (LinearCombination<E>, Option<E::Fr>, &'a AllocatedNum<E>)>,
+{
+    let mut product: Option<AllocatedNum<E>> = None;
+
+    for (i, (key, key_value, value)) in keys.enumerate() {
+        let mut cs = cs.namespace(|| format!("factor {}", i));
+
+        let scaled = alpha.mul(cs.namespace(|| "alpha value"), value)?;
+        let factor_value = gamma.get_value().and_then(|mut factor| {
+            factor.sub_assign(&key_value?);
+            factor.sub_assign(&scaled.get_value()?);
+            Some(factor)
+        });
+        let factor =
+            LinearCombination::zero() + gamma.get_variable() - &key - scaled.get_variable();
+
+        let next = AllocatedNum::alloc(cs.namespace(|| "product"), || {
+            let mut tmp = *factor_value.get()?;
+            if let Some(ref product) = product {
+                tmp.mul_assign(product.get_value().get()?);
+            }
+            Ok(tmp)
+        })?;
+
+        // Constrain: product * factor = next
+        cs.enforce(
+            || "product constraint",
+            |lc| match product {
+                Some(ref product) => lc + product.get_variable(),
+                None => lc + CS::one(),
+            },
+            |_| factor,
+            |lc| lc + next.get_variable(),
+        );
+
+        product = Some(next);
+    }
+
+    Ok(product.expect("at least one operation"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::hash::Blake2s;
+    use crate::gadgets::test::*;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn alloc_num<CS: ConstraintSystem<Bls12>>(cs: CS, value: u64) -> AllocatedNum<Bls12> {
+        AllocatedNum::alloc(cs, || Ok(Fr::from_str(&value.to_string()).unwrap())).unwrap()
+    }
+
+    #[test]
+    fn test_memory() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..3 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let mut memory = Memory::new(2);
+            let mut model = BTreeMap::new();
+
+            for i in 0..8 {
+                let mut cs = cs.namespace(|| format!("operation {}", i));
+
+                // Keep to a few addresses, so that they are reused
+                let addr_value = [0, 1, 0xfffe, 0xffff][rng.next_u32() as usize % 4];
+                let addr = alloc_num(cs.namespace(|| "address"), addr_value);
+
+                if rng.next_u32() % 2 == 0 {
+                    let value = Fr::random(&mut rng);
+                    let value =
+                        AllocatedNum::alloc(cs.namespace(|| "value"), || Ok(value)).unwrap();
+                    memory
+                        .write(cs.namespace(|| "write"), &addr, &value)
+                        .unwrap();
+                    model.insert(addr_value, value.get_value().unwrap());
+                } else {
+                    let value = memory.read(cs.namespace(|| "read"), &addr).unwrap();
+                    let expected = model.get(&addr_value).cloned().unwrap_or_else(Fr::zero);
+                    assert_eq!(value.get_value(), Some(expected));
+                }
+            }
+            assert_eq!(memory.len(), 8);
+
+            memory
+                .finalize(cs.namespace(|| "memory"), &Blake2s::new(b"12345678"))
+                .unwrap();
+            assert!(cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_memory_wrong_read() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let mut memory = Memory::new(1);
+
+        let addr = alloc_num(cs.namespace(|| "address"), 7);
+        let value = alloc_num(cs.namespace(|| "value"), 42);
+
+        memory
+            .write(cs.namespace(|| "write"), &addr, &value)
+            .unwrap();
+        assert_eq!(
+            memory
+                .read(cs.namespace(|| "read"), &addr)
+                .unwrap()
+                .get_value(),
+            value.get_value()
+        );
+
+        // A dishonest prover returns another value for the next read, which
+        // is consistently carried to the sorted copy.
+        memory
+            .contents
+            .insert(addr.get_value().unwrap().into_repr(), Fr::one());
+        memory.read(cs.namespace(|| "wrong read"), &addr).unwrap();
+
+        memory
+            .finalize(cs.namespace(|| "memory"), &Blake2s::new(b"12345678"))
+            .unwrap();
+
+        assert!(!cs.is_satisfied());
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("memory/consistency 2/read constraint")
+        );
+    }
+
+    #[test]
+    fn test_memory_wrong_permutation() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let mut memory = Memory::new(1);
+
+        let addr = alloc_num(cs.namespace(|| "address"), 7);
+        let value = alloc_num(cs.namespace(|| "value"), 42);
+        memory
+            .write(cs.namespace(|| "write"), &addr, &value)
+            .unwrap();
+
+        // A dishonest prover writes another value in the sorted copy, which
+        // no read depends on.
+        {
+            let mut cs = cs.namespace(|| "memory");
+            let mut sorted = memory.alloc_sorted(cs.namespace(|| "sorted"), 1).unwrap();
+            sorted[0].value = alloc_num(cs.namespace(|| "wrong value"), 43);
+
+            memory
+                .enforce_sorted(cs, &Blake2s::new(b"12345678"), &sorted, 1)
+                .unwrap();
+        }
+
+        assert!(!cs.is_satisfied());
+        assert_eq!(cs.which_is_unsatisfied(), Some("memory/permutation check"));
+    }
+
+    #[test]
+    fn test_memory_address_range() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let mut memory = Memory::new(1);
+
+        let addr = alloc_num(cs.namespace(|| "address"), 256);
+        memory.read(cs.namespace(|| "read"), &addr).unwrap();
+
+        assert!(!cs.is_satisfied());
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("read/address range/unpacking constraint")
+        );
+    }
+}
@@ -0,0 +1,388 @@
+//! Multiplexers over allocated numbers, and the switching networks built
+//! from them.
+//!
+//! The switching networks follow Batcher's odd-even merge sort, which has
+//! 1, 5, 19, 63 and 191 comparators for 2, 4, 8, 16 and 32 wires.
+
+use ff::ScalarEngine;
+
+use super::boolean::{AllocatedBit, Boolean};
+use super::num::AllocatedNum;
+use crate::{ConstraintSystem, SynthesisError};
+
+/// Selects `values[i]`, where `i` is given by `bits` in little-endian
+/// order. There must be exactly `2^k` values for `k` bits.
+///
+/// The selection is a tree of two-input multiplexers, which costs
+/// `2^k - 1` constraints, none of which are needed for the levels whose
+/// bit is constant.
+pub fn mux<E, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    values: &[AllocatedNum<E>],
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(values.len(), 1 << bits.len());
+
+    let mut layer = values.to_vec();
+    for (i, bit) in bits.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("layer {}", i));
+
+        layer = layer
+            .chunks(2)
+            .enumerate()
+            .map(|(j, pair)| match *bit {
+                Boolean::Constant(true) => Ok(pair[1].clone()),
+                Boolean::Constant(false) => Ok(pair[0].clone()),
+                _ => AllocatedNum::conditionally_select(
+                    cs.namespace(|| format!("select {}", j)),
+                    &pair[1],
+                    &pair[0],
+                    bit,
+                ),
+            })
+            .collect::<Result<_, _>>()?;
+    }
+
+    Ok(layer.pop().expect("there is exactly one value left"))
+}
+
+/// Sorts `values` in ascending order with a sorting network, in which
+/// each comparator swaps its inputs when they are out of order.
+///
+/// Every value must already be known to lie in `[0, 2^num_bits)`, as for
+/// `AllocatedNum::less_than`. Each comparator costs `num_bits + 4`
+/// constraints.
+pub fn sort<E, CS>(
+    mut cs: CS,
+    values: &[AllocatedNum<E>],
+    num_bits: usize,
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let mut wires = values.to_vec();
+    for (k, (i, j)) in batcher_network(values.len()).into_iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("comparator {}", k));
+
+        let out_of_order = wires[j].less_than(cs.namespace(|| "compare"), &wires[i], num_bits)?;
+        let (a, b) = AllocatedNum::conditionally_reverse(
+            cs.namespace(|| "swap"),
+            &wires[i],
+            &wires[j],
+            &out_of_order,
+        )?;
+
+        wires[i] = a;
+        wires[j] = b;
+    }
+
+    Ok(wires)
+}
+
+/// Permutes `values` with a switching network, such that the `i`-th
+/// result is `values[permutation[i]]`. The permutation is only needed
+/// when synthesizing with witness values.
+///
+/// This is the sorting network of [`sort`] with witnessed switches, which
+/// cost three constraints each. Any permutation can be reached, as the
+/// network sorts any order of the wires.
+pub fn permute<E, CS>(
+    mut cs: CS,
+    values: &[AllocatedNum<E>],
+    permutation: Option<&[usize]>,
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    // Each wire is keyed by the position its value must reach; the
+    // switches are those which sort the keys.
+    let mut keys = permutation.map(|permutation| {
+        assert_eq!(permutation.len(), values.len());
+
+        let mut keys = vec![None; values.len()];
+        for (position, &source) in permutation.iter().enumerate() {
+            assert!(keys[source].is_none(), "not a permutation");
+            keys[source] = Some(position);
+        }
+
+        keys.into_iter()
+            .map(|key| key.expect("not a permutation"))
+            .collect::<Vec<_>>()
+    });
+
+    let mut wires = values.to_vec();
+    for (k, (i, j)) in batcher_network(values.len()).into_iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("switch {}", k));
+
+        let swap = keys.as_mut().map(|keys| {
+            let swap = keys[i] > keys[j];
+            if swap {
+                keys.swap(i, j);
+            }
+            swap
+        });
+
+        let swap = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "swap bit"), swap)?);
+        let (a, b) = AllocatedNum::conditionally_reverse(
+            cs.namespace(|| "swap"),
+            &wires[i],
+            &wires[j],
+            &swap,
+        )?;
+
+        wires[i] = a;
+        wires[j] = b;
+    }
+
+    Ok(wires)
+}
+
+/// Returns the comparators of Batcher's odd-even merge sort over `n`
+/// wires, in order. The first wire of each comparator receives the
+/// smaller value.
+fn batcher_network(n: usize) -> Vec<(usize, usize)> {
+    let mut comparators = vec![];
+
+    let mut p = 1;
+    while p < n {
+        let mut k = p;
+        while k >= 1 {
+            let mut j = k % p;
+            while j + k < n {
+                for i in 0..k.min(n - j - k) {
+                    if (i + j) / (2 * p) == (i + j + k) / (2 * p) {
+                        comparators.push((i + j, i + j + k));
+                    }
+                }
+                j += 2 * k;
+            }
+            k /= 2;
+        }
+        p *= 2;
+    }
+
+    comparators
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::test::*;
+    use crate::util_cs::metric_cs::MetricCS;
+    use ff::{Field, PrimeField};
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn alloc_values<CS: ConstraintSystem<Bls12>>(
+        mut cs: CS,
+        values: &[Option<Fr>],
+    ) -> Vec<AllocatedNum<Bls12>> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("value {}", i)), || {
+                    value.ok_or(SynthesisError::AssignmentMissing)
+                })
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_batcher_network() {
+        // By the 0-1 principle, a network sorts everything iff it sorts
+        // every sequence of zeroes and ones.
+        for n in 0..12 {
+            let comparators = batcher_network(n);
+            for input in 0..(1u32 << n) {
+                let mut wires: Vec<u32> = (0..n).map(|i| (input >> i) & 1).collect();
+                for &(i, j) in &comparators {
+                    assert!(i < j);
+                    if wires[i] > wires[j] {
+                        wires.swap(i, j);
+                    }
+                }
+                assert!(wires.windows(2).all(|w| w[0] <= w[1]));
+            }
+        }
+
+        let sizes: Vec<usize> = [2, 4, 8, 16, 32]
+            .iter()
+            .map(|n| batcher_network(*n).len())
+            .collect();
+        assert_eq!(sizes, vec![1, 5, 19, 63, 191]);
+    }
+
+    #[test]
+    fn test_mux() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for k in 0..5 {
+            for _ in 0..10 {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                let values: Vec<Option<Fr>> =
+                    (0..(1 << k)).map(|_| Some(Fr::random(&mut rng))).collect();
+                let index = rng.next_u32() as usize % (1 << k);
+
+                let nums = alloc_values(cs.namespace(|| "values"), &values);
+                let bits: Vec<Boolean> = (0..k)
+                    .map(|i| {
+                        Boolean::from(
+                            AllocatedBit::alloc(
+                                cs.namespace(|| format!("bit {}", i)),
+                                Some((index >> i) & 1 == 1),
+                            )
+                            .unwrap(),
+                        )
+                    })
+                    .collect();
+
+                let res = mux(cs.namespace(|| "mux"), &bits, &nums).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(res.get_value(), values[index]);
+
+                if k > 0 {
+                    let path =
+                        format!("mux/layer {}/select 0/conditional select result/num", k - 1);
+                    cs.set(&path, Fr::random(&mut rng));
+                    assert!(!cs.is_satisfied());
+                }
+            }
+        }
+
+        // Constant bits select without constraints
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let values: Vec<Option<Fr>> = (0..8)
+            .map(|i| Some(Fr::from_str(&i.to_string()).unwrap()))
+            .collect();
+        let nums = alloc_values(cs.namespace(|| "values"), &values);
+        let bits = [true, false, true]
+            .iter()
+            .map(|b| Boolean::constant(*b))
+            .collect::<Vec<_>>();
+        let res = mux(cs.namespace(|| "mux"), &bits, &nums).unwrap();
+        assert_eq!(res.get_value(), values[5]);
+        assert_eq!(cs.num_constraints(), 0);
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for n in 0..10 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            // Small values, so that some of them are equal
+            let mut input: Vec<u64> = (0..n).map(|_| u64::from(rng.next_u32() % 8)).collect();
+            let values: Vec<Option<Fr>> = input
+                .iter()
+                .map(|v| Some(Fr::from_str(&v.to_string()).unwrap()))
+                .collect();
+            let nums = alloc_values(cs.namespace(|| "values"), &values);
+
+            let sorted = sort(cs.namespace(|| "sort"), &nums, 3).unwrap();
+
+            assert!(cs.is_satisfied());
+            input.sort_unstable();
+            for (res, expected) in sorted.iter().zip(input.iter()) {
+                assert_eq!(
+                    res.get_value().unwrap(),
+                    Fr::from_str(&expected.to_string()).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_permute() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for n in 0..10 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let values: Vec<Option<Fr>> = (0..n).map(|_| Some(Fr::random(&mut rng))).collect();
+            let nums = alloc_values(cs.namespace(|| "values"), &values);
+
+            // Fisher-Yates shuffle
+            let mut permutation: Vec<usize> = (0..n).collect();
+            for i in (1..n).rev() {
+                permutation.swap(i, rng.next_u32() as usize % (i + 1));
+            }
+
+            let permuted =
+                permute(cs.namespace(|| "permute"), &nums, Some(&permutation[..])).unwrap();
+
+            assert!(cs.is_satisfied());
+            for (res, source) in permuted.iter().zip(permutation.iter()) {
+                assert_eq!(res.get_value(), values[*source]);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "not a permutation")]
+    fn test_permute_invalid() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let values = vec![Some(Fr::one()), Some(Fr::zero()), Some(Fr::one())];
+        let nums = alloc_values(cs.namespace(|| "values"), &values);
+
+        permute(cs.namespace(|| "permute"), &nums, Some(&[0, 1, 1][..])).unwrap();
+    }
+
+    #[test]
+    fn test_constraint_counts() {
+        // Counted without witness values, as during parameter generation.
+        for k in 0..6 {
+            let values = vec![None; 1 << k];
+            let mut cs = MetricCS::<Bls12>::new();
+            let nums = alloc_values(cs.namespace(|| "values"), &values);
+            let bits: Vec<Boolean> = (0..k)
+                .map(|i| {
+                    Boolean::from(
+                        AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), None).unwrap(),
+                    )
+                })
+                .collect();
+
+            let before = cs.num_constraints();
+            mux(cs.namespace(|| "mux"), &bits, &nums).unwrap();
+            assert_eq!(cs.num_constraints() - before, (1 << k) - 1);
+        }
+
+        for &(n, comparators) in &[(2, 1), (4, 5), (8, 19), (16, 63)] {
+            let values = vec![None; n];
+
+            let mut cs = MetricCS::<Bls12>::new();
+            let nums = alloc_values(cs.namespace(|| "values"), &values);
+
+            sort(cs.namespace(|| "sort"), &nums, 32).unwrap();
+            assert_eq!(cs.num_constraints(), comparators * (32 + 4));
+
+            let mut cs = MetricCS::<Bls12>::new();
+            let nums = alloc_values(cs.namespace(|| "values"), &values);
+
+            permute(cs.namespace(|| "permute"), &nums, None).unwrap();
+            assert_eq!(cs.num_constraints(), comparators * 3);
+        }
+    }
+}
@@ -0,0 +1,1177 @@
+//! Gadgets for arithmetic over a field other than the scalar field of the
+//! engine, such as the base fields of secp256k1 or BN254.
+//!
+//! An element of the non-native field is represented in radix
+//! `2^limb_width` by limbs which are linear combinations of circuit
+//! variables. Each element keeps track of an upper bound on its limbs:
+//! additions and subtractions only combine limbs, while multiplications
+//! and reductions allocate a quotient and a remainder with range-checked
+//! limbs, and enforce the integer identity between them limb by limb with
+//! range-checked carries. This is sound as long as the limbs of these
+//! identities do not overflow the native field, which is asserted during
+//! synthesis.
+//!
+//! The results of these gadgets are only congruent to the expected values:
+//! range-checked limbs bound an element by `2^(limb_width * n_limbs)`, not
+//! by the modulus. Where a unique representation matters, such as when
+//! comparing or outputting elements, `enforce_canonical` or
+//! `reduce_canonical` must be used.
+
+use ff::{Field, PrimeField, ScalarEngine};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+use super::boolean::Boolean;
+use super::num::AllocatedNum;
+use super::Assignment;
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// The modulus of a non-native field, and the shape of its elements.
+#[derive(Clone, Debug)]
+pub struct NonNativeParams {
+    modulus: BigUint,
+    limb_width: usize,
+    n_limbs: usize,
+}
+
+impl NonNativeParams {
+    /// Describes the field of the given modulus, whose elements are
+    /// represented by `n_limbs` limbs of `limb_width` bits. 64-bit limbs
+    /// leave enough room for a few additions before each multiplication
+    /// over BLS12-381.
+    pub fn new(modulus: BigUint, limb_width: usize, n_limbs: usize) -> Self {
+        assert!(modulus > BigUint::one());
+        assert!(limb_width > 0);
+        assert!(
+            limb_width * n_limbs >= modulus.bits() as usize,
+            "the limbs are too small for the modulus"
+        );
+
+        NonNativeParams {
+            modulus,
+            limb_width,
+            n_limbs,
+        }
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    pub fn limb_width(&self) -> usize {
+        self.limb_width
+    }
+
+    pub fn n_limbs(&self) -> usize {
+        self.n_limbs
+    }
+}
+
+/// An element of a non-native field. Its value is congruent to the
+/// element, but not necessarily reduced below the modulus.
+pub struct NonNativeElement<E: ScalarEngine> {
+    limbs: Vec<LinearCombination<E>>,
+    limb_values: Option<Vec<BigUint>>,
+    // An upper bound on the value of every limb
+    max_limb: BigUint,
+}
+
+impl<E: ScalarEngine> Clone for NonNativeElement<E> {
+    fn clone(&self) -> Self {
+        NonNativeElement {
+            limbs: self.limbs.clone(),
+            limb_values: self.limb_values.clone(),
+            max_limb: self.max_limb.clone(),
+        }
+    }
+}
+
+impl<E: ScalarEngine> NonNativeElement<E> {
+    /// Allocates an element, which must be smaller than the modulus, with
+    /// range-checked limbs. Only the limbs are constrained, so that a
+    /// dishonest prover may allocate a congruent value up to
+    /// `2^(limb_width * n_limbs)`; see `enforce_canonical`.
+    pub fn alloc<CS>(
+        cs: CS,
+        value: Option<BigUint>,
+        params: &NonNativeParams,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let limb_values = match value {
+            Some(ref value) if value >= &params.modulus => {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+            Some(value) => Some(decompose(&value, params.limb_width, params.n_limbs)),
+            None => None,
+        };
+
+        alloc_limbs(cs, limb_values, params.n_limbs, params.limb_width)
+    }
+
+    /// Returns the element of the given value, which must be smaller than
+    /// the modulus, as a constant.
+    pub fn constant(value: &BigUint, params: &NonNativeParams) -> Self {
+        assert!(value < &params.modulus);
+
+        let one = one();
+        let limb_values = decompose(value, params.limb_width, params.n_limbs);
+        let limbs = limb_values
+            .iter()
+            .map(|limb| LinearCombination::zero() + (biguint_to_fr::<E>(limb), one))
+            .collect();
+        let max_limb = limb_values.iter().max().unwrap().clone();
+
+        NonNativeElement {
+            limbs,
+            limb_values: Some(limb_values),
+            max_limb,
+        }
+    }
+
+    pub fn limbs(&self) -> &[LinearCombination<E>] {
+        &self.limbs
+    }
+
+    /// Returns the value of the element, reduced below the modulus.
+    pub fn get_value(&self, params: &NonNativeParams) -> Option<BigUint> {
+        self.limb_values
+            .as_ref()
+            .map(|limbs| compose(limbs, params.limb_width) % &params.modulus)
+    }
+
+    /// Adds the limbs of the elements, without any constraint.
+    pub fn add(&self, other: &Self) -> Self {
+        let n_limbs = std::cmp::max(self.limbs.len(), other.limbs.len());
+
+        let limbs = (0..n_limbs)
+            .map(|i| {
+                let mut lc = LinearCombination::zero();
+                if let Some(limb) = self.limbs.get(i) {
+                    lc = lc + limb;
+                }
+                if let Some(limb) = other.limbs.get(i) {
+                    lc = lc + limb;
+                }
+                lc
+            })
+            .collect();
+
+        let limb_values = match (&self.limb_values, &other.limb_values) {
+            (Some(a), Some(b)) => Some(
+                (0..n_limbs)
+                    .map(|i| {
+                        a.get(i).cloned().unwrap_or_else(BigUint::zero)
+                            + b.get(i).cloned().unwrap_or_else(BigUint::zero)
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+        NonNativeElement {
+            limbs,
+            limb_values,
+            max_limb: &self.max_limb + &other.max_limb,
+        }
+    }
+
+    /// Subtracts the limbs of the elements, without any constraint. A
+    /// multiple of the modulus whose limbs are at least those of `other`
+    /// is added to keep every limb nonnegative.
+    pub fn sub(&self, other: &Self, params: &NonNativeParams) -> Self {
+        let w = params.limb_width;
+        let n_limbs = *[self.limbs.len(), other.limbs.len(), params.n_limbs]
+            .iter()
+            .max()
+            .unwrap();
+
+        // Pad every limb by max_limb, and then by the limbs of the
+        // difference to the next multiple of the modulus
+        let padding = compose(&vec![other.max_limb.clone(); n_limbs], w);
+        let multiple = padding.div_ceil(&params.modulus) * &params.modulus;
+        let pad_limbs: Vec<BigUint> = decompose(&(multiple - padding), w, n_limbs)
+            .into_iter()
+            .map(|limb| limb + &other.max_limb)
+            .collect();
+
+        let one = one();
+        let limbs = pad_limbs
+            .iter()
+            .enumerate()
+            .map(|(i, pad)| {
+                let mut lc = LinearCombination::zero() + (biguint_to_fr::<E>(pad), one);
+                if let Some(limb) = self.limbs.get(i) {
+                    lc = lc + limb;
+                }
+                if let Some(limb) = other.limbs.get(i) {
+                    lc = lc - limb;
+                }
+                lc
+            })
+            .collect();
+
+        let limb_values = match (&self.limb_values, &other.limb_values) {
+            (Some(a), Some(b)) => Some(
+                pad_limbs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, pad)| {
+                        let limb = a.get(i).cloned().unwrap_or_else(BigUint::zero) + pad;
+                        limb - b.get(i).cloned().unwrap_or_else(BigUint::zero)
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+        let max_pad = pad_limbs
+            .iter()
+            .max()
+            .cloned()
+            .unwrap_or_else(BigUint::zero);
+
+        NonNativeElement {
+            limbs,
+            limb_values,
+            max_limb: &self.max_limb + max_pad,
+        }
+    }
+
+    /// Multiplies the elements, and reduces the product.
+    pub fn mul<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        params: &NonNativeParams,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        self.mul_without_reduction(cs.namespace(|| "product"), other)?
+            .reduce(cs.namespace(|| "reduction"), params)
+    }
+
+    /// Multiplies the elements, returning the product of their limbs as
+    /// polynomials. This is cheaper than [`mul`](Self::mul) when several
+    /// products are summed before being reduced.
+    pub fn mul_without_reduction<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let n_coeffs = self.limbs.len() + other.limbs.len() - 1;
+        let max_coeff = BigUint::from(std::cmp::min(self.limbs.len(), other.limbs.len()))
+            * &self.max_limb
+            * &other.max_limb;
+        assert_native_bound::<E>(&max_coeff);
+
+        let coeff_values: Option<Vec<BigUint>> = match (&self.limb_values, &other.limb_values) {
+            (Some(a), Some(b)) => {
+                let mut coeffs = vec![BigUint::zero(); n_coeffs];
+                for (i, a_i) in a.iter().enumerate() {
+                    for (j, b_j) in b.iter().enumerate() {
+                        coeffs[i + j] += a_i * b_j;
+                    }
+                }
+                Some(coeffs)
+            }
+            _ => None,
+        };
+
+        let mut coeffs = Vec::with_capacity(n_coeffs);
+        for k in 0..n_coeffs {
+            let coeff = AllocatedNum::alloc(cs.namespace(|| format!("product {}", k)), || {
+                Ok(biguint_to_fr::<E>(&coeff_values.get()?[k]))
+            })?;
+
+            coeffs.push(coeff);
+        }
+
+        // The product polynomial is determined by its evaluations at
+        // n_coeffs points, which are all nonnegative and below the native
+        // modulus.
+        for x in 0..n_coeffs {
+            let x_fr = E::Fr::from_str(&x.to_string()).unwrap();
+            let powers: Vec<E::Fr> = (0..n_coeffs)
+                .scan(E::Fr::one(), |power, _| {
+                    let current = *power;
+                    power.mul_assign(&x_fr);
+                    Some(current)
+                })
+                .collect();
+
+            cs.enforce(
+                || format!("product evaluation {}", x),
+                |lc| {
+                    self.limbs
+                        .iter()
+                        .zip(powers.iter())
+                        .fold(lc, |lc, (limb, power)| lc + (*power, limb))
+                },
+                |lc| {
+                    other
+                        .limbs
+                        .iter()
+                        .zip(powers.iter())
+                        .fold(lc, |lc, (limb, power)| lc + (*power, limb))
+                },
+                |lc| {
+                    coeffs
+                        .iter()
+                        .zip(powers.iter())
+                        .fold(lc, |lc, (coeff, power)| lc + (*power, coeff.get_variable()))
+                },
+            );
+        }
+
+        Ok(NonNativeElement {
+            limbs: coeffs
+                .iter()
+                .map(|coeff| LinearCombination::zero() + coeff.get_variable())
+                .collect(),
+            limb_values: coeff_values,
+            max_limb: max_coeff,
+        })
+    }
+
+    /// Multiplies the element by a constant smaller than the modulus,
+    /// returning the product of their limbs as polynomials. This does not
+    /// need any constraint.
+    pub fn mul_by_constant_without_reduction(
+        &self,
+        constant: &BigUint,
+        params: &NonNativeParams,
+    ) -> Self {
+        assert!(constant < &params.modulus);
+
+        let constant_limbs = decompose(constant, params.limb_width, params.n_limbs);
+        let n_coeffs = self.limbs.len() + constant_limbs.len() - 1;
+
+        let limbs = (0..n_coeffs)
+            .map(|k| {
+                let mut lc = LinearCombination::zero();
+                for (j, constant_limb) in constant_limbs.iter().enumerate().take(k + 1) {
+                    if let Some(limb) = self.limbs.get(k - j) {
+                        lc = lc + (biguint_to_fr::<E>(constant_limb), limb);
+                    }
+                }
+                lc
+            })
+            .collect();
+
+        let limb_values = self.limb_values.as_ref().map(|values| {
+            let mut coeffs = vec![BigUint::zero(); n_coeffs];
+            for (i, value) in values.iter().enumerate() {
+                for (j, constant_limb) in constant_limbs.iter().enumerate() {
+                    coeffs[i + j] += value * constant_limb;
+                }
+            }
+            coeffs
+        });
+
+        let max_constant_limb = constant_limbs.iter().max().unwrap();
+
+        NonNativeElement {
+            limbs,
+            limb_values,
+            max_limb: BigUint::from(std::cmp::min(self.limbs.len(), constant_limbs.len()))
+                * &self.max_limb
+                * max_constant_limb,
+        }
+    }
+
+    /// Returns `a` if `condition` is true, and `b` otherwise.
+    pub fn conditionally_select<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let n_limbs = std::cmp::max(a.limbs.len(), b.limbs.len());
+        let zero = LinearCombination::zero();
+
+        let limb_values: Option<Vec<BigUint>> =
+            match (&a.limb_values, &b.limb_values, condition.get_value()) {
+                (Some(a), Some(b), Some(condition)) => Some(
+                    (0..n_limbs)
+                        .map(|i| {
+                            let selected = if condition { a.get(i) } else { b.get(i) };
+                            selected.cloned().unwrap_or_else(BigUint::zero)
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            };
+
+        let one = CS::one();
+        let mut limbs = Vec::with_capacity(n_limbs);
+        for i in 0..n_limbs {
+            let limb = AllocatedNum::alloc(cs.namespace(|| format!("limb {}", i)), || {
+                Ok(biguint_to_fr::<E>(&limb_values.get()?[i]))
+            })?;
+
+            let a_limb = a.limbs.get(i).unwrap_or(&zero);
+            let b_limb = b.limbs.get(i).unwrap_or(&zero);
+
+            // (a - b) * condition = limb - b
+            cs.enforce(
+                || format!("limb {} selection", i),
+                |lc| lc + a_limb - b_limb,
+                |_| condition.lc(one, E::Fr::one()),
+                |lc| lc + limb.get_variable() - b_limb,
+            );
+
+            limbs.push(LinearCombination::zero() + limb.get_variable());
+        }
+
+        Ok(NonNativeElement {
+            limbs,
+            limb_values,
+            max_limb: std::cmp::max(a.max_limb.clone(), b.max_limb.clone()),
+        })
+    }
+
+    /// Reduces the element to the form of an allocated element, with
+    /// `n_limbs` range-checked limbs. The value of the result is below the
+    /// modulus for an honest prover, but this is not enforced: the result
+    /// is only congruent to the element, see `reduce_canonical`.
+    pub fn reduce<CS>(&self, mut cs: CS, params: &NonNativeParams) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let (quotient_value, remainder_value) = match self.limb_values {
+            Some(ref limbs) => {
+                let (q, r) = compose(limbs, params.limb_width).div_rem(&params.modulus);
+                (Some(q), Some(r))
+            }
+            None => (None, None),
+        };
+
+        let quotient = self.alloc_quotient(cs.namespace(|| "quotient"), quotient_value, params)?;
+        let remainder =
+            NonNativeElement::alloc(cs.namespace(|| "remainder"), remainder_value, params)?;
+
+        enforce_quotient_remainder(
+            cs.namespace(|| "quotient check"),
+            self,
+            &quotient,
+            Some(&remainder),
+            params,
+        )?;
+
+        Ok(remainder)
+    }
+
+    /// Reduces the element like `reduce`, and enforces that the result is
+    /// smaller than the modulus, so that it is the unique representative of
+    /// the element.
+    pub fn reduce_canonical<CS>(
+        &self,
+        mut cs: CS,
+        params: &NonNativeParams,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let remainder = self.reduce(cs.namespace(|| "reduction"), params)?;
+        remainder.enforce_canonical(cs.namespace(|| "canonical"), params)?;
+
+        Ok(remainder)
+    }
+
+    /// Enforces that the integer represented by the limbs is smaller than
+    /// the modulus, by allocating its difference to `modulus - 1` with
+    /// range-checked limbs.
+    pub fn enforce_canonical<CS>(
+        &self,
+        mut cs: CS,
+        params: &NonNativeParams,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let w = params.limb_width;
+        let max_value = &params.modulus - 1u32;
+        let max_limbs = decompose(&max_value, w, params.n_limbs);
+
+        let diff_values = match self.limb_values {
+            Some(ref limbs) => {
+                let value = compose(limbs, w);
+                if value > max_value {
+                    return Err(SynthesisError::Unsatisfiable);
+                }
+                Some(decompose(&(&max_value - value), w, params.n_limbs))
+            }
+            None => None,
+        };
+        let diff = alloc_limbs(
+            cs.namespace(|| "difference"),
+            diff_values,
+            params.n_limbs,
+            w,
+        )?;
+
+        // The coefficients of self + diff - (modulus - 1)
+        let one = one();
+        let n_coeffs = std::cmp::max(self.limbs.len(), params.n_limbs);
+        let coeffs: Vec<LinearCombination<E>> = (0..n_coeffs)
+            .map(|k| {
+                let mut lc = LinearCombination::zero();
+                if let Some(limb) = self.limbs.get(k) {
+                    lc = lc + limb;
+                }
+                if let Some(limb) = diff.limbs.get(k) {
+                    lc = lc + limb;
+                }
+                if let Some(limb) = max_limbs.get(k) {
+                    lc = lc - (biguint_to_fr::<E>(limb), one);
+                }
+                lc
+            })
+            .collect();
+
+        let coeff_values = match (&self.limb_values, &diff.limb_values) {
+            (Some(a), Some(b)) => Some(
+                (0..n_coeffs)
+                    .map(|k| {
+                        let mut coeff = BigInt::zero();
+                        if let Some(limb) = a.get(k) {
+                            coeff += BigInt::from(limb.clone());
+                        }
+                        if let Some(limb) = b.get(k) {
+                            coeff += BigInt::from(limb.clone());
+                        }
+                        if let Some(limb) = max_limbs.get(k) {
+                            coeff -= BigInt::from(limb.clone());
+                        }
+                        coeff
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+        enforce_zero_when_carried(
+            cs.namespace(|| "sum check"),
+            &coeffs,
+            coeff_values,
+            &(&self.max_limb + &diff.max_limb),
+            w,
+        )
+    }
+
+    /// Enforces that the elements are congruent modulo the modulus.
+    pub fn enforce_equal<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        params: &NonNativeParams,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let diff = a.sub(b, params);
+
+        let quotient_value = diff
+            .limb_values
+            .as_ref()
+            .map(|limbs| compose(limbs, params.limb_width) / &params.modulus);
+        let quotient = diff.alloc_quotient(cs.namespace(|| "quotient"), quotient_value, params)?;
+
+        enforce_quotient_remainder(
+            cs.namespace(|| "quotient check"),
+            &diff,
+            &quotient,
+            None,
+            params,
+        )
+    }
+
+    /// Allocates the quotient of this element by the modulus, with enough
+    /// limbs for the bound on the limbs of this element.
+    fn alloc_quotient<CS>(
+        &self,
+        cs: CS,
+        value: Option<BigUint>,
+        params: &NonNativeParams,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let w = params.limb_width;
+        let max_value = compose(&vec![self.max_limb.clone(); self.limbs.len()], w);
+        let max_quotient = max_value / &params.modulus;
+        let n_limbs = std::cmp::max((max_quotient.bits() as usize + w - 1) / w, 1);
+
+        alloc_limbs(cs, value.map(|q| decompose(&q, w, n_limbs)), n_limbs, w)
+    }
+}
+
+/// Allocates limbs of `limb_width` bits.
+fn alloc_limbs<E, CS>(
+    mut cs: CS,
+    limb_values: Option<Vec<BigUint>>,
+    n_limbs: usize,
+    limb_width: usize,
+) -> Result<NonNativeElement<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let mut limbs = Vec::with_capacity(n_limbs);
+    for i in 0..n_limbs {
+        let limb = AllocatedNum::alloc(cs.namespace(|| format!("limb {}", i)), || {
+            Ok(biguint_to_fr::<E>(&limb_values.get()?[i]))
+        })?;
+        limb.range_check(cs.namespace(|| format!("limb {} range", i)), limb_width)?;
+
+        limbs.push(LinearCombination::zero() + limb.get_variable());
+    }
+
+    Ok(NonNativeElement {
+        limbs,
+        limb_values,
+        max_limb: (BigUint::one() << limb_width) - 1u32,
+    })
+}
+
+/// Enforces that `value = quotient * modulus + remainder` over the
+/// integers, where a missing remainder is zero.
+fn enforce_quotient_remainder<E, CS>(
+    cs: CS,
+    value: &NonNativeElement<E>,
+    quotient: &NonNativeElement<E>,
+    remainder: Option<&NonNativeElement<E>>,
+    params: &NonNativeParams,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let n_limbs = params.n_limbs;
+    let modulus_limbs = decompose(&params.modulus, params.limb_width, n_limbs);
+    let remainder_limbs = remainder.map_or(0, |r| r.limbs.len());
+    let n_coeffs = *[
+        value.limbs.len(),
+        quotient.limbs.len() + n_limbs - 1,
+        remainder_limbs,
+    ]
+    .iter()
+    .max()
+    .unwrap();
+
+    // The coefficients of value - quotient * modulus - remainder
+    let coeffs: Vec<LinearCombination<E>> = (0..n_coeffs)
+        .map(|k| {
+            let mut lc = LinearCombination::zero();
+            if let Some(limb) = value.limbs.get(k) {
+                lc = lc + limb;
+            }
+            if let Some(limb) = remainder.and_then(|r| r.limbs.get(k)) {
+                lc = lc - limb;
+            }
+            for (j, modulus_limb) in modulus_limbs.iter().enumerate().take(k + 1) {
+                if let Some(limb) = quotient.limbs.get(k - j) {
+                    lc = lc - (biguint_to_fr::<E>(modulus_limb), limb);
+                }
+            }
+            lc
+        })
+        .collect();
+
+    let coeff_values =
+        quotient_remainder_values(value, quotient, remainder, &modulus_limbs, n_coeffs);
+
+    // Bound the positive and negative parts of the coefficients
+    let max_modulus_limb = modulus_limbs.iter().max().unwrap();
+    let max_negative = remainder.map_or_else(BigUint::zero, |r| r.max_limb.clone())
+        + BigUint::from(std::cmp::min(quotient.limbs.len(), n_limbs))
+            * &quotient.max_limb
+            * max_modulus_limb;
+    let max_coeff = std::cmp::max(value.max_limb.clone(), max_negative);
+
+    enforce_zero_when_carried(cs, &coeffs, coeff_values, &max_coeff, params.limb_width)
+}
+
+fn quotient_remainder_values<E: ScalarEngine>(
+    value: &NonNativeElement<E>,
+    quotient: &NonNativeElement<E>,
+    remainder: Option<&NonNativeElement<E>>,
+    modulus_limbs: &[BigUint],
+    n_coeffs: usize,
+) -> Option<Vec<BigInt>> {
+    let value = value.limb_values.as_ref()?;
+    let quotient = quotient.limb_values.as_ref()?;
+    let remainder = match remainder {
+        Some(remainder) => Some(remainder.limb_values.as_ref()?),
+        None => None,
+    };
+
+    let coeffs = (0..n_coeffs)
+        .map(|k| {
+            let mut coeff = BigInt::zero();
+            if let Some(limb) = value.get(k) {
+                coeff += BigInt::from(limb.clone());
+            }
+            if let Some(limb) = remainder.and_then(|r| r.get(k)) {
+                coeff -= BigInt::from(limb.clone());
+            }
+            for (j, modulus_limb) in modulus_limbs.iter().enumerate().take(k + 1) {
+                if let Some(limb) = quotient.get(k - j) {
+                    coeff -= BigInt::from(limb * modulus_limb);
+                }
+            }
+            coeff
+        })
+        .collect();
+
+    Some(coeffs)
+}
+
+/// Enforces that the integer with the given limbs, whose absolute values
+/// are at most `max_limb`, is zero, by propagating range-checked carries
+/// from the least significant limb.
+fn enforce_zero_when_carried<E, CS>(
+    mut cs: CS,
+    limbs: &[LinearCombination<E>],
+    limb_values: Option<Vec<BigInt>>,
+    max_limb: &BigUint,
+    limb_width: usize,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_native_bound::<E>(max_limb);
+
+    // The carries are at most max_limb / (2^limb_width - 1) in absolute
+    // value, and are offset to be allocated as nonnegative numbers.
+    let carry_bits = std::cmp::max((max_limb.bits() as usize + 1).saturating_sub(limb_width), 1);
+    let offset = BigInt::one() << carry_bits;
+    let offset_fr = bigint_to_fr::<E>(&offset);
+    let base = BigInt::one() << limb_width;
+    let base_fr = bigint_to_fr::<E>(&base);
+    let mut shifted_offset_fr = offset_fr;
+    shifted_offset_fr.mul_assign(&base_fr);
+
+    let one = CS::one();
+    let mut carry: Option<AllocatedNum<E>> = None;
+    let mut carry_value = Some(BigInt::zero());
+
+    for (k, limb) in limbs.iter().enumerate() {
+        // limb + carry_in = carry_out * 2^limb_width
+        let mut lc = limb.clone();
+        if let Some(ref carry) = carry {
+            lc = lc + carry.get_variable() - (offset_fr, one);
+        }
+
+        let total = match (&limb_values, &carry_value) {
+            (Some(values), Some(carry_value)) => Some(&values[k] + carry_value),
+            _ => None,
+        };
+
+        // The last carry must be zero
+        if k + 1 < limbs.len() {
+            let next_value = total.map(|total| total.div_floor(&base));
+
+            let next = AllocatedNum::alloc(cs.namespace(|| format!("carry {}", k)), || {
+                Ok(bigint_to_fr::<E>(&(next_value.get()? + &offset)))
+            })?;
+            next.range_check(
+                cs.namespace(|| format!("carry {} range", k)),
+                carry_bits + 1,
+            )?;
+
+            lc = lc - (base_fr, next.get_variable()) + (shifted_offset_fr, one);
+
+            carry = Some(next);
+            carry_value = next_value;
+        }
+
+        cs.enforce(
+            || format!("carry constraint {}", k),
+            |_| lc,
+            |lc| lc + one,
+            |lc| lc,
+        );
+    }
+
+    Ok(())
+}
+
+/// Asserts that the terms of the constraints built from limbs bounded by
+/// `max_limb` cannot overflow the native field.
+fn assert_native_bound<E: ScalarEngine>(max_limb: &BigUint) {
+    assert!(
+        (max_limb.bits() as usize) + 4 < E::Fr::CAPACITY as usize,
+        "the limbs overflow the native field"
+    );
+}
+
+/// The "one" input variable, which the constants are multiples of.
+fn one() -> Variable {
+    Variable::new_unchecked(Index::Input(0))
+}
+
+fn decompose(value: &BigUint, limb_width: usize, n_limbs: usize) -> Vec<BigUint> {
+    let mask = (BigUint::one() << limb_width) - 1u32;
+
+    (0..n_limbs)
+        .map(|i| (value >> (limb_width * i)) & &mask)
+        .collect()
+}
+
+fn compose(limbs: &[BigUint], limb_width: usize) -> BigUint {
+    limbs
+        .iter()
+        .rev()
+        .fold(BigUint::zero(), |acc, limb| (acc << limb_width) + limb)
+}
+
+fn biguint_to_fr<E: ScalarEngine>(value: &BigUint) -> E::Fr {
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    let digits = value.to_u64_digits();
+    assert!(digits.len() <= repr.as_ref().len());
+
+    for (limb, digit) in repr.as_mut().iter_mut().zip(digits) {
+        *limb = digit;
+    }
+
+    E::Fr::from_repr(repr).expect("value is smaller than the native modulus")
+}
+
+fn bigint_to_fr<E: ScalarEngine>(value: &BigInt) -> E::Fr {
+    let mut res = biguint_to_fr::<E>(value.magnitude());
+    if value.sign() == Sign::Minus {
+        res.negate();
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::{NonNativeElement, NonNativeParams};
+    use crate::bls::Bls12;
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::test::*;
+    use crate::util_cs::bench_cs::BenchCS;
+    use crate::ConstraintSystem;
+    use ff::Field;
+    use num_bigint::BigUint;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn secp256k1_params() -> NonNativeParams {
+        let modulus = BigUint::parse_bytes(
+            b"fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+            16,
+        )
+        .unwrap();
+
+        NonNativeParams::new(modulus, 64, 4)
+    }
+
+    fn bn254_params() -> NonNativeParams {
+        let modulus = BigUint::parse_bytes(
+            b"30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47",
+            16,
+        )
+        .unwrap();
+
+        NonNativeParams::new(modulus, 64, 4)
+    }
+
+    fn random_element<R: RngCore>(rng: &mut R, params: &NonNativeParams) -> BigUint {
+        let mut bytes = [0u8; 40];
+        rng.fill_bytes(&mut bytes);
+
+        BigUint::from_bytes_le(&bytes) % params.modulus()
+    }
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ])
+    }
+
+    #[test]
+    fn test_nonnative_arithmetic() {
+        let mut rng = rng();
+
+        for params in &[secp256k1_params(), bn254_params()] {
+            let p = params.modulus();
+
+            for _ in 0..10 {
+                let a_value = random_element(&mut rng, params);
+                let b_value = random_element(&mut rng, params);
+
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                let a =
+                    NonNativeElement::alloc(cs.namespace(|| "a"), Some(a_value.clone()), params)
+                        .unwrap();
+                let b =
+                    NonNativeElement::alloc(cs.namespace(|| "b"), Some(b_value.clone()), params)
+                        .unwrap();
+
+                let sum = a.add(&b).reduce(cs.namespace(|| "a + b"), params).unwrap();
+                let diff = a
+                    .sub(&b, params)
+                    .reduce(cs.namespace(|| "reduce a - b"), params)
+                    .unwrap();
+                let product = a.mul(cs.namespace(|| "a * b"), &b, params).unwrap();
+
+                // (a + b) * (a - b) with unreduced operands
+                let lazy_diff = a.sub(&b, params);
+                let lazy = a
+                    .add(&b)
+                    .mul(cs.namespace(|| "(a + b) * (a - b)"), &lazy_diff, params)
+                    .unwrap();
+
+                assert!(cs.is_satisfied());
+
+                assert_eq!(sum.get_value(params).unwrap(), (&a_value + &b_value) % p);
+                assert_eq!(
+                    diff.get_value(params).unwrap(),
+                    (&a_value + p - &b_value) % p
+                );
+                assert_eq!(
+                    product.get_value(params).unwrap(),
+                    (&a_value * &b_value) % p
+                );
+                assert_eq!(
+                    lazy.get_value(params).unwrap(),
+                    ((&a_value * &a_value) + p * p - (&b_value * &b_value)) % p
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_nonnative_enforce_equal() {
+        let mut rng = rng();
+        let params = secp256k1_params();
+
+        let a_value = random_element(&mut rng, &params);
+        let b_value = random_element(&mut rng, &params);
+
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = NonNativeElement::alloc(cs.namespace(|| "a"), Some(a_value.clone()), &params)
+                .unwrap();
+            let b = NonNativeElement::alloc(cs.namespace(|| "b"), Some(b_value.clone()), &params)
+                .unwrap();
+
+            let ab = a.mul(cs.namespace(|| "a * b"), &b, &params).unwrap();
+            let ba = b.mul(cs.namespace(|| "b * a"), &a, &params).unwrap();
+
+            // The unreduced sum is congruent to the reduced one
+            let sum = a.add(&b);
+            let reduced_sum = sum.reduce(cs.namespace(|| "a + b"), &params).unwrap();
+
+            NonNativeElement::enforce_equal(cs.namespace(|| "ab == ba"), &ab, &ba, &params)
+                .unwrap();
+            NonNativeElement::enforce_equal(
+                cs.namespace(|| "sum == reduced sum"),
+                &sum,
+                &reduced_sum,
+                &params,
+            )
+            .unwrap();
+
+            assert!(cs.is_satisfied());
+        }
+
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = NonNativeElement::alloc(cs.namespace(|| "a"), Some(a_value), &params).unwrap();
+            let b = NonNativeElement::alloc(cs.namespace(|| "b"), Some(b_value), &params).unwrap();
+
+            NonNativeElement::enforce_equal(cs.namespace(|| "a == b"), &a, &b, &params).unwrap();
+
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_nonnative_unsatisfied() {
+        let mut rng = rng();
+        let params = bn254_params();
+
+        let a_value = random_element(&mut rng, &params);
+        let b_value = random_element(&mut rng, &params);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let a = NonNativeElement::alloc(cs.namespace(|| "a"), Some(a_value), &params).unwrap();
+        let b = NonNativeElement::alloc(cs.namespace(|| "b"), Some(b_value), &params).unwrap();
+        a.mul(cs.namespace(|| "a * b"), &b, &params).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        // Limbs are range checked
+        let limb = cs.get("a/limb 0/num");
+        cs.set("a/limb 0/num", crate::bls::Fr::one());
+        assert!(!cs.is_satisfied());
+        cs.set("a/limb 0/num", limb);
+        assert!(cs.is_satisfied());
+
+        // The remainder must be the product
+        let limb = cs.get("a * b/reduction/remainder/limb 1/num");
+        let mut tampered = limb;
+        tampered.add_assign(&crate::bls::Fr::one());
+        cs.set("a * b/reduction/remainder/limb 1/num", tampered);
+        assert!(!cs.is_satisfied());
+
+        // Allocating a value which is not reduced fails
+        assert!(NonNativeElement::<Bls12>::alloc(
+            cs.namespace(|| "c"),
+            Some(params.modulus().clone()),
+            &params
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_nonnative_canonical() {
+        let mut rng = rng();
+        let params = secp256k1_params();
+        let p = params.modulus();
+
+        for value in &[
+            BigUint::from(0u32),
+            p - 1u32,
+            random_element(&mut rng, &params),
+        ] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = NonNativeElement::alloc(cs.namespace(|| "a"), Some(value.clone()), &params)
+                .unwrap();
+            a.enforce_canonical(cs.namespace(|| "a canonical"), &params)
+                .unwrap();
+
+            assert!(cs.is_satisfied());
+        }
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let a = NonNativeElement::alloc(cs.namespace(|| "a"), Some(p - 1u32), &params).unwrap();
+        let b = NonNativeElement::alloc(cs.namespace(|| "b"), Some(1u32.into()), &params).unwrap();
+
+        // The sum is congruent to zero, but its limbs are those of p
+        let sum = a.add(&b);
+        assert!(sum
+            .enforce_canonical(cs.namespace(|| "a + b canonical"), &params)
+            .is_err());
+
+        let reduced = sum
+            .reduce_canonical(cs.namespace(|| "reduce a + b"), &params)
+            .unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(reduced.get_value(&params), Some(BigUint::from(0u32)));
+
+        // The difference to p - 1 is bound to the limbs
+        cs.set(
+            "reduce a + b/canonical/difference/limb 0/num",
+            crate::bls::Fr::one(),
+        );
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_nonnative_without_values() {
+        let params = secp256k1_params();
+
+        let mut cs = BenchCS::<Bls12>::new();
+        let a = NonNativeElement::alloc(cs.namespace(|| "a"), None, &params).unwrap();
+        let b = NonNativeElement::alloc(cs.namespace(|| "b"), None, &params).unwrap();
+        let product = a.mul(cs.namespace(|| "a * b"), &b, &params).unwrap();
+        NonNativeElement::enforce_equal(cs.namespace(|| "a * b == a"), &product, &a, &params)
+            .unwrap();
+
+        let mut test_cs = TestConstraintSystem::<Bls12>::new();
+        let a =
+            NonNativeElement::alloc(test_cs.namespace(|| "a"), Some(3u32.into()), &params).unwrap();
+        let b =
+            NonNativeElement::alloc(test_cs.namespace(|| "b"), Some(1u32.into()), &params).unwrap();
+        let product = a.mul(test_cs.namespace(|| "a * b"), &b, &params).unwrap();
+        NonNativeElement::enforce_equal(test_cs.namespace(|| "a * b == a"), &product, &a, &params)
+            .unwrap();
+
+        assert!(test_cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), test_cs.num_constraints());
+    }
+
+    #[test]
+    fn test_nonnative_constants_and_selection() {
+        let mut rng = rng();
+        let params = secp256k1_params();
+        let p = params.modulus();
+
+        for condition in &[false, true] {
+            let a_value = random_element(&mut rng, &params);
+            let b_value = random_element(&mut rng, &params);
+            let c_value = random_element(&mut rng, &params);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = NonNativeElement::alloc(cs.namespace(|| "a"), Some(a_value.clone()), &params)
+                .unwrap();
+            let b = NonNativeElement::alloc(cs.namespace(|| "b"), Some(b_value.clone()), &params)
+                .unwrap();
+            let c = NonNativeElement::constant(&c_value, &params);
+            let condition = Boolean::from(
+                AllocatedBit::alloc(cs.namespace(|| "condition"), Some(*condition)).unwrap(),
+            );
+
+            let selected = NonNativeElement::conditionally_select(
+                cs.namespace(|| "select"),
+                &a,
+                &b,
+                &condition,
+            )
+            .unwrap();
+
+            // a * c computed with and without the constant being allocated
+            let ac = a.mul(cs.namespace(|| "a * c"), &c, &params).unwrap();
+            let ac_constant = a.mul_by_constant_without_reduction(&c_value, &params);
+            NonNativeElement::enforce_equal(
+                cs.namespace(|| "a * c == ac"),
+                &ac,
+                &ac_constant,
+                &params,
+            )
+            .unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let expected = if condition.get_value().unwrap() {
+                &a_value
+            } else {
+                &b_value
+            };
+            assert_eq!(selected.get_value(&params).as_ref(), Some(expected));
+            assert_eq!(ac.get_value(&params).unwrap(), (&a_value * &c_value) % p);
+            assert_eq!(
+                ac_constant.get_value(&params).unwrap(),
+                (&a_value * &c_value) % p
+            );
+
+            cs.set("select/limb 0/num", crate::bls::Fr::one());
+            assert_eq!(cs.which_is_unsatisfied(), Some("select/limb 0 selection"));
+        }
+    }
+}
@@ -71,6 +71,9 @@ impl<E: ScalarEngine> AllocatedNum<E> {
     where
         CS: ConstraintSystem<E>,
     {
+        // This chain of ANDs costs one constraint per operand, more than
+        // `Boolean::kary_and`, but switching would change the shape of every
+        // circuit unpacking a number strictly, and so their parameters.
         pub fn kary_and<E, CS>(
             mut cs: CS,
             v: &[AllocatedBit],
@@ -214,6 +217,172 @@ impl<E: ScalarEngine> AllocatedNum<E> {
         Ok(bits.into_iter().map(Boolean::from).collect())
     }
 
+    /// Convert the allocated number into its `num_bits` least significant
+    /// bits in little-endian order, enforcing that the number lies in
+    /// `[0, 2^num_bits)`.
+    pub fn to_bits_le_bounded<CS>(
+        &self,
+        cs: CS,
+        num_bits: usize,
+    ) -> Result<Vec<Boolean>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        assert!(num_bits <= E::Fr::CAPACITY as usize);
+
+        let bits = alloc_lc_bits_le(
+            cs,
+            self.value,
+            LinearCombination::zero() + self.variable,
+            num_bits,
+        )?;
+
+        Ok(bits.into_iter().map(Boolean::from).collect())
+    }
+
+    /// Enforces that the allocated number lies in `[0, 2^num_bits)`.
+    pub fn range_check<CS>(&self, cs: CS, num_bits: usize) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        self.to_bits_le_bounded(cs, num_bits)?;
+
+        Ok(())
+    }
+
+    /// Returns a `Boolean` which is true iff `self < other`.
+    ///
+    /// Both numbers must already be known to lie in `[0, 2^num_bits)`,
+    /// e.g. through `range_check`; otherwise the result is meaningless.
+    pub fn less_than<CS>(
+        &self,
+        cs: CS,
+        other: &Self,
+        num_bits: usize,
+    ) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        assert!(num_bits < E::Fr::CAPACITY as usize);
+
+        // 2^num_bits + self - other lies in [0, 2^(num_bits + 1)), and its
+        // most significant bit is set iff self >= other.
+        let offset = E::Fr::from_str("2").unwrap().pow(&[num_bits as u64]);
+
+        let value = match (self.value, other.value) {
+            (Some(a), Some(b)) => {
+                let mut tmp = offset;
+                tmp.add_assign(&a);
+                tmp.sub_assign(&b);
+                Some(tmp)
+            }
+            _ => None,
+        };
+
+        let mut bits = alloc_lc_bits_le(
+            cs,
+            value,
+            LinearCombination::zero() + (offset, CS::one()) + self.variable - other.variable,
+            num_bits + 1,
+        )?;
+
+        let geq = bits.pop().expect("num_bits + 1 > 0");
+
+        Ok(Boolean::from(geq).not())
+    }
+
+    /// Returns a `Boolean` which is true iff `self <= other`. See
+    /// `less_than` for the requirements on the operands.
+    pub fn less_than_or_equal<CS>(
+        &self,
+        cs: CS,
+        other: &Self,
+        num_bits: usize,
+    ) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        Ok(other.less_than(cs, self, num_bits)?.not())
+    }
+
+    /// Returns a `Boolean` which is true iff `self > other`. See
+    /// `less_than` for the requirements on the operands.
+    pub fn greater_than<CS>(
+        &self,
+        cs: CS,
+        other: &Self,
+        num_bits: usize,
+    ) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        other.less_than(cs, self, num_bits)
+    }
+
+    /// Returns a `Boolean` which is true iff `self >= other`. See
+    /// `less_than` for the requirements on the operands.
+    pub fn greater_than_or_equal<CS>(
+        &self,
+        cs: CS,
+        other: &Self,
+        num_bits: usize,
+    ) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        Ok(self.less_than(cs, other, num_bits)?.not())
+    }
+
+    /// Enforces that `self < other`. See `less_than` for the requirements
+    /// on the operands.
+    pub fn enforce_less_than<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        num_bits: usize,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let lt = self.less_than(cs.namespace(|| "less than"), other, num_bits)?;
+
+        Boolean::enforce_equal(cs.namespace(|| "enforce"), &lt, &Boolean::constant(true))
+    }
+
+    /// Enforces that `self <= constant`, with no prior requirement on
+    /// the range of `self`.
+    pub fn enforce_less_than_or_equal_constant<CS>(
+        &self,
+        mut cs: CS,
+        constant: E::Fr,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        // Both self and constant - self must fit in as many bits as the
+        // constant; if self > constant, the difference wraps around the
+        // modulus and cannot be that small.
+        let num_bits = constant.into_repr().num_bits() as usize;
+        assert!(num_bits < E::Fr::CAPACITY as usize);
+
+        self.range_check(cs.namespace(|| "range"), num_bits)?;
+
+        let value = self.value.map(|v| {
+            let mut tmp = constant;
+            tmp.sub_assign(&v);
+            tmp
+        });
+
+        alloc_lc_bits_le(
+            cs.namespace(|| "difference"),
+            value,
+            LinearCombination::zero() + (constant, CS::one()) - self.variable,
+            num_bits,
+        )?;
+
+        Ok(())
+    }
+
     pub fn mul<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<E>,
@@ -308,6 +477,123 @@ impl<E: ScalarEngine> AllocatedNum<E> {
         Ok(())
     }
 
+    /// Computes the multiplicative inverse of the allocated number.
+    /// Witness generation fails if the number is zero.
+    pub fn inverse<CS>(&self, mut cs: CS) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut value = None;
+
+        let var = cs.alloc(
+            || "inverse num",
+            || {
+                let tmp = *self.value.get()?;
+
+                let inv = tmp.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+                value = Some(inv);
+
+                Ok(inv)
+            },
+        )?;
+
+        // Constrain: a * inv = 1
+        cs.enforce(
+            || "inverse constraint",
+            |lc| lc + self.variable,
+            |lc| lc + var,
+            |lc| lc + CS::one(),
+        );
+
+        Ok(AllocatedNum {
+            value,
+            variable: var,
+        })
+    }
+
+    /// Computes `self / other`. Witness generation fails if `other`
+    /// is zero, and the constraints cannot be satisfied in that case.
+    pub fn div<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let inv = other.inverse(cs.namespace(|| "inverse"))?;
+
+        self.mul(cs.namespace(|| "quotient"), &inv)
+    }
+
+    /// Returns a `Boolean` which is true iff the allocated number is zero.
+    pub fn is_zero<CS>(&self, cs: CS) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        lc_is_zero(cs, self.value, LinearCombination::zero() + self.variable)
+    }
+
+    /// Returns a `Boolean` which is true iff the two allocated
+    /// numbers are equal.
+    pub fn equals<CS>(cs: CS, a: &Self, b: &Self) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let value = match (a.value, b.value) {
+            (Some(a_value), Some(b_value)) => {
+                let mut tmp = a_value;
+                tmp.sub_assign(&b_value);
+                Some(tmp)
+            }
+            _ => None,
+        };
+
+        lc_is_zero(
+            cs,
+            value,
+            LinearCombination::zero() + a.variable - b.variable,
+        )
+    }
+
+    /// Raises the allocated number to the constant power `exp`, given
+    /// as little-endian `u64` limbs, by square-and-multiply.
+    pub fn pow<CS, S>(&self, mut cs: CS, exp: S) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+        S: AsRef<[u64]>,
+    {
+        let mut res: Option<Self> = None;
+
+        for (i, bit) in BitIterator::new(exp).enumerate() {
+            // Leading zeros are skipped, as there is nothing to square yet
+            if let Some(cur) = res.take() {
+                res = Some(cur.square(cs.namespace(|| format!("square {}", i)))?);
+            }
+
+            if bit {
+                res = Some(match res.take() {
+                    Some(cur) => cur.mul(cs.namespace(|| format!("multiply {}", i)), self)?,
+                    None => self.clone(),
+                });
+            }
+        }
+
+        match res {
+            Some(res) => Ok(res),
+            None => {
+                // The exponent is zero, so the result is the constant one
+                let one = Self::alloc(cs.namespace(|| "one"), || Ok(E::Fr::one()))?;
+
+                cs.enforce(
+                    || "one constraint",
+                    |lc| lc + one.variable,
+                    |lc| lc + CS::one(),
+                    |lc| lc + CS::one(),
+                );
+
+                Ok(one)
+            }
+        }
+    }
+
     /// Takes two allocated numbers (a, b) and returns
     /// (b, a) if the condition is true, and (a, b)
     /// otherwise.
@@ -353,6 +639,36 @@ impl<E: ScalarEngine> AllocatedNum<E> {
         Ok((c, d))
     }
 
+    /// Takes two allocated numbers (a, b) and returns
+    /// a if the condition is true, and b otherwise.
+    pub fn conditionally_select<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let c = Self::alloc(cs.namespace(|| "conditional select result"), || {
+            if *condition.get_value().get()? {
+                Ok(*a.value.get()?)
+            } else {
+                Ok(*b.value.get()?)
+            }
+        })?;
+
+        // Constrain: (a - b) * condition = c - b
+        cs.enforce(
+            || "conditional select constraint",
+            |lc| lc + a.variable - b.variable,
+            |_| condition.lc(CS::one(), E::Fr::one()),
+            |lc| lc + c.variable - b.variable,
+        );
+
+        Ok(c)
+    }
+
     pub fn get_value(&self) -> Option<E::Fr> {
         self.value
     }
@@ -362,6 +678,92 @@ impl<E: ScalarEngine> AllocatedNum<E> {
     }
 }
 
+/// Returns a `Boolean` which is true iff `lc`, whose assignment is
+/// `value`, evaluates to zero.
+pub(super) fn lc_is_zero<E, CS>(
+    mut cs: CS,
+    value: Option<E::Fr>,
+    lc: LinearCombination<E>,
+) -> Result<Boolean, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let is_zero = AllocatedBit::alloc(cs.namespace(|| "is zero"), value.map(|v| v.is_zero()))?;
+
+    // The inverse of the value, or zero if it has none
+    let inv = cs.alloc(
+        || "pseudo-inverse",
+        || Ok(value.get()?.inverse().unwrap_or_else(E::Fr::zero)),
+    )?;
+
+    // Constrain: lc * inv = 1 - is_zero
+    // If lc is zero, this forces is_zero to be true.
+    cs.enforce(
+        || "pseudo-inverse constraint",
+        |_| lc.clone(),
+        |lc| lc + inv,
+        |lc| lc + CS::one() - is_zero.get_variable(),
+    );
+
+    // Constrain: lc * is_zero = 0
+    // If lc is nonzero, this forces is_zero to be false.
+    cs.enforce(
+        || "is zero constraint",
+        |_| lc,
+        |lc| lc + is_zero.get_variable(),
+        |lc| lc,
+    );
+
+    Ok(Boolean::from(is_zero))
+}
+
+/// Allocates the `num_bits` least significant bits of `value` in
+/// little-endian order and enforces that they pack into `lc`.
+fn alloc_lc_bits_le<E, CS>(
+    mut cs: CS,
+    value: Option<E::Fr>,
+    lc: LinearCombination<E>,
+    num_bits: usize,
+) -> Result<Vec<AllocatedBit>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let values: Vec<Option<bool>> = match value {
+        Some(value) => {
+            let repr = value.into_repr();
+            let limbs = repr.as_ref();
+
+            (0..num_bits)
+                .map(|i| Some((limbs[i / 64] >> (i % 64)) & 1 == 1))
+                .collect()
+        }
+        None => vec![None; num_bits],
+    };
+
+    let bits = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, b)| AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), b))
+        .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    let mut packed = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+
+    for bit in bits.iter() {
+        packed = packed + (coeff, bit.get_variable());
+
+        coeff.double();
+    }
+
+    packed = packed - &lc;
+
+    cs.enforce(|| "unpacking constraint", |lc| lc, |lc| lc, |_| packed);
+
+    Ok(bits)
+}
+
 #[derive(Clone)]
 pub struct Num<E: ScalarEngine> {
     value: Option<E::Fr>,
@@ -448,7 +850,7 @@ mod test {
     use rand_core::SeedableRng;
     use rand_xorshift::XorShiftRng;
 
-    use super::{AllocatedNum, Boolean, Num};
+    use super::{AllocatedBit, AllocatedNum, Boolean, Num};
     use crate::gadgets::test::*;
 
     #[test]
@@ -526,6 +928,132 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_num_range_check() {
+        for &(value, expected) in &[(0u64, true), (1, true), (255, true), (256, false)] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::from_str(&value.to_string()).unwrap()))
+                .unwrap();
+            let bits = n.to_bits_le_bounded(cs.namespace(|| "bits"), 8).unwrap();
+
+            assert_eq!(bits.len(), 8);
+            assert_eq!(cs.num_constraints(), 9);
+            assert_eq!(cs.is_satisfied(), expected);
+
+            for (i, bit) in bits.iter().enumerate() {
+                assert_eq!(bit.get_value().unwrap(), (value >> i) & 1 == 1);
+            }
+        }
+
+        let mut negone = Fr::one();
+        negone.negate();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let n = AllocatedNum::alloc(&mut cs, || Ok(negone)).unwrap();
+        n.range_check(cs.namespace(|| "range"), 64).unwrap();
+
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("range/unpacking constraint")
+        );
+    }
+
+    #[test]
+    fn test_num_comparisons() {
+        let values = [0u64, 1, 2, 127, 254, 255];
+
+        for &a_val in values.iter() {
+            for &b_val in values.iter() {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                let a = AllocatedNum::alloc(cs.namespace(|| "a"), || {
+                    Ok(Fr::from_str(&a_val.to_string()).unwrap())
+                })
+                .unwrap();
+                let b = AllocatedNum::alloc(cs.namespace(|| "b"), || {
+                    Ok(Fr::from_str(&b_val.to_string()).unwrap())
+                })
+                .unwrap();
+
+                let lt = a.less_than(cs.namespace(|| "lt"), &b, 8).unwrap();
+                let le = a.less_than_or_equal(cs.namespace(|| "le"), &b, 8).unwrap();
+                let gt = a.greater_than(cs.namespace(|| "gt"), &b, 8).unwrap();
+                let ge = a
+                    .greater_than_or_equal(cs.namespace(|| "ge"), &b, 8)
+                    .unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(lt.get_value().unwrap(), a_val < b_val);
+                assert_eq!(le.get_value().unwrap(), a_val <= b_val);
+                assert_eq!(gt.get_value().unwrap(), a_val > b_val);
+                assert_eq!(ge.get_value().unwrap(), a_val >= b_val);
+
+                // Flipping the comparison bit must break the decomposition
+                let cur = cs.get("lt/bit 8/boolean");
+                let mut flipped = Fr::one();
+                flipped.sub_assign(&cur);
+                cs.set("lt/bit 8/boolean", flipped);
+                assert_eq!(cs.which_is_unsatisfied(), Some("lt/unpacking constraint"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_enforce_less_than() {
+        for &(a_val, b_val, expected) in &[(3u64, 7u64, true), (7, 7, false), (8, 7, false)] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || {
+                Ok(Fr::from_str(&a_val.to_string()).unwrap())
+            })
+            .unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || {
+                Ok(Fr::from_str(&b_val.to_string()).unwrap())
+            })
+            .unwrap();
+
+            a.enforce_less_than(cs.namespace(|| "a < b"), &b, 4)
+                .unwrap();
+
+            assert_eq!(cs.is_satisfied(), expected);
+        }
+    }
+
+    #[test]
+    fn test_num_enforce_less_than_or_equal_constant() {
+        let constant = Fr::from_str("100").unwrap();
+
+        for &(value, expected) in &[
+            (0u64, true),
+            (50, true),
+            (100, true),
+            (101, false),
+            (127, false),
+            (128, false),
+        ] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::from_str(&value.to_string()).unwrap()))
+                .unwrap();
+            n.enforce_less_than_or_equal_constant(cs.namespace(|| "bound"), constant)
+                .unwrap();
+
+            assert_eq!(cs.is_satisfied(), expected);
+        }
+
+        // A value just below the modulus must not pass as "small"
+        let mut negone = Fr::one();
+        negone.negate();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let n = AllocatedNum::alloc(&mut cs, || Ok(negone)).unwrap();
+        n.enforce_less_than_or_equal_constant(cs.namespace(|| "bound"), constant)
+            .unwrap();
+
+        assert!(!cs.is_satisfied());
+    }
+
     #[test]
     fn test_num_nonzero() {
         {
@@ -546,6 +1074,236 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_num_inverse() {
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::from_str("3").unwrap())).unwrap();
+            let inv = n.inverse(&mut cs).unwrap();
+
+            assert!(cs.is_satisfied());
+            let mut product = inv.value.unwrap();
+            product.mul_assign(&Fr::from_str("3").unwrap());
+            assert_eq!(product, Fr::one());
+            cs.set("inverse num", Fr::from_str("3").unwrap());
+            assert_eq!(cs.which_is_unsatisfied(), Some("inverse constraint"));
+        }
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::zero())).unwrap();
+            assert!(n.inverse(&mut cs).is_err());
+        }
+    }
+
+    #[test]
+    fn test_num_division() {
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("12").unwrap()))
+                .unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from_str("4").unwrap()))
+                .unwrap();
+            let c = a.div(cs.namespace(|| "a div b"), &b).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(c.value.unwrap(), Fr::from_str("3").unwrap());
+            cs.set("a div b/quotient/product num", Fr::from_str("4").unwrap());
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("a div b/quotient/multiplication constraint")
+            );
+        }
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("12").unwrap()))
+                .unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::zero())).unwrap();
+            assert!(a.div(cs.namespace(|| "a div b"), &b).is_err());
+        }
+    }
+
+    #[test]
+    fn test_num_is_zero() {
+        for &value in &["0", "1", "5"] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::from_str(value).unwrap())).unwrap();
+            let is_zero = n.is_zero(&mut cs).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(is_zero.get_value().unwrap(), value == "0");
+
+            // Claiming the opposite result is unsatisfiable
+            // whatever the pseudo-inverse is
+            let flipped = if value == "0" { Fr::zero() } else { Fr::one() };
+            cs.set("is zero/boolean", flipped);
+            assert!(!cs.is_satisfied());
+            cs.set("pseudo-inverse", Fr::zero());
+            assert!(!cs.is_satisfied());
+            cs.set("pseudo-inverse", Fr::one());
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_num_equals() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for i in 0..20 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a_value = Fr::random(&mut rng);
+            let b_value = if i % 2 == 0 {
+                a_value
+            } else {
+                Fr::random(&mut rng)
+            };
+
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(a_value)).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(b_value)).unwrap();
+            let eq = AllocatedNum::equals(&mut cs, &a, &b).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(eq.get_value().unwrap(), i % 2 == 0);
+
+            cs.set(
+                "is zero/boolean",
+                if i % 2 == 0 { Fr::zero() } else { Fr::one() },
+            );
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_num_conditional_select() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for &condition_value in &[false, true] {
+            for &allocated in &[false, true] {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                let a =
+                    AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+                let b =
+                    AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
+                let condition = if allocated {
+                    Boolean::from(
+                        AllocatedBit::alloc(cs.namespace(|| "condition"), Some(condition_value))
+                            .unwrap(),
+                    )
+                } else {
+                    Boolean::constant(condition_value)
+                };
+
+                let c = AllocatedNum::conditionally_select(&mut cs, &a, &b, &condition).unwrap();
+
+                assert!(cs.is_satisfied());
+                if condition_value {
+                    assert_eq!(c.value.unwrap(), a.value.unwrap());
+                } else {
+                    assert_eq!(c.value.unwrap(), b.value.unwrap());
+                }
+
+                cs.set(
+                    "conditional select result/num",
+                    if condition_value {
+                        b.value.unwrap()
+                    } else {
+                        a.value.unwrap()
+                    },
+                );
+                assert_eq!(
+                    cs.which_is_unsatisfied(),
+                    Some("conditional select constraint")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_pow() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for &exp in &[0u64, 1, 2, 3, 5, 17, 0x1234_5678_9abc_def0] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let x = Fr::random(&mut rng);
+            let n = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+            let res = n.pow(cs.namespace(|| "pow"), &[exp]).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(res.value.unwrap(), x.pow(&[exp]));
+
+            if exp > 1 {
+                cs.set("x/num", Fr::random(&mut rng));
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_ops_without_values() {
+        use crate::util_cs::bench_cs::BenchCS;
+        use crate::SynthesisError;
+
+        // Synthesizing without witness values, as during parameter
+        // generation, must produce the same shape as with them.
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            mut cs: CS,
+            x: Option<Fr>,
+            y: Option<Fr>,
+            condition: Option<bool>,
+        ) -> Result<(), SynthesisError> {
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+                x.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+                y.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let condition = Boolean::from(AllocatedBit::alloc(
+                cs.namespace(|| "condition"),
+                condition,
+            )?);
+
+            x.inverse(cs.namespace(|| "inverse"))?;
+            x.div(cs.namespace(|| "div"), &y)?;
+            x.is_zero(cs.namespace(|| "is zero"))?;
+            AllocatedNum::equals(cs.namespace(|| "equals"), &x, &y)?;
+            AllocatedNum::conditionally_select(cs.namespace(|| "select"), &x, &y, &condition)?;
+            x.pow(cs.namespace(|| "pow"), &[5u64])?;
+
+            Ok(())
+        }
+
+        let mut test_cs = TestConstraintSystem::<Bls12>::new();
+        synthesize(
+            &mut test_cs,
+            Some(Fr::from_str("3").unwrap()),
+            Some(Fr::from_str("7").unwrap()),
+            Some(true),
+        )
+        .unwrap();
+        assert!(test_cs.is_satisfied());
+
+        let mut bench_cs = BenchCS::<Bls12>::new();
+        synthesize(&mut bench_cs, None, None, None).unwrap();
+
+        assert_eq!(bench_cs.num_constraints(), test_cs.num_constraints());
+    }
+
     #[test]
     fn test_into_bits_strict() {
         let mut negone = Fr::one();
@@ -557,6 +1315,8 @@ mod test {
         n.to_bits_le_strict(&mut cs).unwrap();
 
         assert!(cs.is_satisfied());
+        // The shape of the unpacking must not change
+        assert_eq!(cs.num_constraints(), 388);
 
         // make the bit representation the characteristic
         cs.set("bit 254/boolean", Fr::one());
@@ -0,0 +1,290 @@
+//! The Pedersen hash over the Jubjub curve, as used by Zcash Sapling. The
+//! input is split into 3-bit chunks, each selecting a multiple in
+//! `[-4, 4] \ {0}` of the generator of its segment.
+
+use ff::ScalarEngine;
+
+use super::boolean::Boolean;
+use super::ecc::{EdwardsPoint, MontgomeryPoint};
+use super::lookup::lookup3_xy_with_conditional_negation;
+use crate::jubjub::JubjubParams;
+use crate::{ConstraintSystem, SynthesisError};
+
+/// Computes the Pedersen hash of `bits`, prefixed by the constant
+/// `personalization` bits. The input must not be empty, and at most
+/// `3 * PEDERSEN_HASH_GENERATORS * PEDERSEN_HASH_CHUNKS_PER_GENERATOR`
+/// bits long.
+///
+/// The chunks of a segment are summed in Montgomery form, which is
+/// cheaper, as the sum cannot reach the exceptional cases of the
+/// incomplete addition law. The segments are then summed in Edwards form.
+pub fn pedersen_hash<E, CS>(
+    mut cs: CS,
+    personalization: &[bool],
+    bits: &[Boolean],
+    params: &JubjubParams<E>,
+) -> Result<EdwardsPoint<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let personalization: Vec<Boolean> = personalization
+        .iter()
+        .map(|b| Boolean::constant(*b))
+        .collect();
+
+    let boolean_false = Boolean::constant(false);
+    let mut bits = personalization.iter().chain(bits.iter()).peekable();
+    assert!(bits.peek().is_some(), "input must not be empty");
+
+    let mut segment_generators = params.pedersen_circuit_generators().iter();
+    let mut edwards_result: Option<EdwardsPoint<E>> = None;
+    let mut segment_i = 0;
+
+    while bits.peek().is_some() {
+        let segment_windows = segment_generators.next().expect("enough segments");
+        let mut segment_result: Option<MontgomeryPoint<E>> = None;
+
+        for (window_i, window) in segment_windows.iter().enumerate() {
+            let a = match bits.next() {
+                Some(a) => a,
+                None => break,
+            };
+            let b = bits.next().unwrap_or(&boolean_false);
+            let c = bits.next().unwrap_or(&boolean_false);
+
+            let (x, y) = lookup3_xy_with_conditional_negation(
+                cs.namespace(|| format!("segment {}, window {}", segment_i, window_i)),
+                &[a.clone(), b.clone(), c.clone()],
+                window,
+            )?;
+
+            let tmp = MontgomeryPoint::interpret_unchecked(x, y);
+
+            segment_result = Some(match segment_result {
+                None => tmp,
+                Some(segment_result) => segment_result.add(
+                    cs.namespace(|| {
+                        format!("addition of segment {}, window {}", segment_i, window_i)
+                    }),
+                    &tmp,
+                    params,
+                )?,
+            });
+        }
+
+        let segment_result = segment_result.expect("segment is not empty").into_edwards(
+            cs.namespace(|| format!("conversion of segment {} into edwards", segment_i)),
+            params,
+        )?;
+
+        edwards_result = Some(match edwards_result {
+            None => segment_result,
+            Some(edwards_result) => edwards_result.add(
+                cs.namespace(|| format!("addition of segment {} to accumulator", segment_i)),
+                &segment_result,
+                params,
+            )?,
+        });
+
+        segment_i += 1;
+    }
+
+    Ok(edwards_result.expect("input is not empty"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::pedersen_hash;
+    use crate::bls::Bls12;
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::multipack::bytes_to_bits_le;
+    use crate::gadgets::test::*;
+    use crate::jubjub::{self, test_vectors, JubjubParams};
+    use crate::ConstraintSystem;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    /// Predicts the number of constraints of a Pedersen hash, as in
+    /// `zcash_proofs`.
+    fn ph_num_constraints(input_bits: usize) -> usize {
+        // Account for the 6 personalization bits
+        let personalized_bits = 6 + input_bits;
+        // The lookups of chunks whose first two bits are constant need no
+        // "precomp" constraint
+        let precomputed_booleans = 2 + (personalized_bits % 3 == 1) as usize;
+
+        let chunks = (personalized_bits + 3 - 1) / 3;
+        let segments = (chunks + 63 - 1) / 63;
+        let all_but_last_segments = segments - 1;
+        let last_chunks = chunks - all_but_last_segments * 63;
+
+        let lookup_chunk = 2;
+        let add_chunks = 3; // Montgomery addition
+        let convert_segment = 2; // Conversion to Edwards
+        let add_segments = 6; // Edwards addition
+
+        chunks * lookup_chunk - precomputed_booleans
+            + segments * convert_segment
+            + all_but_last_segments * ((63 - 1) * add_chunks + add_segments)
+            + (last_chunks - 1) * add_chunks
+    }
+
+    #[test]
+    fn test_pedersen_hash_constraints() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = JubjubParams::<Bls12>::new();
+
+        let leaves_len = 2 * 255;
+        let note_len = 64 + 256 + 256;
+
+        for &n_bits in [
+            0,
+            3 * 63 - 6,
+            3 * 63 - 6 + 1,
+            3 * 63 - 6 + 2,
+            510 - 6,
+            leaves_len,
+            note_len,
+        ]
+        .iter()
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let input: Vec<bool> = (0..n_bits).map(|_| rng.next_u32() % 2 != 0).collect();
+            let input_bools: Vec<Boolean> = input
+                .iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    Boolean::from(
+                        AllocatedBit::alloc(cs.namespace(|| format!("input {}", i)), Some(*b))
+                            .unwrap(),
+                    )
+                })
+                .collect();
+
+            pedersen_hash(
+                cs.namespace(|| "pedersen hash"),
+                &[true; 6],
+                &input_bools,
+                &params,
+            )
+            .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), n_bits + ph_num_constraints(n_bits));
+
+            // The counts of zcash_proofs for its actual usages
+            if n_bits == 510 - 6 {
+                assert_eq!(cs.num_constraints(), 1361);
+            }
+            if n_bits == leaves_len {
+                assert_eq!(cs.num_constraints(), leaves_len + 867);
+            }
+            if n_bits == note_len {
+                assert_eq!(cs.num_constraints(), note_len + 982);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pedersen_hash_test_vectors() {
+        let params = JubjubParams::<Bls12>::new();
+
+        for v in test_vectors::get_vectors() {
+            let bits: Vec<bool> = v.input_bits.iter().map(|&b| b == 1).collect();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let input_bools: Vec<Boolean> = bits[6..]
+                .iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    Boolean::from(
+                        AllocatedBit::alloc(cs.namespace(|| format!("input {}", i)), Some(*b))
+                            .unwrap(),
+                    )
+                })
+                .collect();
+
+            let res = pedersen_hash(
+                cs.namespace(|| "pedersen hash"),
+                &bits[..6],
+                &input_bools,
+                &params,
+            )
+            .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(
+                res.get_x().get_value(),
+                Some(test_vectors::fr_from_hex(v.hash_u))
+            );
+            assert_eq!(
+                res.get_y().get_value(),
+                Some(test_vectors::fr_from_hex(v.hash_v))
+            );
+        }
+    }
+
+    #[test]
+    fn test_pedersen_hash() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = JubjubParams::<Bls12>::new();
+
+        // Cover the first segment boundary, and the last segment
+        for length in (0..30).chain(185..195).chain(1120..1128) {
+            let input: Vec<bool> = (0..length).map(|_| rng.next_u32() % 2 != 0).collect();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let input_bools: Vec<Boolean> = input
+                .iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    Boolean::from(
+                        AllocatedBit::alloc(cs.namespace(|| format!("input {}", i)), Some(*b))
+                            .unwrap(),
+                    )
+                })
+                .collect();
+
+            let res = pedersen_hash(
+                cs.namespace(|| "pedersen hash"),
+                &[false, true, false, false, true, true],
+                &input_bools,
+                &params,
+            )
+            .unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let expected =
+                jubjub::pedersen_hash(&[false, true, false, false, true, true], &input, &params);
+            assert_eq!(res.get_value(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_pedersen_hash_constant_input() {
+        let params = JubjubParams::<Bls12>::new();
+
+        let input = bytes_to_bits_le(b"bellperson");
+        let input_bools: Vec<Boolean> = input.iter().map(|b| Boolean::constant(*b)).collect();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let res = pedersen_hash(&mut cs, &[true; 6], &input_bools, &params).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(
+            res.get_value(),
+            Some(jubjub::pedersen_hash(&[true; 6], &input, &params))
+        );
+    }
+}
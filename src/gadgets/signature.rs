@@ -0,0 +1,375 @@
+//! Gadgets verifying the EdDSA and Schnorr signatures over Jubjub of
+//! [`crate::jubjub::signature`], with BLAKE2s challenges and a fixed-base
+//! multiplication of the signature generator.
+//!
+//! The response `s` is given as at most `SCALAR_BITS` little-endian bits.
+//! It is not checked to be reduced, so a signature remains valid when
+//! `s` is replaced by `s + r` for the subgroup order `r`, as long as it
+//! fits: statements must not rely on the uniqueness of signatures.
+//!
+//! The public key is enforced not to be of small order: otherwise `c.A`
+//! would vanish after multiplication by the cofactor, and with `A` the
+//! identity, any `(R, s)` with `R = s.G` would verify for any message.
+
+use ff::ScalarEngine;
+
+use super::blake2s::blake2s;
+use super::boolean::Boolean;
+use super::ecc::{fixed_base_multiplication, EdwardsPoint};
+use crate::jubjub::signature::{EDDSA_PERSONALIZATION, SCHNORR_PERSONALIZATION};
+use crate::jubjub::{JubjubParams, SCALAR_BITS};
+use crate::{ConstraintSystem, SynthesisError};
+
+/// Computes the challenge `H(R || A || msg)`, as 256 little-endian bits.
+fn challenge<E, CS>(
+    mut cs: CS,
+    personalization: &[u8; 8],
+    r: &EdwardsPoint<E>,
+    public_key: &EdwardsPoint<E>,
+    msg: &[Boolean],
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let mut input = r.repr(cs.namespace(|| "R representation"))?;
+    input.extend(public_key.repr(cs.namespace(|| "A representation"))?);
+    input.extend(msg.iter().cloned());
+
+    blake2s(cs.namespace(|| "hash"), &input, personalization)
+}
+
+/// Enforces that the EdDSA signature `(r, s)` of `msg` by `public_key` is
+/// valid, i.e. that `s.G = R + H(R || A || msg).A` and that `A` is not of
+/// small order. The message must be a whole number of bytes, each in
+/// little-endian bit order.
+pub fn verify_eddsa<E, CS>(
+    mut cs: CS,
+    public_key: &EdwardsPoint<E>,
+    r: &EdwardsPoint<E>,
+    s: &[Boolean],
+    msg: &[Boolean],
+    params: &JubjubParams<E>,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(s.len() <= SCALAR_BITS);
+
+    public_key.assert_not_small_order(cs.namespace(|| "A not small order"), params)?;
+
+    let c = challenge(
+        cs.namespace(|| "challenge"),
+        EDDSA_PERSONALIZATION,
+        r,
+        public_key,
+        msg,
+    )?;
+
+    let lhs = fixed_base_multiplication(
+        cs.namespace(|| "s.G"),
+        params.signature_generator_table(),
+        s,
+        params,
+    )?;
+
+    let ca = public_key.mul(cs.namespace(|| "c.A"), &c, params)?;
+    let rhs = r.add(cs.namespace(|| "R + c.A"), &ca, params)?;
+
+    enforce_equal_points(cs.namespace(|| "s.G = R + c.A"), &lhs, &rhs);
+
+    Ok(())
+}
+
+/// Enforces that the Schnorr signature `(c, s)` of `msg` by `public_key`
+/// is valid, i.e. that `c = H(R || A || msg)` for `R = s.G + c.A` and that
+/// `A` is not of small order. The challenge is given as the 256
+/// little-endian bits of the hash, and the message as for
+/// [`verify_eddsa`].
+pub fn verify_schnorr<E, CS>(
+    mut cs: CS,
+    public_key: &EdwardsPoint<E>,
+    c: &[Boolean],
+    s: &[Boolean],
+    msg: &[Boolean],
+    params: &JubjubParams<E>,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(c.len(), 256);
+    assert!(s.len() <= SCALAR_BITS);
+
+    public_key.assert_not_small_order(cs.namespace(|| "A not small order"), params)?;
+
+    let sg = fixed_base_multiplication(
+        cs.namespace(|| "s.G"),
+        params.signature_generator_table(),
+        s,
+        params,
+    )?;
+
+    let ca = public_key.mul(cs.namespace(|| "c.A"), c, params)?;
+    let r = sg.add(cs.namespace(|| "R = s.G + c.A"), &ca, params)?;
+
+    let expected = challenge(
+        cs.namespace(|| "challenge"),
+        SCHNORR_PERSONALIZATION,
+        &r,
+        public_key,
+        msg,
+    )?;
+
+    Boolean::enforce_equal_vectors(cs.namespace(|| "challenge check"), &expected, c)
+}
+
+fn enforce_equal_points<E, CS>(mut cs: CS, a: &EdwardsPoint<E>, b: &EdwardsPoint<E>)
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    cs.enforce(
+        || "x equality",
+        |lc| lc + a.get_x().get_variable() - b.get_x().get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc,
+    );
+    cs.enforce(
+        || "y equality",
+        |lc| lc + a.get_y().get_variable() - b.get_y().get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc,
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::multipack::bytes_to_bits_le;
+    use crate::gadgets::test::*;
+    use crate::jubjub::signature::{PrivateKey, Scalar};
+    use crate::jubjub::Point;
+    use crate::{LinearCombination, Variable};
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// A prover which assigns one to the variables it cannot compute, such
+    /// as the inverse of zero, instead of giving up.
+    struct Forger(TestConstraintSystem<Bls12>);
+
+    impl ConstraintSystem<Bls12> for Forger {
+        type Root = Self;
+
+        fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+        where
+            F: FnOnce() -> Result<Fr, SynthesisError>,
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            self.0
+                .alloc(annotation, || Ok(f().unwrap_or_else(|_| Fr::one())))
+        }
+
+        fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+        where
+            F: FnOnce() -> Result<Fr, SynthesisError>,
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            self.0
+                .alloc_input(annotation, || Ok(f().unwrap_or_else(|_| Fr::one())))
+        }
+
+        fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+            LA: FnOnce(LinearCombination<Bls12>) -> LinearCombination<Bls12>,
+            LB: FnOnce(LinearCombination<Bls12>) -> LinearCombination<Bls12>,
+            LC: FnOnce(LinearCombination<Bls12>) -> LinearCombination<Bls12>,
+        {
+            self.0.enforce(annotation, a, b, c)
+        }
+
+        fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where
+            NR: Into<String>,
+            N: FnOnce() -> NR,
+        {
+            self.0.push_namespace(name_fn)
+        }
+
+        fn pop_namespace(&mut self) {
+            self.0.pop_namespace()
+        }
+
+        fn get_root(&mut self) -> &mut Self::Root {
+            self
+        }
+    }
+
+    fn alloc_bits<CS: ConstraintSystem<Bls12>>(mut cs: CS, bits: &[bool]) -> Vec<Boolean> {
+        bits.iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b)).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_eddsa() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = JubjubParams::<Bls12>::new();
+
+        let sk = PrivateKey::random(&mut rng);
+        let pk = sk.public_key(&params);
+        let signature = sk.sign_eddsa(b"bellperson", &mut rng, &params);
+
+        let forged_s = signature.s.add(&Scalar::from_bytes_wide(&[1]));
+        for (msg, s, valid) in &[
+            (&b"bellperson"[..], &signature.s, true),
+            (&b"bellpersoN"[..], &signature.s, false),
+            (&b"bellperson"[..], &forged_s, false),
+        ] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = EdwardsPoint::witness(cs.namespace(|| "A"), Some(pk.0), &params).unwrap();
+            let r =
+                EdwardsPoint::witness(cs.namespace(|| "R"), Some(signature.r), &params).unwrap();
+            let s = alloc_bits(cs.namespace(|| "s"), &s.to_bits_le());
+            let msg = alloc_bits(cs.namespace(|| "msg"), &bytes_to_bits_le(msg));
+
+            verify_eddsa(cs.namespace(|| "verify"), &a, &r, &s, &msg, &params).unwrap();
+
+            assert_eq!(cs.is_satisfied(), *valid);
+            if !valid {
+                assert_eq!(
+                    cs.which_is_unsatisfied(),
+                    Some("verify/s.G = R + c.A/x equality")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_schnorr() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = JubjubParams::<Bls12>::new();
+
+        let sk = PrivateKey::random(&mut rng);
+        let pk = sk.public_key(&params);
+        let signature = sk.sign_schnorr(b"bellperson", &mut rng, &params);
+
+        let forged_s = signature.s.add(&Scalar::from_bytes_wide(&[1]));
+        for (msg, s, valid) in &[
+            (&b"bellperson"[..], &signature.s, true),
+            (&b"bellpersoN"[..], &signature.s, false),
+            (&b"bellperson"[..], &forged_s, false),
+        ] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = EdwardsPoint::witness(cs.namespace(|| "A"), Some(pk.0), &params).unwrap();
+            let c = alloc_bits(cs.namespace(|| "c"), &bytes_to_bits_le(&signature.c));
+            let s = alloc_bits(cs.namespace(|| "s"), &s.to_bits_le());
+            let msg = alloc_bits(cs.namespace(|| "msg"), &bytes_to_bits_le(msg));
+
+            verify_schnorr(cs.namespace(|| "verify"), &a, &c, &s, &msg, &params).unwrap();
+
+            assert_eq!(cs.is_satisfied(), *valid);
+            if !valid {
+                assert_eq!(
+                    cs.which_is_unsatisfied(),
+                    Some("verify/challenge check/chunk 0")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_eddsa_small_order_key() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = JubjubParams::<Bls12>::new();
+
+        // c.A vanishes for the identity, so that s.G = R + c.A holds for any
+        // message when R = s.G
+        let s = Scalar::random(&mut rng);
+        let r = s.mul_point(params.signature_generator(), &params);
+
+        let mut cs = Forger(TestConstraintSystem::new());
+
+        let a =
+            EdwardsPoint::witness(cs.namespace(|| "A"), Some(Point::identity()), &params).unwrap();
+        let r = EdwardsPoint::witness(cs.namespace(|| "R"), Some(r), &params).unwrap();
+        let s = alloc_bits(cs.namespace(|| "s"), &s.to_bits_le());
+        let msg = alloc_bits(cs.namespace(|| "msg"), &bytes_to_bits_le(b"bellperson"));
+
+        verify_eddsa(cs.namespace(|| "verify"), &a, &r, &s, &msg, &params).unwrap();
+
+        assert!(!cs.0.is_satisfied());
+        assert_eq!(cs.0.unsatisfied_constraints().len(), 1);
+        assert_eq!(
+            cs.0.which_is_unsatisfied(),
+            Some("verify/A not small order/check x != 0/nonzero assertion constraint")
+        );
+
+        // An honest prover cannot even compute the witness
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let a =
+            EdwardsPoint::witness(cs.namespace(|| "A"), Some(Point::identity()), &params).unwrap();
+        assert!(matches!(
+            a.assert_not_small_order(cs.namespace(|| "A not small order"), &params),
+            Err(SynthesisError::DivisionByZero)
+        ));
+    }
+
+    #[test]
+    fn test_verify_schnorr_small_order_key() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = JubjubParams::<Bls12>::new();
+
+        // With the identity as the key, R = s.G and c = H(R || A || msg)
+        // would verify for any s
+        let sk = PrivateKey(Scalar::from_bytes_wide(&[0]));
+        let signature = sk.sign_schnorr(b"bellperson", &mut rng, &params);
+
+        let mut cs = Forger(TestConstraintSystem::new());
+
+        let a =
+            EdwardsPoint::witness(cs.namespace(|| "A"), Some(Point::identity()), &params).unwrap();
+        let c = alloc_bits(cs.namespace(|| "c"), &bytes_to_bits_le(&signature.c));
+        let s = alloc_bits(cs.namespace(|| "s"), &signature.s.to_bits_le());
+        let msg = alloc_bits(cs.namespace(|| "msg"), &bytes_to_bits_le(b"bellperson"));
+
+        verify_schnorr(cs.namespace(|| "verify"), &a, &c, &s, &msg, &params).unwrap();
+
+        assert_eq!(cs.0.unsatisfied_constraints().len(), 1);
+        assert_eq!(
+            cs.0.which_is_unsatisfied(),
+            Some("verify/A not small order/check x != 0/nonzero assertion constraint")
+        );
+    }
+}
@@ -2,10 +2,11 @@
 
 use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
 
+use crate::util_cs::export::Export;
 use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
 
 use std::collections::HashMap;
-use std::fmt::Write;
+use std::fmt::{self, Write};
 
 use byteorder::{BigEndian, ByteOrder};
 use std::cmp::Ordering;
@@ -35,6 +36,32 @@ pub struct TestConstraintSystem<E: ScalarEngine> {
     aux: Vec<(E::Fr, String)>,
 }
 
+/// A constraint which is not satisfied by the assignment, with the values
+/// of its linear combinations and of the variables involved.
+pub struct UnsatisfiedConstraint<E: ScalarEngine> {
+    pub path: String,
+    pub a: E::Fr,
+    pub b: E::Fr,
+    pub c: E::Fr,
+    /// The paths and assignments of the variables with a nonzero
+    /// coefficient in the constraint, inputs first.
+    pub variables: Vec<(String, E::Fr)>,
+}
+
+impl<E: ScalarEngine> fmt::Display for UnsatisfiedConstraint<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "`{}`: A * B != C", self.path)?;
+        writeln!(f, "  A = {}", self.a)?;
+        writeln!(f, "  B = {}", self.b)?;
+        writeln!(f, "  C = {}", self.c)?;
+        for (path, value) in &self.variables {
+            writeln!(f, "  `{}` = {}", path, value)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Clone, Copy)]
 struct OrderedVariable(Variable);
 
@@ -66,7 +93,7 @@ impl Ord for OrderedVariable {
 
 fn proc_lc<E: ScalarEngine>(terms: &LinearCombination<E>) -> BTreeMap<OrderedVariable, E::Fr> {
     let mut map = BTreeMap::new();
-    for (&var, &coeff) in terms.0.iter() {
+    for (&var, &coeff) in terms.iter() {
         map.entry(OrderedVariable(var))
             .or_insert_with(E::Fr::zero)
             .add_assign(&coeff);
@@ -119,7 +146,7 @@ fn eval_lc<E: ScalarEngine>(
 ) -> E::Fr {
     let mut acc = E::Fr::zero();
 
-    for (&var, coeff) in terms.0.iter() {
+    for (&var, coeff) in terms.iter() {
         let mut tmp = match var.get_unchecked() {
             Index::Input(index) => inputs[index].0,
             Index::Aux(index) => aux[index].0,
@@ -132,7 +159,51 @@ fn eval_lc<E: ScalarEngine>(
     acc
 }
 
+impl<E: ScalarEngine> Default for TestConstraintSystem<E> {
+    fn default() -> Self {
+        let mut map = HashMap::new();
+        map.insert(
+            "ONE".into(),
+            NamedObject::Var(TestConstraintSystem::<E>::one()),
+        );
+
+        TestConstraintSystem {
+            named_objects: map,
+            current_namespace: vec![],
+            constraints: vec![],
+            inputs: vec![(E::Fr::one(), "ONE".into())],
+            aux: vec![],
+        }
+    }
+}
+
 impl<E: ScalarEngine> TestConstraintSystem<E> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Lists the paths of the inputs, the auxiliary variables and the
+    /// constraints, in this order.
+    pub fn pretty_print_list(&self) -> Vec<String> {
+        let mut result = Vec::new();
+
+        for input in &self.inputs {
+            result.push(format!("INPUT {}", input.1));
+        }
+        for aux in &self.aux {
+            result.push(format!("AUX {}", aux.1));
+        }
+
+        for &(ref _a, ref _b, ref _c, ref name) in &self.constraints {
+            result.push(name.to_string());
+        }
+
+        result
+    }
+
+    /// Prints each constraint with its linear combinations. Before the test
+    /// constraint systems were merged, `util_cs::test_cs` printed the list
+    /// of paths instead, which is now `pretty_print_list().join("\n")`.
     pub fn pretty_print(&self) -> String {
         let mut s = String::new();
 
@@ -200,6 +271,15 @@ impl<E: ScalarEngine> TestConstraintSystem<E> {
         s
     }
 
+    /// Exports the constraints with the paths of their variables.
+    pub fn export(&self) -> Export {
+        Export::new(
+            self.inputs.iter().map(|(_, path)| path.clone()).collect(),
+            self.aux.iter().map(|(_, path)| path.clone()).collect(),
+            &self.constraints,
+        )
+    }
+
     pub fn hash(&self) -> String {
         let mut h = Blake2sParams::new().hash_length(32).to_state();
         {
@@ -241,6 +321,58 @@ impl<E: ScalarEngine> TestConstraintSystem<E> {
         None
     }
 
+    /// Returns every constraint which is not satisfied, in the order they
+    /// were enforced.
+    pub fn unsatisfied_constraints(&self) -> Vec<UnsatisfiedConstraint<E>> {
+        let mut unsatisfied = vec![];
+
+        for &(ref a, ref b, ref c, ref path) in &self.constraints {
+            let a_value = eval_lc::<E>(a, &self.inputs, &self.aux);
+            let b_value = eval_lc::<E>(b, &self.inputs, &self.aux);
+            let c_value = eval_lc::<E>(c, &self.inputs, &self.aux);
+
+            let mut product = a_value;
+            product.mul_assign(&b_value);
+
+            if product == c_value {
+                continue;
+            }
+
+            let mut vars = proc_lc::<E>(a);
+            vars.extend(proc_lc::<E>(b));
+            vars.extend(proc_lc::<E>(c));
+
+            let variables = vars
+                .keys()
+                .map(|var| match var.0.get_unchecked() {
+                    Index::Input(i) => (self.inputs[i].1.clone(), self.inputs[i].0),
+                    Index::Aux(i) => (self.aux[i].1.clone(), self.aux[i].0),
+                })
+                .collect();
+
+            unsatisfied.push(UnsatisfiedConstraint {
+                path: path.clone(),
+                a: a_value,
+                b: b_value,
+                c: c_value,
+                variables,
+            });
+        }
+
+        unsatisfied
+    }
+
+    /// Describes every constraint which is not satisfied, or returns an
+    /// empty string if the assignment satisfies the constraint system.
+    pub fn unsatisfied_report(&self) -> String {
+        let mut s = String::new();
+        for constraint in self.unsatisfied_constraints() {
+            write!(&mut s, "{}", constraint).unwrap();
+        }
+
+        s
+    }
+
     pub fn is_satisfied(&self) -> bool {
         self.which_is_unsatisfied().is_none()
     }
@@ -287,6 +419,14 @@ impl<E: ScalarEngine> TestConstraintSystem<E> {
         assignment
     }
 
+    pub fn get_inputs(&self) -> &[(E::Fr, String)] {
+        &self.inputs[..]
+    }
+
+    pub fn get_aux(&self) -> &[(E::Fr, String)] {
+        &self.aux[..]
+    }
+
     pub fn get(&mut self, path: &str) -> E::Fr {
         match self.named_objects.get(path) {
             Some(&NamedObject::Var(ref v)) => match v.get_unchecked() {
@@ -310,43 +450,25 @@ impl<E: ScalarEngine> TestConstraintSystem<E> {
     }
 }
 
-fn compute_path(ns: &[String], this: String) -> String {
-    if this.chars().any(|a| a == '/') {
-        panic!("'/' is not allowed in names");
-    }
-
-    let mut name = String::new();
-
-    let mut needs_separation = false;
-    for ns in ns.iter().chain(Some(&this).into_iter()) {
-        if needs_separation {
-            name += "/";
-        }
+fn compute_path(ns: &[String], this: &str) -> String {
+    assert!(
+        !this.chars().any(|a| a == '/'),
+        "'/' is not allowed in names"
+    );
 
-        name += ns;
-        needs_separation = true;
+    if ns.is_empty() {
+        return this.to_string();
     }
 
-    name
+    let name = ns.join("/");
+    format!("{}/{}", name, this)
 }
 
 impl<E: ScalarEngine> ConstraintSystem<E> for TestConstraintSystem<E> {
     type Root = Self;
 
     fn new() -> TestConstraintSystem<E> {
-        let mut map = HashMap::new();
-        map.insert(
-            "ONE".into(),
-            NamedObject::Var(TestConstraintSystem::<E>::one()),
-        );
-
-        TestConstraintSystem {
-            named_objects: map,
-            current_namespace: vec![],
-            constraints: vec![],
-            inputs: vec![(E::Fr::one(), "ONE".into())],
-            aux: vec![],
-        }
+        Default::default()
     }
 
     fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
@@ -356,7 +478,7 @@ impl<E: ScalarEngine> ConstraintSystem<E> for TestConstraintSystem<E> {
         AR: Into<String>,
     {
         let index = self.aux.len();
-        let path = compute_path(&self.current_namespace, annotation().into());
+        let path = compute_path(&self.current_namespace, &annotation().into());
         self.aux.push((f()?, path.clone()));
         let var = Variable::new_unchecked(Index::Aux(index));
         self.set_named_obj(path, NamedObject::Var(var));
@@ -371,7 +493,7 @@ impl<E: ScalarEngine> ConstraintSystem<E> for TestConstraintSystem<E> {
         AR: Into<String>,
     {
         let index = self.inputs.len();
-        let path = compute_path(&self.current_namespace, annotation().into());
+        let path = compute_path(&self.current_namespace, &annotation().into());
         self.inputs.push((f()?, path.clone()));
         let var = Variable::new_unchecked(Index::Input(index));
         self.set_named_obj(path, NamedObject::Var(var));
@@ -387,7 +509,7 @@ impl<E: ScalarEngine> ConstraintSystem<E> for TestConstraintSystem<E> {
         LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
         LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
     {
-        let path = compute_path(&self.current_namespace, annotation().into());
+        let path = compute_path(&self.current_namespace, &annotation().into());
         let index = self.constraints.len();
         self.set_named_obj(path.clone(), NamedObject::Constraint(index));
 
@@ -404,7 +526,7 @@ impl<E: ScalarEngine> ConstraintSystem<E> for TestConstraintSystem<E> {
         N: FnOnce() -> NR,
     {
         let name = name_fn().into();
-        let path = compute_path(&self.current_namespace, name.clone());
+        let path = compute_path(&self.current_namespace, &name);
         self.set_named_obj(path, NamedObject::Namespace);
         self.current_namespace.push(name);
     }
@@ -418,6 +540,22 @@ impl<E: ScalarEngine> ConstraintSystem<E> for TestConstraintSystem<E> {
     }
 }
 
+#[test]
+fn test_compute_path() {
+    assert_eq!(
+        compute_path(
+            &[
+                "hello".to_string(),
+                "world".to_string(),
+                "things".to_string()
+            ],
+            "thing"
+        ),
+        "hello/world/things/thing"
+    );
+    assert_eq!(compute_path(&[], "thing"), "thing");
+}
+
 #[test]
 fn test_cs() {
     use crate::bls::{Bls12, Fr};
@@ -450,10 +588,35 @@ fn test_cs() {
     assert!(!cs.is_satisfied());
     assert!(cs.which_is_unsatisfied() == Some("mult"));
 
+    let unsatisfied = cs.unsatisfied_constraints();
+    assert_eq!(unsatisfied.len(), 1);
+    assert_eq!(unsatisfied[0].path, "mult");
+    assert!(unsatisfied[0].a == Fr::from_str("4").unwrap());
+    assert!(unsatisfied[0].b == Fr::from_str("4").unwrap());
+    assert!(unsatisfied[0].c == Fr::from_str("40").unwrap());
+    assert_eq!(
+        unsatisfied[0]
+            .variables
+            .iter()
+            .map(|(path, _)| &path[..])
+            .collect::<Vec<_>>(),
+        vec!["a/var", "b/var", "product"]
+    );
+
+    let report = cs.unsatisfied_report();
+    assert!(report.starts_with("`mult`: A * B != C\n"));
+    assert!(!report.contains("`eq`"));
+    assert!(report.contains(&format!(
+        "\n  `product` = {}\n",
+        Fr::from_str("40").unwrap()
+    )));
+
     assert!(cs.get("product") == Fr::from_str("40").unwrap());
 
     cs.set("product", Fr::from_str("16").unwrap());
     assert!(cs.is_satisfied());
+    assert!(cs.unsatisfied_constraints().is_empty());
+    assert_eq!(cs.unsatisfied_report(), "");
 
     {
         let mut cs = cs.namespace(|| "test1");
@@ -462,4 +625,19 @@ fn test_cs() {
     }
 
     assert!(cs.get("test1/test2/hehe") == Fr::one());
+
+    assert_eq!(cs.get_inputs().len(), 1);
+    assert_eq!(cs.get_inputs()[0].1, "ONE");
+    assert_eq!(
+        cs.pretty_print_list(),
+        vec![
+            "INPUT ONE",
+            "AUX a/var",
+            "AUX b/var",
+            "AUX product",
+            "AUX test1/test2/hehe",
+            "mult",
+            "eq"
+        ]
+    );
 }
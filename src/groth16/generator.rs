@@ -67,14 +67,15 @@ impl<E: Engine> ConstraintSystem<E> for KeypairAssembly<E> {
         }
     }
 
-    /// Explicitly declare this `ConstraintSystem` is not extensible as a reminder to future implementers.
-    /// By forbidding use of `ConstraintSystem::extend` when generating Groth parameters, we enforce
-    /// the requirement of a well-defined sequential circuit synthesis. This also means we know that any
-    /// synthesized `ProvingAssignment` is well-formed if it leads to a verifiable proof using the resulting
-    /// groth parameters and verifying key. This is true even if the `ProvingAssignment` was synthesized
-    /// in parallel components which were then joined by `ConstraintSystem::extend`.
+    /// Parameters may be generated for circuits synthesized in parallel
+    /// components, such as with [`synthesize_parallel`](crate::synthesize_parallel),
+    /// as long as the components are joined with `ConstraintSystem::extend`
+    /// in the order in which they would have been synthesized sequentially.
+    /// The resulting QAP is then the same as for the sequential synthesis, so
+    /// that any `ProvingAssignment` synthesized in the same way leads to a
+    /// verifiable proof.
     fn is_extensible() -> bool {
-        false
+        true
     }
 
     fn alloc<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
@@ -174,6 +175,44 @@ impl<E: Engine> ConstraintSystem<E> for KeypairAssembly<E> {
     fn get_root(&mut self) -> &mut Self::Root {
         self
     }
+
+    fn extend(&mut self, other: Self) {
+        assert!(self.num_inputs > 0 && other.num_inputs > 0);
+
+        let offset = self.num_constraints;
+        for (own, other) in vec![
+            (&mut self.at_inputs, other.at_inputs),
+            (&mut self.bt_inputs, other.bt_inputs),
+            (&mut self.ct_inputs, other.ct_inputs),
+        ] {
+            // The first input of `other` is its own "one" variable, which is
+            // merged with ours.
+            let mut other = shift_queries(other, offset);
+            own[0].extend(other.next().unwrap());
+            own.extend(other);
+        }
+        self.at_aux.extend(shift_queries(other.at_aux, offset));
+        self.bt_aux.extend(shift_queries(other.bt_aux, offset));
+        self.ct_aux.extend(shift_queries(other.ct_aux, offset));
+
+        self.num_inputs += other.num_inputs - 1;
+        self.num_aux += other.num_aux;
+        self.num_constraints += other.num_constraints;
+    }
+}
+
+/// Shifts the queries of an assembly whose constraints are to follow the
+/// first `offset` ones.
+fn shift_queries<F>(
+    queries: Vec<Vec<(F, usize)>>,
+    offset: usize,
+) -> impl Iterator<Item = Vec<(F, usize)>> {
+    queries.into_iter().map(move |terms| {
+        terms
+            .into_iter()
+            .map(|(coeff, constraint)| (coeff, constraint + offset))
+            .collect()
+    })
 }
 
 /// Create parameters for a circuit, given some toxic waste.
@@ -492,3 +531,63 @@ where
         ),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+
+    // Allocates `n` inputs and auxiliary variables, constrained together.
+    fn synthesize_part(cs: &mut KeypairAssembly<Bls12>, n: usize) {
+        for i in 0..n {
+            let x = cs.alloc(|| format!("x {}", i), || Ok(Fr::one())).unwrap();
+            let y = cs
+                .alloc_input(|| format!("y {}", i), || Ok(Fr::one()))
+                .unwrap();
+            cs.enforce(
+                || "x * (y + 1) = x + y",
+                |lc| lc + x,
+                |lc| lc + y + KeypairAssembly::<Bls12>::one(),
+                |lc| lc + x + y,
+            );
+        }
+    }
+
+    fn queries(cs: &KeypairAssembly<Bls12>) -> Vec<&Vec<Vec<(Fr, usize)>>> {
+        vec![
+            &cs.at_inputs,
+            &cs.bt_inputs,
+            &cs.ct_inputs,
+            &cs.at_aux,
+            &cs.bt_aux,
+            &cs.ct_aux,
+        ]
+    }
+
+    #[test]
+    fn test_keypair_assembly_extend() {
+        let sizes = [3, 1, 0, 5];
+
+        let mut sequential = KeypairAssembly::<Bls12>::new();
+        sequential.alloc_input(|| "", || Ok(Fr::one())).unwrap();
+        for n in &sizes {
+            synthesize_part(&mut sequential, *n);
+        }
+
+        let mut combined = KeypairAssembly::<Bls12>::new();
+        combined.alloc_input(|| "", || Ok(Fr::one())).unwrap();
+        for n in &sizes {
+            let mut part = KeypairAssembly::new();
+            part.alloc_input(|| "", || Ok(Fr::one())).unwrap();
+            synthesize_part(&mut part, *n);
+
+            combined.extend(part);
+        }
+
+        assert_eq!(combined.num_inputs, sequential.num_inputs);
+        assert_eq!(combined.num_aux, sequential.num_aux);
+        assert_eq!(combined.num_constraints, sequential.num_constraints);
+        assert_eq!(queries(&combined), queries(&sequential));
+    }
+}
@@ -5,7 +5,7 @@
 // The `DummyEngine` currently only works on the CPU as G1/G2 is using `Fr` and `Fr` isn't
 // supported by the GPU kernels
 #[cfg(all(test, not(feature = "gpu")))]
-mod tests;
+pub(crate) mod tests;
 
 pub mod aggregate;
 mod ext;
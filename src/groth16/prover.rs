@@ -31,7 +31,7 @@ fn eval<E: Engine>(
 ) -> E::Fr {
     let mut acc = E::Fr::zero();
 
-    for (&index, &coeff) in lc.0.iter() {
+    for (&index, &coeff) in lc.iter() {
         let mut tmp;
 
         match index {
@@ -593,6 +593,7 @@ mod tests {
     use rand::Rng;
     use rand_core::SeedableRng;
     use rand_xorshift::XorShiftRng;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn test_proving_assignment_extend() {
@@ -654,4 +655,131 @@ mod tests {
             }
         }
     }
+
+    // Proves knowledge of a square root of its input.
+    struct SquareRoot(Option<Fr>);
+
+    impl Circuit<Bls12> for SquareRoot {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let square = self.0.map(|mut x| {
+                x.square();
+                x
+            });
+
+            let x = cs.alloc(|| "x", || self.0.ok_or(SynthesisError::AssignmentMissing))?;
+            let square = cs.alloc_input(
+                || "square",
+                || square.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            cs.enforce(
+                || "x * x = square",
+                |lc| lc + x,
+                |lc| lc + x,
+                |lc| lc + square,
+            );
+
+            Ok(())
+        }
+    }
+
+    struct SquareRoots(Vec<Option<Fr>>);
+
+    impl Circuit<Bls12> for SquareRoots {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let circuits = self.0.into_iter().map(SquareRoot).collect();
+            crate::synthesize_parallel(cs, circuits)
+        }
+    }
+
+    #[test]
+    fn test_synthesize_parallel() {
+        use crate::groth16::{
+            create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+        };
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let roots: Vec<Fr> = (0..8).map(|_| Fr::random(&mut rng)).collect();
+        let squares: Vec<Fr> = roots
+            .iter()
+            .map(|x| {
+                let mut x = *x;
+                x.square();
+                x
+            })
+            .collect();
+
+        // The assignment is the same as for a sequential synthesis
+        let mut sequential = ProvingAssignment::<Bls12>::new();
+        sequential.alloc_input(|| "", || Ok(Fr::one())).unwrap();
+        for x in &roots {
+            SquareRoot(Some(*x)).synthesize(&mut sequential).unwrap();
+        }
+
+        let mut parallel = ProvingAssignment::<Bls12>::new();
+        parallel.alloc_input(|| "", || Ok(Fr::one())).unwrap();
+        SquareRoots(roots.iter().cloned().map(Some).collect())
+            .synthesize(&mut parallel)
+            .unwrap();
+
+        assert_eq!(parallel, sequential);
+
+        let params =
+            generate_random_parameters::<Bls12, _, _>(SquareRoots(vec![None; 8]), &mut rng)
+                .unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let proof = create_random_proof(
+            SquareRoots(roots.iter().cloned().map(Some).collect()),
+            &params,
+            &mut rng,
+        )
+        .unwrap();
+
+        assert!(verify_proof(&pvk, &proof, &squares).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &roots).unwrap());
+    }
+
+    // Counts the times it was synthesized in a constraint system of its own.
+    struct Fresh<'a>(&'a AtomicUsize);
+
+    impl<'a> Circuit<Bls12> for Fresh<'a> {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = cs.alloc(|| "x", || Ok(Fr::one()))?;
+            if x.get_unchecked() == Index::Aux(0) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_synthesize_parallel_in_namespace() {
+        let fresh = AtomicUsize::new(0);
+
+        let mut cs = ProvingAssignment::<Bls12>::new();
+        cs.alloc_input(|| "", || Ok(Fr::one())).unwrap();
+        cs.alloc(|| "before", || Ok(Fr::one())).unwrap();
+
+        let circuits = (0..4).map(|_| Fresh(&fresh)).collect();
+        crate::synthesize_parallel(&mut cs.namespace(|| "parallel"), circuits).unwrap();
+
+        // Each circuit had a constraint system of its own, which was appended
+        assert_eq!(fresh.load(Ordering::SeqCst), 4);
+        assert_eq!(cs.aux_assignment.len(), 5);
+        assert_eq!(cs.input_assignment.len(), 1);
+    }
 }
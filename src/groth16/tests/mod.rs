@@ -4,7 +4,7 @@ use ff::{Field, PrimeField};
 use rand_core::SeedableRng;
 use rand_xorshift::XorShiftRng;
 
-mod dummy_engine;
+pub(crate) mod dummy_engine;
 use self::dummy_engine::*;
 
 use std::marker::PhantomData;
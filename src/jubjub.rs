@@ -0,0 +1,637 @@
+//! A native implementation of the Jubjub twisted Edwards curve, which is
+//! embedded in the scalar field of BLS12-381. It is used to compute the
+//! constants of the [`ecc`] and [`pedersen_hash`] gadgets, and the values
+//! they are expected to produce.
+//!
+//! The curve is `-x^2 + y^2 = 1 + d.x^2.y^2` with `d = -(10240/10241)`.
+//! [`JubjubParams`] is generic over the engine so that it can be used with
+//! any backend, but it only describes Jubjub when the scalar field of the
+//! engine is that of BLS12-381.
+//!
+//! [`ecc`]: crate::gadgets::ecc
+//! [`pedersen_hash`]: crate::gadgets::pedersen_hash
+
+pub mod signature;
+#[cfg(test)]
+pub(crate) mod test_vectors;
+
+use std::fmt;
+
+use blake2s_simd::Params as Blake2sParams;
+use ff::{BitIterator, Field, PrimeField, PrimeFieldRepr, ScalarEngine, SqrtField};
+
+/// First 64 bytes of the BLAKE2s input during group hash. This is chosen
+/// as a "nothing up my sleeve" value, and is the same as in Zcash Sapling.
+pub const GH_FIRST_BLOCK: &[u8; 64] =
+    b"096b36a5804bfacef1691e173c366a47ff5ba84a44f26ddd7e8d9f79d5b42df0";
+
+/// BLAKE2s personalization for the Pedersen hash generators.
+pub const PEDERSEN_HASH_GENERATORS_PERSONALIZATION: &[u8; 8] = b"Zcash_PH";
+
+/// The number of Pedersen hash generators, which bounds the length of
+/// its input.
+pub const PEDERSEN_HASH_GENERATORS: usize = 6;
+
+/// The number of 3-bit chunks hashed with each Pedersen hash generator.
+pub const PEDERSEN_HASH_CHUNKS_PER_GENERATOR: usize = 63;
+
+/// BLAKE2s personalization for the generator of the signature schemes.
+pub const SIGNATURE_GENERATOR_PERSONALIZATION: &[u8; 8] = b"bp_SigGn";
+
+/// The order of the prime order subgroup, as little-endian `u64` limbs.
+pub const SUBGROUP_ORDER: [u64; 4] = [
+    0xd097_0e5e_d6f7_2cb7,
+    0xa668_2093_ccc8_1082,
+    0x0667_3b01_0134_3b00,
+    0x0e7d_b4ea_6533_afa9,
+];
+
+/// The number of bits of the scalars of the prime order subgroup.
+pub const SCALAR_BITS: usize = 252;
+
+/// The curve constants, and the precomputed Pedersen hash generators.
+pub struct JubjubParams<E: ScalarEngine> {
+    edwards_d: E::Fr,
+    montgomery_a: E::Fr,
+    scale: E::Fr,
+    pedersen_hash_generators: Vec<Point<E>>,
+    // For each generator and chunk, the multiples 1 to 4 of the
+    // generator shifted by four bits per chunk
+    pedersen_hash_windows: Vec<Vec<Vec<Point<E>>>>,
+    // The same multiples in Montgomery coordinates
+    pedersen_circuit_generators: Vec<Vec<Vec<(E::Fr, E::Fr)>>>,
+    signature_generator: Point<E>,
+    // The window table of the signature generator for scalars of
+    // SCALAR_BITS bits
+    signature_generator_table: Vec<Vec<(E::Fr, E::Fr)>>,
+}
+
+impl<E: ScalarEngine> Default for JubjubParams<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: ScalarEngine> JubjubParams<E> {
+    pub fn new() -> Self {
+        // d = -(10240/10241)
+        let mut edwards_d = E::Fr::from_str("10241").unwrap().inverse().unwrap();
+        edwards_d.mul_assign(&E::Fr::from_str("10240").unwrap());
+        edwards_d.negate();
+
+        // A = 40962
+        let montgomery_a = E::Fr::from_str("40962").unwrap();
+
+        // The birationally equivalent Montgomery curve is
+        // -(A + 2).v^2 = u^3 + A.u^2 + u, which is rescaled to
+        // v^2 = u^3 + A.u^2 + u by scale = sqrt(-(A + 2)).
+        let mut scale = E::Fr::from_str("40964").unwrap();
+        scale.negate();
+        let scale = scale.sqrt().expect("-40964 is a square");
+
+        let mut params = JubjubParams {
+            edwards_d,
+            montgomery_a,
+            scale,
+            pedersen_hash_generators: vec![],
+            pedersen_hash_windows: vec![],
+            pedersen_circuit_generators: vec![],
+            signature_generator: Point::identity(),
+            signature_generator_table: vec![],
+        };
+
+        let generators: Vec<Point<E>> = (0..PEDERSEN_HASH_GENERATORS as u32)
+            .map(|i| {
+                find_group_hash(
+                    &i.to_le_bytes(),
+                    PEDERSEN_HASH_GENERATORS_PERSONALIZATION,
+                    &params,
+                )
+            })
+            .collect();
+
+        let windows: Vec<Vec<Vec<Point<E>>>> = generators
+            .iter()
+            .map(|generator| {
+                let mut base = *generator;
+
+                (0..PEDERSEN_HASH_CHUNKS_PER_GENERATOR)
+                    .map(|_| {
+                        let mut window = Vec::with_capacity(4);
+                        let mut cur = base;
+                        for _ in 0..4 {
+                            window.push(cur);
+                            cur = cur.add(&base, &params);
+                        }
+
+                        // Each chunk encodes a value in [-4, 4], so the
+                        // next chunk is shifted by four bits
+                        for _ in 0..4 {
+                            base = base.double(&params);
+                        }
+
+                        window
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let circuit_generators = windows
+            .iter()
+            .map(|segment| {
+                segment
+                    .iter()
+                    .map(|window| {
+                        window
+                            .iter()
+                            .map(|p| {
+                                p.to_montgomery(&params)
+                                    .expect("generator multiples are not of small order")
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        params.pedersen_hash_generators = generators;
+        params.pedersen_hash_windows = windows;
+        params.pedersen_circuit_generators = circuit_generators;
+
+        let signature_generator =
+            find_group_hash(&[], SIGNATURE_GENERATOR_PERSONALIZATION, &params);
+        params.signature_generator_table =
+            signature_generator.window_table((SCALAR_BITS + 2) / 3, &params);
+        params.signature_generator = signature_generator;
+
+        params
+    }
+
+    pub fn edwards_d(&self) -> &E::Fr {
+        &self.edwards_d
+    }
+
+    pub fn montgomery_a(&self) -> &E::Fr {
+        &self.montgomery_a
+    }
+
+    pub fn scale(&self) -> &E::Fr {
+        &self.scale
+    }
+
+    pub fn pedersen_hash_generators(&self) -> &[Point<E>] {
+        &self.pedersen_hash_generators
+    }
+
+    /// For each Pedersen hash generator and chunk, the Montgomery
+    /// coordinates of the four points selected by the chunk.
+    pub fn pedersen_circuit_generators(&self) -> &[Vec<Vec<(E::Fr, E::Fr)>>] {
+        &self.pedersen_circuit_generators
+    }
+
+    /// The generator of the prime order subgroup used by the signature
+    /// schemes of [`signature`].
+    pub fn signature_generator(&self) -> &Point<E> {
+        &self.signature_generator
+    }
+
+    /// The window table of the signature generator, for use with the
+    /// [`fixed_base_multiplication`](crate::gadgets::ecc::fixed_base_multiplication)
+    /// gadget with scalars of up to `SCALAR_BITS` bits.
+    pub fn signature_generator_table(&self) -> &[Vec<(E::Fr, E::Fr)>] {
+        &self.signature_generator_table
+    }
+}
+
+/// An affine point of the Jubjub curve in twisted Edwards coordinates.
+pub struct Point<E: ScalarEngine> {
+    x: E::Fr,
+    y: E::Fr,
+}
+
+impl<E: ScalarEngine> Clone for Point<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E: ScalarEngine> Copy for Point<E> {}
+
+impl<E: ScalarEngine> PartialEq for Point<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.x == other.x && self.y == other.y
+    }
+}
+
+impl<E: ScalarEngine> Eq for Point<E> {}
+
+impl<E: ScalarEngine> fmt::Debug for Point<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Point")
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .finish()
+    }
+}
+
+impl<E: ScalarEngine> Point<E> {
+    pub fn identity() -> Self {
+        Point {
+            x: E::Fr::zero(),
+            y: E::Fr::one(),
+        }
+    }
+
+    /// Constructs a point from its coordinates, if it is on the curve.
+    pub fn from_xy(x: E::Fr, y: E::Fr, params: &JubjubParams<E>) -> Option<Self> {
+        let p = Self::from_xy_unchecked(x, y);
+
+        if p.is_on_curve(params) {
+            Some(p)
+        } else {
+            None
+        }
+    }
+
+    /// Constructs a point from its coordinates, without checking that it
+    /// is on the curve.
+    pub fn from_xy_unchecked(x: E::Fr, y: E::Fr) -> Self {
+        Point { x, y }
+    }
+
+    /// Recovers the point with the given y-coordinate, whose x-coordinate
+    /// is odd iff `sign` is set.
+    pub fn from_y(y: E::Fr, sign: bool, params: &JubjubParams<E>) -> Option<Self> {
+        // x^2 = (y^2 - 1) / (d.y^2 + 1)
+        let mut y2 = y;
+        y2.square();
+
+        let mut num = y2;
+        num.sub_assign(&E::Fr::one());
+
+        let mut den = y2;
+        den.mul_assign(&params.edwards_d);
+        den.add_assign(&E::Fr::one());
+
+        num.mul_assign(&den.inverse()?);
+
+        let mut x = num.sqrt()?;
+        if x.into_repr().is_odd() != sign {
+            x.negate();
+        }
+
+        Some(Point { x, y })
+    }
+
+    /// Reads a point from the little-endian encoding of its y-coordinate,
+    /// whose most significant bit holds the sign of the x-coordinate.
+    pub fn from_bytes(bytes: &[u8; 32], params: &JubjubParams<E>) -> Option<Self> {
+        let mut y_repr = <E::Fr as PrimeField>::Repr::default();
+        y_repr.read_le(&bytes[..]).ok()?;
+
+        let limbs = y_repr.as_mut();
+        let last = limbs.len() - 1;
+        let sign = limbs[last] >> 63 == 1;
+        limbs[last] &= 0x7fff_ffff_ffff_ffff;
+
+        let y = E::Fr::from_repr(y_repr).ok()?;
+
+        Self::from_y(y, sign, params)
+    }
+
+    /// Writes the little-endian encoding of the y-coordinate, whose most
+    /// significant bit holds the sign of the x-coordinate, as read by
+    /// [`from_bytes`](Self::from_bytes).
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut y_repr = self.y.into_repr();
+
+        if self.x.into_repr().is_odd() {
+            let limbs = y_repr.as_mut();
+            let last = limbs.len() - 1;
+            limbs[last] |= 1 << 63;
+        }
+
+        let mut bytes = [0u8; 32];
+        y_repr
+            .write_le(&mut bytes[..])
+            .expect("the y-coordinate fits in 32 bytes");
+
+        bytes
+    }
+
+    pub fn get_x(&self) -> E::Fr {
+        self.x
+    }
+
+    pub fn get_y(&self) -> E::Fr {
+        self.y
+    }
+
+    pub fn is_on_curve(&self, params: &JubjubParams<E>) -> bool {
+        let mut x2 = self.x;
+        x2.square();
+
+        let mut y2 = self.y;
+        y2.square();
+
+        // -x^2 + y^2
+        let mut lhs = y2;
+        lhs.sub_assign(&x2);
+
+        // 1 + d.x^2.y^2
+        let mut rhs = x2;
+        rhs.mul_assign(&y2);
+        rhs.mul_assign(&params.edwards_d);
+        rhs.add_assign(&E::Fr::one());
+
+        lhs == rhs
+    }
+
+    pub fn negate(&self) -> Self {
+        let mut x = self.x;
+        x.negate();
+
+        Point { x, y: self.y }
+    }
+
+    pub fn add(&self, other: &Self, params: &JubjubParams<E>) -> Self {
+        // x3 = (x1.y2 + y1.x2) / (1 + d.x1.x2.y1.y2)
+        // y3 = (y1.y2 + x1.x2) / (1 - d.x1.x2.y1.y2)
+        let mut x1y2 = self.x;
+        x1y2.mul_assign(&other.y);
+
+        let mut y1x2 = self.y;
+        y1x2.mul_assign(&other.x);
+
+        let mut x1x2 = self.x;
+        x1x2.mul_assign(&other.x);
+
+        let mut y1y2 = self.y;
+        y1y2.mul_assign(&other.y);
+
+        let mut c = params.edwards_d;
+        c.mul_assign(&x1y2);
+        c.mul_assign(&y1x2);
+
+        // The addition law is complete, as d is not a square
+        let mut x3 = x1y2;
+        x3.add_assign(&y1x2);
+        let mut tmp = E::Fr::one();
+        tmp.add_assign(&c);
+        x3.mul_assign(&tmp.inverse().expect("d is not a square"));
+
+        let mut y3 = y1y2;
+        y3.add_assign(&x1x2);
+        let mut tmp = E::Fr::one();
+        tmp.sub_assign(&c);
+        y3.mul_assign(&tmp.inverse().expect("d is not a square"));
+
+        Point { x: x3, y: y3 }
+    }
+
+    pub fn double(&self, params: &JubjubParams<E>) -> Self {
+        self.add(self, params)
+    }
+
+    /// Multiplies the point by a scalar given as little-endian `u64` limbs.
+    pub fn mul<S: AsRef<[u64]>>(&self, scalar: S, params: &JubjubParams<E>) -> Self {
+        let mut res = Self::identity();
+
+        for bit in BitIterator::new(scalar) {
+            res = res.double(params);
+
+            if bit {
+                res = res.add(self, params);
+            }
+        }
+
+        res
+    }
+
+    pub fn mul_by_cofactor(&self, params: &JubjubParams<E>) -> Self {
+        self.double(params).double(params).double(params)
+    }
+
+    /// Converts the point to the coordinates of the equivalent point
+    /// on the Montgomery curve `v^2 = u^3 + A.u^2 + u`, unless it is
+    /// the identity or the point of order two, which have no affine
+    /// representation there.
+    pub fn to_montgomery(&self, params: &JubjubParams<E>) -> Option<(E::Fr, E::Fr)> {
+        if self.x.is_zero() {
+            return None;
+        }
+
+        // u = (1 + y) / (1 - y)
+        let mut u = E::Fr::one();
+        u.add_assign(&self.y);
+        let mut tmp = E::Fr::one();
+        tmp.sub_assign(&self.y);
+        u.mul_assign(&tmp.inverse()?);
+
+        // v = scale.u / x
+        let mut v = u;
+        v.mul_assign(&params.scale);
+        v.mul_assign(&self.x.inverse()?);
+
+        Some((u, v))
+    }
+
+    /// Computes the window table used by the
+    /// [`fixed_base_multiplication`](crate::gadgets::ecc::fixed_base_multiplication)
+    /// gadget to multiply this point by scalars of up to
+    /// `3 * num_windows` bits: the multiples 0 to 7 of the point
+    /// shifted by three bits per window.
+    pub fn window_table(
+        &self,
+        num_windows: usize,
+        params: &JubjubParams<E>,
+    ) -> Vec<Vec<(E::Fr, E::Fr)>> {
+        let mut base = *self;
+
+        (0..num_windows)
+            .map(|_| {
+                let mut window = Vec::with_capacity(8);
+                let mut cur = Self::identity();
+                for _ in 0..8 {
+                    window.push((cur.x, cur.y));
+                    cur = cur.add(&base, params);
+                }
+
+                base = cur;
+
+                window
+            })
+            .collect()
+    }
+}
+
+/// Hashes `tag` to a point of the prime order subgroup, if it maps to
+/// one other than the identity.
+pub fn group_hash<E: ScalarEngine>(
+    tag: &[u8],
+    personalization: &[u8; 8],
+    params: &JubjubParams<E>,
+) -> Option<Point<E>> {
+    let hash = Blake2sParams::new()
+        .hash_length(32)
+        .personal(personalization)
+        .to_state()
+        .update(GH_FIRST_BLOCK)
+        .update(tag)
+        .finalize();
+
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(hash.as_ref());
+
+    let p = Point::from_bytes(&bytes, params)?.mul_by_cofactor(params);
+
+    if p == Point::identity() {
+        None
+    } else {
+        Some(p)
+    }
+}
+
+/// Hashes `m` to a point of the prime order subgroup, appending an
+/// incrementing byte to it until `group_hash` succeeds.
+pub fn find_group_hash<E: ScalarEngine>(
+    m: &[u8],
+    personalization: &[u8; 8],
+    params: &JubjubParams<E>,
+) -> Point<E> {
+    let mut tag = m.to_vec();
+    let i = tag.len();
+    tag.push(0u8);
+
+    loop {
+        if let Some(p) = group_hash(&tag, personalization, params) {
+            return p;
+        }
+
+        assert!(tag[i] != u8::max_value());
+        tag[i] += 1;
+    }
+}
+
+/// Computes the Pedersen hash of `bits` prefixed by the constant
+/// `personalization` bits, as enforced by the
+/// [`pedersen_hash`](crate::gadgets::pedersen_hash::pedersen_hash) gadget.
+pub fn pedersen_hash<E: ScalarEngine>(
+    personalization: &[bool],
+    bits: &[bool],
+    params: &JubjubParams<E>,
+) -> Point<E> {
+    let mut bits = personalization
+        .iter()
+        .chain(bits.iter())
+        .cloned()
+        .peekable();
+    let mut segments = params.pedersen_hash_windows.iter();
+
+    let mut result = Point::identity();
+
+    while bits.peek().is_some() {
+        let windows = segments.next().expect("enough segments");
+
+        for window in windows {
+            let a = match bits.next() {
+                Some(a) => a,
+                None => break,
+            };
+            let b = bits.next().unwrap_or(false);
+            let c = bits.next().unwrap_or(false);
+
+            // The chunk encodes (1 + a + 2b) * (1 - 2c)
+            let mut p = window[a as usize + 2 * (b as usize)];
+            if c {
+                p = p.negate();
+            }
+
+            result = result.add(&p, params);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_params() {
+        let params = JubjubParams::<Bls12>::new();
+
+        // The first generator is the same as in Zcash Sapling
+        assert_eq!(
+            params.pedersen_hash_generators()[0].get_x(),
+            Fr::from_str(
+                "52355368488200756720908213129543630848976972731871436319321443845291207170897"
+            )
+            .unwrap()
+        );
+
+        for g in params.pedersen_hash_generators() {
+            assert!(g.is_on_curve(&params));
+            assert_eq!(g.mul(SUBGROUP_ORDER, &params), Point::identity());
+        }
+
+        assert_eq!(
+            params.pedersen_circuit_generators().len(),
+            PEDERSEN_HASH_GENERATORS
+        );
+
+        let g = params.signature_generator();
+        assert!(g.is_on_curve(&params));
+        assert_eq!(g.mul(SUBGROUP_ORDER, &params), Point::identity());
+        assert_eq!(params.signature_generator_table().len(), 84);
+    }
+
+    #[test]
+    fn test_point_arithmetic() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = JubjubParams::<Bls12>::new();
+        let g = params.pedersen_hash_generators()[1];
+
+        for _ in 0..10 {
+            let a = rng.next_u64();
+            let b = rng.next_u64();
+
+            let p = g.mul([a], &params);
+            let q = g.mul([b], &params);
+            assert!(p.is_on_curve(&params));
+
+            // aG + bG = (a + b)G
+            let (sum, carry) = a.overflowing_add(b);
+            assert_eq!(p.add(&q, &params), g.mul([sum, carry as u64], &params));
+
+            assert_eq!(p.add(&p.negate(), &params), Point::identity());
+            assert_eq!(p.double(&params), p.add(&p, &params));
+
+            // Points round-trip through their y-coordinate
+            let sign = p.get_x().into_repr().is_odd();
+            assert_eq!(Point::from_y(p.get_y(), sign, &params), Some(p));
+            assert_eq!(Point::from_bytes(&p.to_bytes(), &params), Some(p));
+        }
+    }
+
+    #[test]
+    fn test_pedersen_hash_test_vectors() {
+        let params = JubjubParams::<Bls12>::new();
+
+        for v in test_vectors::get_vectors() {
+            let bits: Vec<bool> = v.input_bits.iter().map(|&b| b == 1).collect();
+            let hash = pedersen_hash(&bits[..6], &bits[6..], &params);
+
+            assert_eq!(hash.get_x(), test_vectors::fr_from_hex::<Fr>(v.hash_u));
+            assert_eq!(hash.get_y(), test_vectors::fr_from_hex::<Fr>(v.hash_v));
+        }
+    }
+}
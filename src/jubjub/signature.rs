@@ -0,0 +1,384 @@
+//! Native EdDSA and Schnorr signatures over Jubjub, as verified by the
+//! [`signature`](crate::gadgets::signature) gadgets. They are meant to
+//! produce test vectors and witnesses, and are not constant time.
+//!
+//! Both schemes use the generator `G` of
+//! [`JubjubParams::signature_generator`], and BLAKE2s challenges over the
+//! encodings of the points followed by the message:
+//!
+//! - an EdDSA signature `(R, s)` of `m` by `A` is valid iff
+//!   `s.G = R + c.A`, where `c = H(R || A || m)`;
+//! - a Schnorr signature `(c, s)` of `m` by `A` is valid iff
+//!   `c = H(R || A || m)`, where `R = s.G + c.A`.
+//!
+//! The challenges multiply points as 256-bit integers, without reduction.
+
+use blake2s_simd::Params as Blake2sParams;
+use ff::ScalarEngine;
+use num_bigint::BigUint;
+use rand_core::RngCore;
+
+use super::{JubjubParams, Point, SCALAR_BITS, SUBGROUP_ORDER};
+
+/// BLAKE2s personalization for the EdDSA challenges.
+pub const EDDSA_PERSONALIZATION: &[u8; 8] = b"bp_EdDSA";
+
+/// BLAKE2s personalization for the Schnorr challenges.
+pub const SCHNORR_PERSONALIZATION: &[u8; 8] = b"bp_Schnr";
+
+fn subgroup_order() -> BigUint {
+    let bytes: Vec<u8> = SUBGROUP_ORDER
+        .iter()
+        .flat_map(|limb| limb.to_le_bytes().to_vec())
+        .collect();
+
+    BigUint::from_bytes_le(&bytes)
+}
+
+/// An integer modulo the order of the prime order subgroup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Scalar(BigUint);
+
+impl Scalar {
+    /// Samples a uniform scalar, by reducing 512 random bits.
+    pub fn random<R: RngCore>(rng: &mut R) -> Self {
+        let mut bytes = [0u8; 64];
+        rng.fill_bytes(&mut bytes);
+
+        Self::from_bytes_wide(&bytes)
+    }
+
+    /// Reduces the little-endian integer `bytes`.
+    pub fn from_bytes_wide(bytes: &[u8]) -> Self {
+        Scalar(BigUint::from_bytes_le(bytes) % subgroup_order())
+    }
+
+    /// Reads a little-endian scalar, if it is reduced.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let value = BigUint::from_bytes_le(bytes);
+
+        if value < subgroup_order() {
+            Some(Scalar(value))
+        } else {
+            None
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let le = self.0.to_bytes_le();
+
+        let mut bytes = [0u8; 32];
+        bytes[..le.len()].copy_from_slice(&le);
+
+        bytes
+    }
+
+    /// Returns the `SCALAR_BITS` bits of the scalar in little-endian
+    /// order, as expected by the gadgets.
+    pub fn to_bits_le(&self) -> Vec<bool> {
+        (0..SCALAR_BITS).map(|i| self.0.bit(i as u64)).collect()
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        Scalar((&self.0 + &other.0) % subgroup_order())
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        let order = subgroup_order();
+
+        Scalar((&self.0 + &order - &other.0) % order)
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        Scalar((&self.0 * &other.0) % subgroup_order())
+    }
+
+    /// Multiplies `p` by this scalar.
+    pub fn mul_point<E: ScalarEngine>(&self, p: &Point<E>, params: &JubjubParams<E>) -> Point<E> {
+        p.mul(self.0.to_u64_digits(), params)
+    }
+}
+
+/// Computes the challenge of a signature, as a little-endian integer.
+fn challenge<E: ScalarEngine>(
+    personalization: &[u8; 8],
+    r: &Point<E>,
+    public_key: &Point<E>,
+    msg: &[u8],
+) -> [u8; 32] {
+    let hash = Blake2sParams::new()
+        .hash_length(32)
+        .personal(personalization)
+        .to_state()
+        .update(&r.to_bytes())
+        .update(&public_key.to_bytes())
+        .update(msg)
+        .finalize();
+
+    let mut c = [0u8; 32];
+    c.copy_from_slice(hash.as_ref());
+
+    c
+}
+
+/// Multiplies `p` by the unreduced challenge `c`.
+fn mul_by_challenge<E: ScalarEngine>(
+    p: &Point<E>,
+    c: &[u8; 32],
+    params: &JubjubParams<E>,
+) -> Point<E> {
+    let mut limbs = [0u64; 4];
+    for (limb, bytes) in limbs.iter_mut().zip(c.chunks(8)) {
+        let mut tmp = [0u8; 8];
+        tmp.copy_from_slice(bytes);
+        *limb = u64::from_le_bytes(tmp);
+    }
+
+    p.mul(limbs, params)
+}
+
+/// An EdDSA signature.
+pub struct EddsaSignature<E: ScalarEngine> {
+    pub r: Point<E>,
+    pub s: Scalar,
+}
+
+impl<E: ScalarEngine> Clone for EddsaSignature<E> {
+    fn clone(&self) -> Self {
+        EddsaSignature {
+            r: self.r,
+            s: self.s.clone(),
+        }
+    }
+}
+
+/// A Schnorr signature. The challenge is kept as the bytes of the hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchnorrSignature {
+    pub c: [u8; 32],
+    pub s: Scalar,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrivateKey(pub Scalar);
+
+impl PrivateKey {
+    pub fn random<R: RngCore>(rng: &mut R) -> Self {
+        PrivateKey(Scalar::random(rng))
+    }
+
+    pub fn public_key<E: ScalarEngine>(&self, params: &JubjubParams<E>) -> PublicKey<E> {
+        PublicKey(self.0.mul_point(params.signature_generator(), params))
+    }
+
+    /// Signs `msg` with EdDSA, using a nonce sampled from `rng`.
+    pub fn sign_eddsa<E, R>(
+        &self,
+        msg: &[u8],
+        rng: &mut R,
+        params: &JubjubParams<E>,
+    ) -> EddsaSignature<E>
+    where
+        E: ScalarEngine,
+        R: RngCore,
+    {
+        let public_key = self.public_key(params);
+
+        let nonce = Scalar::random(rng);
+        let r = nonce.mul_point(params.signature_generator(), params);
+
+        // s = nonce + c.sk
+        let c = challenge(EDDSA_PERSONALIZATION, &r, &public_key.0, msg);
+        let s = nonce.add(&Scalar::from_bytes_wide(&c).mul(&self.0));
+
+        EddsaSignature { r, s }
+    }
+
+    /// Signs `msg` with Schnorr, using a nonce sampled from `rng`.
+    pub fn sign_schnorr<E, R>(
+        &self,
+        msg: &[u8],
+        rng: &mut R,
+        params: &JubjubParams<E>,
+    ) -> SchnorrSignature
+    where
+        E: ScalarEngine,
+        R: RngCore,
+    {
+        let public_key = self.public_key(params);
+
+        let nonce = Scalar::random(rng);
+        let r = nonce.mul_point(params.signature_generator(), params);
+
+        // s = nonce - c.sk, so that s.G + c.A = nonce.G
+        let c = challenge(SCHNORR_PERSONALIZATION, &r, &public_key.0, msg);
+        let s = nonce.sub(&Scalar::from_bytes_wide(&c).mul(&self.0));
+
+        SchnorrSignature { c, s }
+    }
+}
+
+pub struct PublicKey<E: ScalarEngine>(pub Point<E>);
+
+impl<E: ScalarEngine> Clone for PublicKey<E> {
+    fn clone(&self) -> Self {
+        PublicKey(self.0)
+    }
+}
+
+impl<E: ScalarEngine> PublicKey<E> {
+    /// Checks that the key is on the curve and not of small order, since
+    /// `c.A` would vanish for a key of small order, up to the cofactor.
+    fn is_valid(&self, params: &JubjubParams<E>) -> bool {
+        self.0.is_on_curve(params) && self.0.mul_by_cofactor(params) != Point::identity()
+    }
+
+    pub fn verify_eddsa(
+        &self,
+        msg: &[u8],
+        signature: &EddsaSignature<E>,
+        params: &JubjubParams<E>,
+    ) -> bool {
+        if !self.is_valid(params) || !signature.r.is_on_curve(params) {
+            return false;
+        }
+
+        let c = challenge(EDDSA_PERSONALIZATION, &signature.r, &self.0, msg);
+
+        // s.G = R + c.A
+        let lhs = signature.s.mul_point(params.signature_generator(), params);
+        let rhs = signature
+            .r
+            .add(&mul_by_challenge(&self.0, &c, params), params);
+
+        lhs == rhs
+    }
+
+    pub fn verify_schnorr(
+        &self,
+        msg: &[u8],
+        signature: &SchnorrSignature,
+        params: &JubjubParams<E>,
+    ) -> bool {
+        if !self.is_valid(params) {
+            return false;
+        }
+
+        // R = s.G + c.A
+        let r = signature
+            .s
+            .mul_point(params.signature_generator(), params)
+            .add(&mul_by_challenge(&self.0, &signature.c, params), params);
+
+        challenge(SCHNORR_PERSONALIZATION, &r, &self.0, msg) == signature.c
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bls::Bls12;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_scalar_arithmetic() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = JubjubParams::<Bls12>::new();
+        let g = params.signature_generator();
+
+        for _ in 0..10 {
+            let a = Scalar::random(&mut rng);
+            let b = Scalar::random(&mut rng);
+
+            assert_eq!(Scalar::from_bytes(&a.to_bytes()), Some(a.clone()));
+            assert_eq!(a.add(&b).sub(&b), a);
+
+            // (a + b).G = a.G + b.G, and (a.b).G = a.(b.G)
+            assert_eq!(
+                a.add(&b).mul_point(g, &params),
+                a.mul_point(g, &params)
+                    .add(&b.mul_point(g, &params), &params)
+            );
+            assert_eq!(
+                a.mul(&b).mul_point(g, &params),
+                a.mul_point(&b.mul_point(g, &params), &params)
+            );
+        }
+
+        // The order itself is not a reduced encoding
+        let mut order = [0u8; 32];
+        for (bytes, limb) in order.chunks_mut(8).zip(SUBGROUP_ORDER.iter()) {
+            bytes.copy_from_slice(&limb.to_le_bytes());
+        }
+        assert_eq!(Scalar::from_bytes(&order), None);
+        assert_eq!(
+            Scalar::from_bytes_wide(&order),
+            Scalar::from_bytes(&[0; 32]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_eddsa() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = JubjubParams::<Bls12>::new();
+
+        for _ in 0..5 {
+            let sk = PrivateKey::random(&mut rng);
+            let pk = sk.public_key(&params);
+
+            let signature = sk.sign_eddsa(b"bellperson", &mut rng, &params);
+            assert!(pk.verify_eddsa(b"bellperson", &signature, &params));
+            assert!(!pk.verify_eddsa(b"bellpersoN", &signature, &params));
+
+            let other = PrivateKey::random(&mut rng).public_key(&params);
+            assert!(!other.verify_eddsa(b"bellperson", &signature, &params));
+
+            let mut forged = signature.clone();
+            forged.s = forged.s.add(&Scalar::from_bytes_wide(&[1]));
+            assert!(!pk.verify_eddsa(b"bellperson", &forged, &params));
+        }
+
+        // With the identity as the key, R = s.G would verify any message
+        let s = Scalar::random(&mut rng);
+        let forged = EddsaSignature {
+            r: s.mul_point(params.signature_generator(), &params),
+            s,
+        };
+        assert!(!PublicKey(Point::identity()).verify_eddsa(b"bellperson", &forged, &params));
+    }
+
+    #[test]
+    fn test_schnorr() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = JubjubParams::<Bls12>::new();
+
+        for _ in 0..5 {
+            let sk = PrivateKey::random(&mut rng);
+            let pk = sk.public_key(&params);
+
+            let signature = sk.sign_schnorr(b"bellperson", &mut rng, &params);
+            assert!(pk.verify_schnorr(b"bellperson", &signature, &params));
+            assert!(!pk.verify_schnorr(b"bellpersoN", &signature, &params));
+
+            let other = PrivateKey::random(&mut rng).public_key(&params);
+            assert!(!other.verify_schnorr(b"bellperson", &signature, &params));
+
+            let mut forged = signature.clone();
+            forged.c[0] ^= 1;
+            assert!(!pk.verify_schnorr(b"bellperson", &forged, &params));
+        }
+    }
+}
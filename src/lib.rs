@@ -149,13 +149,14 @@ pub mod gadgets;
 pub mod gpu;
 #[cfg(feature = "groth16")]
 pub mod groth16;
+pub mod jubjub;
 pub mod multicore;
 pub mod multiexp;
 
 pub mod util_cs;
 use ff::{Field, ScalarEngine};
 
-use rustc_hash::FxHashMap as HashMap;
+use rayon::prelude::*;
 use std::io;
 use std::marker::PhantomData;
 use std::ops::{Add, Sub};
@@ -199,28 +200,105 @@ pub enum Index {
 
 /// This represents a linear combination of some variables, with coefficients
 /// in the scalar field of a pairing-friendly elliptic curve group.
+///
+/// The terms are kept sorted by variable, inputs first, with one term per
+/// variable: adding a term is a binary search, or a push when variables are
+/// added in the order they were allocated, and adding a linear combination
+/// is a merge in place.
 #[derive(Clone)]
-pub struct LinearCombination<E: ScalarEngine>(HashMap<Variable, E::Fr>);
+pub struct LinearCombination<E: ScalarEngine>(Vec<(Variable, E::Fr)>);
 impl<E: ScalarEngine> Default for LinearCombination<E> {
     fn default() -> Self {
         Self::zero()
     }
 }
 
+fn sort_key(var: &Variable) -> (bool, usize) {
+    match var.0 {
+        Index::Input(i) => (false, i),
+        Index::Aux(i) => (true, i),
+    }
+}
+
 impl<E: ScalarEngine> LinearCombination<E> {
     pub fn zero() -> LinearCombination<E> {
-        LinearCombination(HashMap::default())
+        LinearCombination(Vec::new())
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (&Variable, &E::Fr)> + '_ {
-        self.0.iter()
+        self.0.iter().map(|(var, coeff)| (var, coeff))
     }
 
     pub fn add_unsimplified(mut self, (coeff, var): (E::Fr, Variable)) -> LinearCombination<E> {
-        self.0
-            .entry(var)
-            .or_insert_with(E::Fr::zero)
-            .add_assign(&coeff);
+        self.add_term(coeff, var);
+
+        self
+    }
+
+    fn add_term(&mut self, coeff: E::Fr, var: Variable) {
+        let key = sort_key(&var);
+
+        // Most terms are added in allocation order
+        match self.0.last_mut() {
+            Some((last, last_coeff)) if *last == var => {
+                last_coeff.add_assign(&coeff);
+                return;
+            }
+            Some((last, _)) if sort_key(last) > key => {}
+            _ => {
+                self.0.push((var, coeff));
+                return;
+            }
+        }
+
+        match self.0.binary_search_by_key(&key, |(var, _)| sort_key(var)) {
+            Ok(i) => self.0[i].1.add_assign(&coeff),
+            Err(i) => self.0.insert(i, (var, coeff)),
+        }
+    }
+
+    /// Adds the terms of `other`, with their coefficients mapped by `f`, by
+    /// merging them into ours from the back, in place.
+    fn merge<F>(mut self, other: &LinearCombination<E>, f: F) -> LinearCombination<E>
+    where
+        F: Fn(&E::Fr) -> E::Fr,
+    {
+        if let [(var, coeff)] = other.0.as_slice() {
+            self.add_term(f(coeff), *var);
+            return self;
+        }
+
+        let len = self.0.len();
+        self.0.extend_from_slice(&other.0);
+
+        // The terms left to merge are ours up to i and theirs up to j, and
+        // the next merged term goes before k
+        let (mut i, mut j, mut k) = (len, other.0.len(), self.0.len());
+        while j > 0 {
+            let (var, coeff) = &other.0[j - 1];
+            let term = match self.0[..i].last() {
+                Some((last, _)) if sort_key(last) > sort_key(var) => {
+                    i -= 1;
+                    self.0[i]
+                }
+                Some((last, last_coeff)) if last == var => {
+                    let mut sum = *last_coeff;
+                    sum.add_assign(&f(coeff));
+                    i -= 1;
+                    j -= 1;
+                    (*var, sum)
+                }
+                _ => {
+                    j -= 1;
+                    (*var, f(coeff))
+                }
+            };
+            k -= 1;
+            self.0[k] = term;
+        }
+
+        // Each variable we had in common leaves a slot free
+        self.0.drain(i..k);
 
         self
     }
@@ -230,10 +308,7 @@ impl<E: ScalarEngine> Add<(E::Fr, Variable)> for LinearCombination<E> {
     type Output = LinearCombination<E>;
 
     fn add(mut self, (coeff, var): (E::Fr, Variable)) -> LinearCombination<E> {
-        self.0
-            .entry(var)
-            .or_insert_with(E::Fr::zero)
-            .add_assign(&coeff);
+        self.add_term(coeff, var);
 
         self
     }
@@ -269,55 +344,45 @@ impl<E: ScalarEngine> Sub<Variable> for LinearCombination<E> {
 impl<'a, E: ScalarEngine> Add<&'a LinearCombination<E>> for LinearCombination<E> {
     type Output = LinearCombination<E>;
 
-    fn add(mut self, other: &'a LinearCombination<E>) -> LinearCombination<E> {
-        for (var, val) in &other.0 {
-            self.0
-                .entry(*var)
-                .or_insert_with(E::Fr::zero)
-                .add_assign(val);
-        }
-
-        self
+    fn add(self, other: &'a LinearCombination<E>) -> LinearCombination<E> {
+        self.merge(other, |coeff| *coeff)
     }
 }
 
 impl<'a, E: ScalarEngine> Sub<&'a LinearCombination<E>> for LinearCombination<E> {
     type Output = LinearCombination<E>;
 
-    fn sub(mut self, other: &'a LinearCombination<E>) -> LinearCombination<E> {
-        for (var, val) in &other.0 {
-            self = self - (*val, *var);
-        }
-
-        self
+    fn sub(self, other: &'a LinearCombination<E>) -> LinearCombination<E> {
+        self.merge(other, |coeff| {
+            let mut tmp = *coeff;
+            tmp.negate();
+            tmp
+        })
     }
 }
 
 impl<'a, E: ScalarEngine> Add<(E::Fr, &'a LinearCombination<E>)> for LinearCombination<E> {
     type Output = LinearCombination<E>;
 
-    fn add(mut self, (coeff, other): (E::Fr, &'a LinearCombination<E>)) -> LinearCombination<E> {
-        for s in &other.0 {
-            let mut tmp = *s.1;
+    fn add(self, (coeff, other): (E::Fr, &'a LinearCombination<E>)) -> LinearCombination<E> {
+        self.merge(other, |s| {
+            let mut tmp = *s;
             tmp.mul_assign(&coeff);
-            self = self + (tmp, *s.0);
-        }
-
-        self
+            tmp
+        })
     }
 }
 
 impl<'a, E: ScalarEngine> Sub<(E::Fr, &'a LinearCombination<E>)> for LinearCombination<E> {
     type Output = LinearCombination<E>;
 
-    fn sub(mut self, (coeff, other): (E::Fr, &'a LinearCombination<E>)) -> LinearCombination<E> {
-        for s in &other.0 {
-            let mut tmp = *s.1;
+    fn sub(self, (coeff, other): (E::Fr, &'a LinearCombination<E>)) -> LinearCombination<E> {
+        self.merge(other, |s| {
+            let mut tmp = *s;
             tmp.mul_assign(&coeff);
-            self = self - (tmp, *s.0);
-        }
-
-        self
+            tmp.negate();
+            tmp
+        })
     }
 }
 
@@ -590,6 +655,48 @@ impl<'cs, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for &'cs
     }
 }
 
+/// Synthesizes independent sub-circuits into `cs`, in order.
+///
+/// When the root of `cs` is extensible, each sub-circuit is synthesized in
+/// a new constraint system of its own on the rayon thread pool, which starts
+/// with its own "one" input, and the root is then extended with them in
+/// order, so that this also holds for a namespace of such a constraint
+/// system. Otherwise, they are synthesized sequentially, each in its own
+/// namespace. The sub-circuits cannot use the variables of `cs` or of each
+/// other.
+pub fn synthesize_parallel<E, CS, C>(cs: &mut CS, circuits: Vec<C>) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+    C: Circuit<E> + Send,
+{
+    if !CS::Root::is_extensible() {
+        for (i, circuit) in circuits.into_iter().enumerate() {
+            circuit.synthesize(&mut cs.namespace(|| format!("circuit {}", i)))?;
+        }
+
+        return Ok(());
+    }
+
+    let parts = circuits
+        .into_par_iter()
+        .map(|circuit| {
+            let mut part = CS::Root::new();
+            part.alloc_input(|| "", || Ok(E::Fr::one()))?;
+            circuit.synthesize(&mut part)?;
+
+            Ok(part)
+        })
+        .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    let root = cs.get_root();
+    for part in parts {
+        root.extend(part);
+    }
+
+    Ok(())
+}
+
 #[cfg(all(test, feature = "groth16"))]
 mod tests {
     use super::*;
@@ -623,4 +730,62 @@ mod tests {
             _ => panic!("unexpected variable type"),
         });
     }
+
+    #[test]
+    fn test_add_unordered() {
+        use crate::bls::Bls12;
+        use rand_core::{RngCore, SeedableRng};
+        use rand_xorshift::XorShiftRng;
+
+        type Fr = <Bls12 as ScalarEngine>::Fr;
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let mut random_lc = |expected: &mut std::collections::BTreeMap<(bool, usize), Fr>| {
+            let mut lc = LinearCombination::<Bls12>::zero();
+            for _ in 0..20 {
+                let i = (rng.next_u32() % 10) as usize;
+                let var = if rng.next_u32() % 2 == 0 {
+                    Variable::new_unchecked(Index::Input(i))
+                } else {
+                    Variable::new_unchecked(Index::Aux(i))
+                };
+                let coeff = Fr::random(&mut rng);
+
+                lc = lc + (coeff, var);
+                expected
+                    .entry(sort_key(&var))
+                    .or_insert_with(Fr::zero)
+                    .add_assign(&coeff);
+            }
+            lc
+        };
+
+        for _ in 0..100 {
+            let mut expected = std::collections::BTreeMap::new();
+            let mut negated = std::collections::BTreeMap::new();
+
+            let a = random_lc(&mut expected);
+            let b = random_lc(&mut expected);
+            let c = random_lc(&mut negated);
+            for (key, mut coeff) in negated {
+                coeff.negate();
+                expected
+                    .entry(key)
+                    .or_insert_with(Fr::zero)
+                    .add_assign(&coeff);
+            }
+
+            // The terms are sorted, with one per variable
+            let lc = a + &b - &c;
+            let terms: Vec<_> = lc
+                .iter()
+                .map(|(var, coeff)| (sort_key(var), *coeff))
+                .collect();
+            assert_eq!(terms, expected.into_iter().collect::<Vec<_>>());
+        }
+    }
 }
@@ -1,8 +1,70 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 use crate::bls::Engine;
 use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
 
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator which counts the allocated bytes, for
+/// [`BenchCS::with_timing`]. It has to be registered by the binary:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Returns the number of bytes allocated so far by all the threads, which
+/// stays at zero unless [`CountingAllocator`] is the global allocator.
+pub fn allocated_bytes() -> usize {
+    ALLOCATED.load(Ordering::Relaxed)
+}
+
+/// The resources spent in a top-level namespace, over all the times it was
+/// entered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NamespaceStats {
+    pub entered: usize,
+    pub duration: Duration,
+    /// The bytes allocated by all the threads, freed or not, if
+    /// [`CountingAllocator`] is the global allocator.
+    pub allocated_bytes: usize,
+}
+
+#[derive(Debug, Default)]
+struct Timing {
+    depth: usize,
+    // The current top-level namespace, and when it was entered
+    current: Option<(String, Instant, usize)>,
+    stats: BTreeMap<String, NamespaceStats>,
+}
+
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 pub struct BenchCS<E: Engine> {
@@ -11,7 +73,8 @@ pub struct BenchCS<E: Engine> {
     a: usize,
     b: usize,
     c: usize,
-    _e: PhantomData<E>,
+    timing: Option<Timing>,
+    _e: PhantomData<fn() -> E>,
 }
 
 impl<E: Engine> BenchCS<E> {
@@ -19,6 +82,22 @@ impl<E: Engine> BenchCS<E> {
         BenchCS::default()
     }
 
+    /// Also records the wall-clock time and the allocated bytes of each
+    /// top-level namespace. The assignments are then computed, so that the
+    /// witness computation is measured, and their errors are ignored.
+    pub fn with_timing() -> Self {
+        BenchCS {
+            timing: Some(Timing::default()),
+            ..BenchCS::default()
+        }
+    }
+
+    /// Returns the resources spent in each top-level namespace, if created
+    /// by [`BenchCS::with_timing`].
+    pub fn timings(&self) -> Option<&BTreeMap<String, NamespaceStats>> {
+        self.timing.as_ref().map(|timing| &timing.stats)
+    }
+
     pub fn num_constraints(&self) -> usize {
         self.a
     }
@@ -36,40 +115,40 @@ impl<E: Engine> Default for BenchCS<E> {
             a: 0,
             b: 0,
             c: 0,
+            timing: None,
             _e: PhantomData,
         }
     }
 }
 
-// Safety: Engine is static and this is only a marker
-unsafe impl<E: Engine> Send for BenchCS<E> {}
-
 impl<E: Engine> ConstraintSystem<E> for BenchCS<E> {
     type Root = Self;
 
-    fn new() -> Self {
-        BenchCS::default()
-    }
-
-    fn alloc<F, A, AR>(&mut self, _: A, _f: F) -> Result<Variable, SynthesisError>
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
     where
         F: FnOnce() -> Result<E::Fr, SynthesisError>,
         A: FnOnce() -> AR,
         AR: Into<String>,
     {
-        // don't invoke f, we just count
+        // only invoke f when timing the witness computation
+        if self.timing.is_some() {
+            let _ = f();
+        }
         self.aux += 1;
 
         Ok(Variable::new_unchecked(Index::Aux(self.aux - 1)))
     }
 
-    fn alloc_input<F, A, AR>(&mut self, _: A, _f: F) -> Result<Variable, SynthesisError>
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
     where
         F: FnOnce() -> Result<E::Fr, SynthesisError>,
         A: FnOnce() -> AR,
         AR: Into<String>,
     {
-        // don't invoke f, we just count
+        // only invoke f when timing the witness computation
+        if self.timing.is_some() {
+            let _ = f();
+        }
         self.inputs += 1;
 
         Ok(Variable::new_unchecked(Index::Input(self.inputs - 1)))
@@ -88,14 +167,31 @@ impl<E: Engine> ConstraintSystem<E> for BenchCS<E> {
         self.c += 1;
     }
 
-    fn push_namespace<NR, N>(&mut self, _: N)
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
     where
         NR: Into<String>,
         N: FnOnce() -> NR,
     {
+        if let Some(timing) = self.timing.as_mut() {
+            if timing.depth == 0 {
+                timing.current = Some((name_fn().into(), Instant::now(), allocated_bytes()));
+            }
+            timing.depth += 1;
+        }
     }
 
-    fn pop_namespace(&mut self) {}
+    fn pop_namespace(&mut self) {
+        if let Some(timing) = self.timing.as_mut() {
+            timing.depth -= 1;
+            if timing.depth == 0 {
+                let (name, start, allocated) = timing.current.take().unwrap();
+                let stats = timing.stats.entry(name).or_default();
+                stats.entered += 1;
+                stats.duration += start.elapsed();
+                stats.allocated_bytes += allocated_bytes() - allocated;
+            }
+        }
+    }
 
     fn get_root(&mut self) -> &mut Self::Root {
         self
@@ -0,0 +1,283 @@
+//! Exports the named constraints recorded by
+//! [`TestConstraintSystem`](crate::gadgets::test::TestConstraintSystem) and
+//! [`MetricCS`](super::metric_cs::MetricCS), to JSON for tooling and to
+//! GraphViz DOT to inspect small gadgets visually.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use ff::{Field, PrimeField, ScalarEngine};
+use serde::{Deserialize, Serialize};
+
+use crate::{Index, LinearCombination};
+
+/// A term of a linear combination.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedTerm {
+    /// The path of the variable.
+    pub variable: String,
+    /// The coefficient, as its canonical representation in hexadecimal.
+    pub coeff: String,
+}
+
+/// A constraint `A * B = C`, with the terms of each linear combination
+/// ordered by variable, the inputs first, and no zero coefficient.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedConstraint {
+    pub path: String,
+    pub a: Vec<ExportedTerm>,
+    pub b: Vec<ExportedTerm>,
+    pub c: Vec<ExportedTerm>,
+}
+
+/// The paths of the variables and the constraints of a circuit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Export {
+    /// The paths of the inputs, starting with `ONE`.
+    pub inputs: Vec<String>,
+    pub aux: Vec<String>,
+    pub constraints: Vec<ExportedConstraint>,
+}
+
+#[allow(clippy::type_complexity)]
+impl Export {
+    pub(crate) fn new<'a, E, I>(inputs: Vec<String>, aux: Vec<String>, constraints: I) -> Self
+    where
+        E: ScalarEngine,
+        I: IntoIterator<
+            Item = &'a (
+                LinearCombination<E>,
+                LinearCombination<E>,
+                LinearCombination<E>,
+                String,
+            ),
+        >,
+    {
+        let constraints = constraints
+            .into_iter()
+            .map(|(a, b, c, path)| ExportedConstraint {
+                path: path.clone(),
+                a: export_lc(a, &inputs, &aux),
+                b: export_lc(b, &inputs, &aux),
+                c: export_lc(c, &inputs, &aux),
+            })
+            .collect();
+
+        Export {
+            inputs,
+            aux,
+            constraints,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("failed to serialize the constraint system")
+    }
+
+    /// Returns a graph with the variables and the constraints as nodes,
+    /// each constraint linked to its variables by edges labeled with the
+    /// linear combinations they appear in, and clustered by namespace.
+    /// `ONE` is left out, since most constraints use it.
+    pub fn to_dot(&self) -> String {
+        let mut root = Cluster::default();
+
+        for (i, path) in self.inputs.iter().enumerate().skip(1) {
+            root.insert(path, format!("\"input {}\" [shape=box, style=bold", i));
+        }
+        for (i, path) in self.aux.iter().enumerate() {
+            root.insert(path, format!("\"aux {}\" [shape=ellipse", i));
+        }
+        for (i, constraint) in self.constraints.iter().enumerate() {
+            root.insert(
+                &constraint.path,
+                format!("\"constraint {}\" [shape=diamond", i),
+            );
+        }
+
+        let mut s = String::new();
+        writeln!(s, "graph circuit {{").unwrap();
+        let mut clusters = 0;
+        root.write(&mut s, 1, &mut clusters);
+
+        let nodes: BTreeMap<&str, String> = self
+            .inputs
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, path)| (&path[..], format!("input {}", i)))
+            .chain(
+                self.aux
+                    .iter()
+                    .enumerate()
+                    .map(|(i, path)| (&path[..], format!("aux {}", i))),
+            )
+            .collect();
+
+        for (i, constraint) in self.constraints.iter().enumerate() {
+            for (label, terms) in &[
+                ("A", &constraint.a),
+                ("B", &constraint.b),
+                ("C", &constraint.c),
+            ] {
+                for term in terms.iter() {
+                    if let Some(node) = nodes.get(&term.variable[..]) {
+                        writeln!(
+                            s,
+                            "  \"{}\" -- \"constraint {}\" [label=\"{}\"];",
+                            node, i, label
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+        }
+
+        writeln!(s, "}}").unwrap();
+        s
+    }
+}
+
+fn export_lc<E: ScalarEngine>(
+    lc: &LinearCombination<E>,
+    inputs: &[String],
+    aux: &[String],
+) -> Vec<ExportedTerm> {
+    let mut terms = BTreeMap::new();
+    for (var, coeff) in lc.iter() {
+        let key = match var.get_unchecked() {
+            Index::Input(i) => (0, i),
+            Index::Aux(i) => (1, i),
+        };
+        terms
+            .entry(key)
+            .or_insert_with(E::Fr::zero)
+            .add_assign(coeff);
+    }
+
+    terms
+        .into_iter()
+        .filter(|(_, coeff)| !coeff.is_zero())
+        .map(|((kind, i), coeff)| ExportedTerm {
+            variable: if kind == 0 { &inputs[i] } else { &aux[i] }.clone(),
+            coeff: coeff.into_repr().to_string(),
+        })
+        .collect()
+}
+
+/// The nodes of a namespace, and its child namespaces.
+#[derive(Default)]
+struct Cluster {
+    nodes: Vec<(String, String)>,
+    children: BTreeMap<String, Cluster>,
+}
+
+impl Cluster {
+    /// Inserts the node declared by `node`, without its label and the
+    /// closing bracket, in the namespace of `path`.
+    fn insert(&mut self, path: &str, node: String) {
+        let mut names: Vec<&str> = path.split('/').collect();
+        let name = names.pop().unwrap();
+
+        let cluster = names.into_iter().fold(self, |cluster, namespace| {
+            cluster.children.entry(namespace.to_string()).or_default()
+        });
+        cluster.nodes.push((node, name.to_string()));
+    }
+
+    fn write(&self, s: &mut String, depth: usize, clusters: &mut usize) {
+        let indent = "  ".repeat(depth);
+        for (node, name) in &self.nodes {
+            writeln!(s, "{}{}, label={}];", indent, node, quote(name)).unwrap();
+        }
+
+        for (name, child) in &self.children {
+            writeln!(s, "{}subgraph \"cluster {}\" {{", indent, clusters).unwrap();
+            writeln!(s, "{}  label={};", indent, quote(name)).unwrap();
+            *clusters += 1;
+            child.write(s, depth + 1, clusters);
+            writeln!(s, "{}}}", indent).unwrap();
+        }
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::util_cs::metric_cs::MetricCS;
+    use crate::ConstraintSystem;
+
+    fn synthesize<CS: ConstraintSystem<Bls12>>(cs: &mut CS) {
+        let a = AllocatedBit::alloc(cs.namespace(|| "a"), Some(true)).unwrap();
+        let b = AllocatedBit::alloc(cs.namespace(|| "b \"quoted\""), Some(false)).unwrap();
+        Boolean::xor(cs.namespace(|| "xor"), &Boolean::from(a), &Boolean::from(b)).unwrap();
+
+        let x = cs.alloc_input(|| "x", || Ok(Fr::one())).unwrap();
+        cs.enforce(
+            || "x = 1",
+            |lc| lc + x,
+            |lc| lc + CS::one(),
+            |lc| lc + CS::one(),
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        synthesize(&mut cs);
+        let export = cs.export();
+
+        assert_eq!(export.inputs, vec!["ONE", "x"]);
+        assert_eq!(
+            export.aux,
+            vec!["a/boolean", "b \"quoted\"/boolean", "xor/xor result"]
+        );
+        assert_eq!(export.constraints.len(), 4);
+
+        // The terms are normalized: (1 - a) * a = 0
+        let one = Fr::one().into_repr().to_string();
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        let minus_one = minus_one.into_repr().to_string();
+        let term = |variable: &str, coeff: &str| ExportedTerm {
+            variable: variable.to_string(),
+            coeff: coeff.to_string(),
+        };
+        assert_eq!(
+            export.constraints[0],
+            ExportedConstraint {
+                path: "a/boolean constraint".to_string(),
+                a: vec![term("ONE", &one), term("a/boolean", &minus_one)],
+                b: vec![term("a/boolean", &one)],
+                c: vec![],
+            }
+        );
+
+        let json = export.to_json();
+        assert!(json.contains("\"path\": \"xor/xor constraint\""));
+        assert_eq!(serde_json::from_str::<Export>(&json).unwrap(), export);
+
+        // The names alone are enough
+        let mut metric = MetricCS::<Bls12>::new();
+        synthesize(&mut metric);
+        assert_eq!(metric.export(), export);
+
+        let dot = export.to_dot();
+        assert!(dot.starts_with("graph circuit {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("  \"input 1\" [shape=box, style=bold, label=\"x\"];\n"));
+        assert!(dot.contains("  \"constraint 3\" [shape=diamond, label=\"x = 1\"];\n"));
+        assert!(dot.contains("  subgraph \"cluster 1\" {\n    label=\"b \\\"quoted\\\"\";\n"));
+        assert!(dot.contains("    \"aux 0\" [shape=ellipse, label=\"boolean\"];\n"));
+        assert!(dot.contains("  \"aux 0\" -- \"constraint 0\" [label=\"A\"];\n"));
+        assert!(dot.contains("  \"aux 0\" -- \"constraint 0\" [label=\"B\"];\n"));
+        assert!(dot.contains("  \"input 1\" -- \"constraint 3\" [label=\"A\"];\n"));
+        assert!(!dot.contains("ONE"));
+    }
+}
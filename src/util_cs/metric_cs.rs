@@ -1,3 +1,4 @@
+use super::export::Export;
 use crate::bls::Engine;
 use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
 use ff::{Field, PrimeField, ScalarEngine};
@@ -179,6 +180,11 @@ impl<E: Engine> MetricCS<E> {
         s
     }
 
+    /// Exports the constraints with the paths of their variables.
+    pub fn export(&self) -> Export {
+        Export::new(self.inputs.clone(), self.aux.clone(), &self.constraints)
+    }
+
     fn set_named_obj(&mut self, path: String, to: NamedObject) {
         if self.named_objects.contains_key(&path) {
             panic!("tried to create object at existing path: {}", path);
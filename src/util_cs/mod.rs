@@ -1,3 +1,12 @@
 pub mod bench_cs;
+pub mod export;
 pub mod metric_cs;
+pub mod optimizing_cs;
+pub mod profiling_cs;
+pub mod sat_cs;
+pub mod shape_cs;
+pub mod snapshot;
+mod terms;
 pub mod test_cs;
+pub mod underconstrained_cs;
+pub mod witness_fuzz;
@@ -0,0 +1,645 @@
+//! A constraint system which records a circuit and simplifies it before
+//! handing it to another constraint system.
+//!
+//! Gadgets emit their constraints verbatim, so that a circuit often
+//! contains linear constraints (such as `(a + b) * 1 = c`), which only
+//! define a variable in terms of others, and exact duplicates. Each of
+//! them costs as much as any other constraint in the QAP.
+//! [`OptimizingCS`] substitutes the variables defined by linear
+//! constraints away and drops the duplicate and trivially satisfied
+//! constraints, and [`OptimizedCircuit`] wraps a circuit so that it is
+//! simplified in the same way during parameter generation and proving.
+//!
+//! The simplification only depends on the shape of the circuit, never on
+//! the witness, so that the parameters and the proofs always agree.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use ff::{Field, PrimeField};
+use log::info;
+
+use super::terms::{add_scaled, add_term, terms, Terms, Var};
+use crate::bls::Engine;
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+#[derive(Clone, Debug)]
+pub(super) struct Constraint<F> {
+    pub(super) a: Terms<F>,
+    pub(super) b: Terms<F>,
+    pub(super) c: Terms<F>,
+}
+
+/// Returns the value of `terms` if it is a constant.
+fn constant<F: Field>(terms: &Terms<F>) -> Option<F> {
+    match terms.len() {
+        0 => Some(F::zero()),
+        1 => terms.get(&Var::Input(0)).cloned(),
+        _ => None,
+    }
+}
+
+pub(super) fn aux_variables<'a, F: 'a>(terms: &'a Terms<F>) -> impl Iterator<Item = usize> + 'a {
+    terms.keys().filter_map(|var| match *var {
+        Var::Aux(i) => Some(i),
+        Var::Input(_) => None,
+    })
+}
+
+/// The auxiliary variables eliminated so far, each defined as a linear
+/// combination of the remaining variables.
+#[derive(Debug)]
+struct Substitutions<F> {
+    definitions: HashMap<usize, Terms<F>>,
+    // The eliminated variables whose definitions may use each variable.
+    users: HashMap<usize, Vec<usize>>,
+}
+
+impl<F: Field> Substitutions<F> {
+    fn new() -> Self {
+        Substitutions {
+            definitions: HashMap::new(),
+            users: HashMap::new(),
+        }
+    }
+
+    fn apply(&self, terms: &Terms<F>) -> Terms<F> {
+        let mut res = Terms::new();
+        for (&var, &coeff) in terms {
+            match var {
+                Var::Aux(i) if self.definitions.contains_key(&i) => {
+                    add_scaled(&mut res, &self.definitions[&i], coeff)
+                }
+                _ => add_term(&mut res, var, coeff),
+            }
+        }
+        res
+    }
+
+    /// Eliminates the auxiliary variable `i`, given a definition in which
+    /// all substitutions have already been applied.
+    fn insert(&mut self, i: usize, definition: Terms<F>) {
+        // Keep the existing definitions free of eliminated variables
+        for user in self.users.remove(&i).unwrap_or_default() {
+            let existing = self.definitions.get_mut(&user).unwrap();
+            if let Some(coeff) = existing.remove(&Var::Aux(i)) {
+                add_scaled(existing, &definition, coeff);
+                for j in aux_variables(&definition) {
+                    self.users.entry(j).or_default().push(user);
+                }
+            }
+        }
+
+        for j in aux_variables(&definition) {
+            self.users.entry(j).or_default().push(i);
+        }
+        self.definitions.insert(i, definition);
+    }
+}
+
+/// The effect of [`OptimizingCS::optimize`] on a circuit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OptimizationReport {
+    pub constraints_before: usize,
+    pub constraints_after: usize,
+    pub aux_before: usize,
+    pub aux_after: usize,
+    /// The number of linear constraints removed by eliminating a variable.
+    pub linear_eliminated: usize,
+    /// The number of constraints removed as a duplicate of another one.
+    pub duplicates_removed: usize,
+    /// The number of constraints removed as satisfied by any assignment.
+    pub trivial_removed: usize,
+}
+
+impl OptimizationReport {
+    /// The number of constraints removed from the circuit.
+    pub fn constraints_saved(&self) -> usize {
+        self.constraints_before - self.constraints_after
+    }
+}
+
+impl fmt::Display for OptimizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "constraints: {} -> {}, auxiliary variables: {} -> {} \
+             ({} linear constraints eliminated, {} duplicate and {} trivial constraints removed)",
+            self.constraints_before,
+            self.constraints_after,
+            self.aux_before,
+            self.aux_after,
+            self.linear_eliminated,
+            self.duplicates_removed,
+            self.trivial_removed,
+        )
+    }
+}
+
+/// Records a circuit, with the values of its variables when they are
+/// known, so that it can be optimized and then synthesized into another
+/// constraint system. Names and namespaces are discarded.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
+pub struct OptimizingCS<E: Engine> {
+    inputs: Vec<Option<E::Fr>>,
+    aux: Vec<Option<E::Fr>>,
+    constraints: Vec<Constraint<E::Fr>>,
+}
+
+impl<E: Engine> OptimizingCS<E> {
+    pub fn new() -> Self {
+        OptimizingCS::default()
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Simplifies the recorded circuit.
+    ///
+    /// Each linear constraint is used to eliminate the last allocated
+    /// auxiliary variable it contains, which is replaced by its definition
+    /// in all the other constraints, until none is left. Inputs are never
+    /// eliminated. The duplicate and trivial constraints are then removed,
+    /// as well as the auxiliary variables which are no longer constrained.
+    pub fn optimize(&mut self) -> OptimizationReport {
+        let mut report = OptimizationReport {
+            constraints_before: self.constraints.len(),
+            aux_before: self.aux.len(),
+            ..Default::default()
+        };
+
+        let mut substitutions = Substitutions::new();
+        let mut constraints = std::mem::take(&mut self.constraints);
+
+        // Eliminating a variable may turn a constraint which was already
+        // visited into a linear one, so repeat until nothing changes.
+        loop {
+            let eliminated = report.linear_eliminated;
+            let mut remaining = Vec::with_capacity(constraints.len());
+
+            for constraint in constraints {
+                let constraint = Constraint {
+                    a: substitutions.apply(&constraint.a),
+                    b: substitutions.apply(&constraint.b),
+                    c: substitutions.apply(&constraint.c),
+                };
+
+                // A constant factor `k` makes the constraint `k * x - c = 0`
+                let linear = match (constant(&constraint.a), constant(&constraint.b)) {
+                    (Some(k), _) => Some((k, &constraint.b)),
+                    (None, Some(k)) => Some((k, &constraint.a)),
+                    (None, None) => None,
+                };
+                let linear = linear.map(|(k, x)| {
+                    let mut terms = Terms::new();
+                    add_scaled(&mut terms, x, k);
+                    let mut minus_one = E::Fr::one();
+                    minus_one.negate();
+                    add_scaled(&mut terms, &constraint.c, minus_one);
+                    terms
+                });
+
+                match linear {
+                    Some(terms) if terms.is_empty() => report.trivial_removed += 1,
+                    Some(mut terms) if aux_variables(&terms).next().is_some() => {
+                        let i = aux_variables(&terms).last().unwrap();
+                        let mut coeff = terms.remove(&Var::Aux(i)).unwrap();
+
+                        // var = -(terms - coeff * var) / coeff
+                        coeff = coeff.inverse().unwrap();
+                        coeff.negate();
+                        let mut definition = Terms::new();
+                        add_scaled(&mut definition, &terms, coeff);
+
+                        substitutions.insert(i, definition);
+                        report.linear_eliminated += 1;
+                    }
+                    _ => remaining.push(constraint),
+                }
+            }
+
+            constraints = remaining;
+            if report.linear_eliminated == eliminated {
+                break;
+            }
+        }
+
+        // Two constraints are the same if they only differ by the order of
+        // the factors.
+        let mut seen = BTreeSet::new();
+        for mut constraint in constraints {
+            let mut key = [
+                repr(&constraint.a),
+                repr(&constraint.b),
+                repr(&constraint.c),
+            ];
+            if key[0] > key[1] {
+                key.swap(0, 1);
+                std::mem::swap(&mut constraint.a, &mut constraint.b);
+            }
+
+            if seen.insert(key) {
+                self.constraints.push(constraint);
+            } else {
+                report.duplicates_removed += 1;
+            }
+        }
+
+        report.constraints_after = self.constraints.len();
+        report.aux_after = self.used_aux().len();
+        report
+    }
+
+    /// Synthesizes the recorded circuit into `cs`. The inputs are allocated
+    /// in the same order, and only the auxiliary variables which appear in
+    /// a constraint are allocated.
+    pub fn synthesize_into<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+    ) -> Result<(), SynthesisError> {
+        let inputs = self
+            .inputs
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, value)| {
+                cs.alloc_input(
+                    || format!("input {}", i),
+                    || value.ok_or(SynthesisError::AssignmentMissing),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut aux = HashMap::new();
+        for i in self.used_aux() {
+            let value = self.aux[i];
+            let var = cs.alloc(
+                || format!("aux {}", i),
+                || value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            aux.insert(i, var);
+        }
+
+        let variable = |var: &Var| match *var {
+            Var::Input(0) => CS::one(),
+            Var::Input(i) => inputs[i - 1],
+            Var::Aux(i) => aux[&i],
+        };
+        let lc = |terms: &Terms<E::Fr>, mut lc: LinearCombination<E>| {
+            for (var, coeff) in terms {
+                lc = lc + (*coeff, variable(var));
+            }
+            lc
+        };
+
+        for (i, constraint) in self.constraints.iter().enumerate() {
+            cs.enforce(
+                || format!("constraint {}", i),
+                |zero| lc(&constraint.a, zero),
+                |zero| lc(&constraint.b, zero),
+                |zero| lc(&constraint.c, zero),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn used_aux(&self) -> BTreeSet<usize> {
+        self.constraints
+            .iter()
+            .flat_map(|c| {
+                aux_variables(&c.a)
+                    .chain(aux_variables(&c.b))
+                    .chain(aux_variables(&c.c))
+            })
+            .collect()
+    }
+}
+
+fn repr<F: PrimeField>(terms: &Terms<F>) -> Vec<(Var, F::Repr)> {
+    terms
+        .iter()
+        .map(|(var, coeff)| (*var, coeff.into_repr()))
+        .collect()
+}
+
+impl<E: Engine> Default for OptimizingCS<E> {
+    fn default() -> Self {
+        OptimizingCS {
+            inputs: vec![Some(E::Fr::one())],
+            aux: vec![],
+            constraints: vec![],
+        }
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for OptimizingCS<E> {
+    type Root = Self;
+
+    fn new() -> Self {
+        OptimizingCS::default()
+    }
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux.push(known(f())?);
+
+        Ok(Variable::new_unchecked(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs.push(known(f())?);
+
+        Ok(Variable::new_unchecked(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        self.constraints.push(Constraint {
+            a: terms(&a(LinearCombination::zero())),
+            b: terms(&b(LinearCombination::zero())),
+            c: terms(&c(LinearCombination::zero())),
+        });
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// Values are missing when the circuit is synthesized to generate
+/// parameters, in which case only the shape of the circuit is recorded.
+pub(super) fn known<F>(value: Result<F, SynthesisError>) -> Result<Option<F>, SynthesisError> {
+    match value {
+        Ok(value) => Ok(Some(value)),
+        Err(SynthesisError::AssignmentMissing) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// A circuit which is recorded and optimized by an [`OptimizingCS`] before
+/// being synthesized, so that the same optimized circuit is used by
+/// `generate_parameters` and by the prover.
+#[derive(Clone, Debug)]
+pub struct OptimizedCircuit<C> {
+    circuit: C,
+}
+
+impl<C> OptimizedCircuit<C> {
+    pub fn new(circuit: C) -> Self {
+        OptimizedCircuit { circuit }
+    }
+}
+
+impl<E: Engine, C: Circuit<E>> Circuit<E> for OptimizedCircuit<C> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut optimizing = OptimizingCS::new();
+        self.circuit.synthesize(&mut optimizing)?;
+
+        let report = optimizing.optimize();
+        info!("optimized circuit: {}", report);
+
+        optimizing.synthesize_into(cs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::multieq::MultiEq;
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::gadgets::uint32::UInt32;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    // Proves knowledge of `x` such that `(x + 5)^2 = out`, with a linear
+    // constraint, a duplicate and a trivial constraint.
+    #[derive(Clone)]
+    struct Square {
+        x: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for Square {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let mut five = Fr::one();
+            five.double();
+            five.double();
+            five.add_assign(&Fr::one());
+
+            let y_value = self.x.map(|mut x| {
+                x.add_assign(&five);
+                x
+            });
+            let z_value = y_value.map(|mut y| {
+                y.square();
+                y
+            });
+
+            let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc(|| "y", || y_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let z = cs.alloc(|| "z", || z_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let out = cs.alloc_input(
+                || "out",
+                || z_value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+
+            let one = CS::one();
+            cs.enforce(
+                || "y = x + 5",
+                |lc| lc + x + (five, one),
+                |lc| lc + one,
+                |lc| lc + y,
+            );
+            cs.enforce(|| "z = y^2", |lc| lc + y, |lc| lc + y, |lc| lc + z);
+            cs.enforce(
+                || "z = y * (x + 5)",
+                |lc| lc + x + (five, one),
+                |lc| lc + y,
+                |lc| lc + z,
+            );
+            cs.enforce(|| "out = z", |lc| lc + z, |lc| lc + one, |lc| lc + out);
+            cs.enforce(|| "0 = 0", |lc| lc, |lc| lc, |lc| lc);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_optimize() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let x = Fr::random(&mut rng);
+
+        let mut cs = OptimizingCS::<Bls12>::new();
+        Square { x: Some(x) }.synthesize(&mut cs).unwrap();
+
+        let report = cs.optimize();
+        assert_eq!(
+            report,
+            OptimizationReport {
+                constraints_before: 5,
+                constraints_after: 1,
+                aux_before: 3,
+                aux_after: 1,
+                linear_eliminated: 2,
+                duplicates_removed: 1,
+                trivial_removed: 1,
+            }
+        );
+        assert_eq!(report.constraints_saved(), 4);
+
+        let mut test_cs = TestConstraintSystem::<Bls12>::new();
+        cs.synthesize_into(&mut test_cs).unwrap();
+
+        assert!(test_cs.is_satisfied());
+        assert_eq!(test_cs.num_constraints(), 1);
+        assert_eq!(test_cs.num_inputs(), 2);
+        assert_eq!(test_cs.get("aux 0"), x);
+
+        test_cs.set("aux 0", Fr::random(&mut rng));
+        assert!(!test_cs.is_satisfied());
+
+        // A second pass has nothing left to do
+        let report = cs.optimize();
+        assert_eq!(report.constraints_saved(), 0);
+        assert_eq!(report.aux_before, 3);
+        assert_eq!(report.aux_after, 1);
+    }
+
+    #[test]
+    fn test_optimize_gadgets() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..10 {
+            let values: Vec<u32> = (0..3).map(|_| rng.next_u32()).collect();
+            let expected = values
+                .iter()
+                .fold(0u32, |acc, value| acc.wrapping_add(*value));
+
+            let mut cs = OptimizingCS::<Bls12>::new();
+            {
+                let words: Vec<_> = values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        UInt32::alloc(cs.namespace(|| format!("word {}", i)), Some(*value)).unwrap()
+                    })
+                    .collect();
+
+                let sum = {
+                    let mut cs = MultiEq::new(&mut cs);
+                    UInt32::addmany(cs.namespace(|| "addition"), &words).unwrap()
+                };
+
+                for (i, bit) in sum.into_bits().iter().enumerate() {
+                    let expected = AllocatedBit::alloc(
+                        cs.namespace(|| format!("expected bit {}", i)),
+                        Some((expected >> i) & 1 == 1),
+                    )
+                    .unwrap();
+                    Boolean::enforce_equal(
+                        cs.namespace(|| format!("check bit {}", i)),
+                        bit,
+                        &Boolean::from(expected),
+                    )
+                    .unwrap();
+                }
+            }
+
+            let before = cs.num_constraints();
+            let report = cs.optimize();
+            assert_eq!(report.constraints_before, before);
+            assert!(report.linear_eliminated > 0);
+            assert_eq!(report.constraints_after, cs.num_constraints());
+            assert_eq!(
+                report.constraints_after,
+                before
+                    - report.linear_eliminated
+                    - report.duplicates_removed
+                    - report.trivial_removed
+            );
+
+            let mut test_cs = TestConstraintSystem::<Bls12>::new();
+            cs.synthesize_into(&mut test_cs).unwrap();
+
+            assert!(test_cs.is_satisfied());
+            assert_eq!(test_cs.num_constraints(), report.constraints_after);
+        }
+    }
+
+    #[cfg(feature = "groth16")]
+    #[test]
+    fn test_optimized_proof() {
+        use crate::groth16::{
+            create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+        };
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = generate_random_parameters::<Bls12, _, _>(
+            OptimizedCircuit::new(Square { x: None }),
+            &mut rng,
+        )
+        .unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let x = Fr::random(&mut rng);
+        let mut out = x;
+        out.add_assign(&Fr::from_str("5").unwrap());
+        out.square();
+
+        let proof = create_random_proof(
+            OptimizedCircuit::new(Square { x: Some(x) }),
+            &params,
+            &mut rng,
+        )
+        .unwrap();
+
+        assert!(verify_proof(&pvk, &proof, &[out]).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &[x]).unwrap());
+    }
+}
@@ -0,0 +1,418 @@
+//! A constraint system which aggregates the cost of a circuit by namespace,
+//! to find out which gadgets dominate it.
+//!
+//! The costs can be printed as a tree, or written as collapsed stacks, one
+//! line `a;b;c count` per namespace path, which is the input format of
+//! flamegraph tools such as `flamegraph.pl` and `inferno-flamegraph`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io;
+use std::ops::AddAssign;
+
+use ff::Field;
+
+use crate::bls::Engine;
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// The frame of the costs which are not in any namespace.
+const ROOT_NAME: &str = "root";
+
+/// The cost of a part of a circuit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cost {
+    pub constraints: usize,
+    pub aux: usize,
+    pub inputs: usize,
+    /// The number of terms with a nonzero coefficient in the linear
+    /// combinations of the constraints.
+    pub terms: usize,
+}
+
+impl AddAssign for Cost {
+    fn add_assign(&mut self, other: Cost) {
+        self.constraints += other.constraints;
+        self.aux += other.aux;
+        self.inputs += other.inputs;
+        self.terms += other.terms;
+    }
+}
+
+/// The measure of the cost used for the collapsed stacks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+    Constraints,
+    Aux,
+    Inputs,
+    Terms,
+}
+
+impl Metric {
+    fn of(self, cost: &Cost) -> usize {
+        match self {
+            Metric::Constraints => cost.constraints,
+            Metric::Aux => cost.aux,
+            Metric::Inputs => cost.inputs,
+            Metric::Terms => cost.terms,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    // The cost of the namespace, excluding its children
+    cost: Cost,
+    children: BTreeMap<String, usize>,
+}
+
+/// Aggregates the constraints, variables and terms of a circuit by
+/// namespace path. Namespaces entered several times under the same path
+/// are merged.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
+pub struct ProfilingCS<E: Engine> {
+    nodes: Vec<Node>,
+    current: Vec<usize>,
+    num_inputs: usize,
+    num_aux: usize,
+    _e: std::marker::PhantomData<fn() -> E>,
+}
+
+impl<E: Engine> ProfilingCS<E> {
+    pub fn new() -> Self {
+        ProfilingCS::default()
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.total().constraints
+    }
+
+    /// Returns the cost of the whole circuit.
+    pub fn total(&self) -> Cost {
+        self.total_of(0)
+    }
+
+    /// Returns the cost of the namespace at `path`, including its children,
+    /// or `None` if it was never entered.
+    pub fn cost(&self, path: &str) -> Option<Cost> {
+        self.find(path).map(|node| self.total_of(node))
+    }
+
+    /// Returns the cost of the namespace at `path`, excluding its children.
+    pub fn self_cost(&self, path: &str) -> Option<Cost> {
+        self.find(path).map(|node| self.nodes[node].cost)
+    }
+
+    /// Returns the paths of the namespaces at most `max_depth` levels deep,
+    /// with their cost including their children, depth-first. The path of
+    /// the whole circuit is empty.
+    pub fn costs(&self, max_depth: usize) -> Vec<(String, Cost)> {
+        let mut costs = vec![];
+        self.collect_costs(&mut costs, String::new(), 0, max_depth);
+        costs
+    }
+
+    /// Pretty-prints the cost tree, with the most expensive namespaces
+    /// first at each level.
+    pub fn pretty_print(&self) -> String {
+        let mut s = String::new();
+        self.print_node(&mut s, ROOT_NAME, 0, 0);
+        s
+    }
+
+    /// Returns the collapsed stacks of `metric`, with one line per namespace
+    /// path whose own cost is nonzero. The `;` in names are replaced by `:`,
+    /// since they separate the frames.
+    pub fn collapsed_stacks(&self, metric: Metric) -> String {
+        let mut s = String::new();
+        let mut stack = vec![ROOT_NAME.to_string()];
+        self.collapse_node(&mut s, &mut stack, 0, metric);
+        s
+    }
+
+    /// Writes the collapsed stacks of `metric` to `writer`.
+    pub fn write_collapsed_stacks<W: io::Write>(
+        &self,
+        mut writer: W,
+        metric: Metric,
+    ) -> io::Result<()> {
+        writer.write_all(self.collapsed_stacks(metric).as_bytes())
+    }
+
+    fn find(&self, path: &str) -> Option<usize> {
+        if path.is_empty() {
+            return Some(0);
+        }
+
+        path.split('/')
+            .try_fold(0, |node, name| self.nodes[node].children.get(name).cloned())
+    }
+
+    fn total_of(&self, node: usize) -> Cost {
+        let mut cost = self.nodes[node].cost;
+        for child in self.nodes[node].children.values() {
+            cost += self.total_of(*child);
+        }
+        cost
+    }
+
+    fn collect_costs(
+        &self,
+        costs: &mut Vec<(String, Cost)>,
+        path: String,
+        node: usize,
+        depth_left: usize,
+    ) {
+        costs.push((path.clone(), self.total_of(node)));
+        if depth_left == 0 {
+            return;
+        }
+
+        for (name, child) in &self.nodes[node].children {
+            let child_path = if path.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", path, name)
+            };
+            self.collect_costs(costs, child_path, *child, depth_left - 1);
+        }
+    }
+
+    fn print_node(&self, s: &mut String, name: &str, node: usize, depth: usize) {
+        let cost = self.total_of(node);
+        writeln!(
+            s,
+            "{:indent$}{}: {} constraints, {} aux, {} inputs, {} terms",
+            "",
+            name,
+            cost.constraints,
+            cost.aux,
+            cost.inputs,
+            cost.terms,
+            indent = 2 * depth,
+        )
+        .unwrap();
+
+        let mut children: Vec<_> = self.nodes[node]
+            .children
+            .iter()
+            .map(|(name, child)| (self.total_of(*child).constraints, name, *child))
+            .collect();
+        children.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        for (_, name, child) in children {
+            self.print_node(s, name, child, depth + 1);
+        }
+    }
+
+    fn collapse_node(&self, s: &mut String, stack: &mut Vec<String>, node: usize, metric: Metric) {
+        let count = metric.of(&self.nodes[node].cost);
+        if count > 0 {
+            writeln!(s, "{} {}", stack.join(";"), count).unwrap();
+        }
+
+        for (name, child) in &self.nodes[node].children {
+            stack.push(name.replace(';', ":"));
+            self.collapse_node(s, stack, *child, metric);
+            stack.pop();
+        }
+    }
+
+    fn current_cost(&mut self) -> &mut Cost {
+        let node = *self.current.last().unwrap();
+        &mut self.nodes[node].cost
+    }
+}
+
+impl<E: Engine> Default for ProfilingCS<E> {
+    fn default() -> Self {
+        ProfilingCS {
+            nodes: vec![Node::default()],
+            current: vec![0],
+            num_inputs: 1,
+            num_aux: 0,
+            _e: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for ProfilingCS<E> {
+    type Root = Self;
+
+    fn new() -> Self {
+        ProfilingCS::default()
+    }
+
+    fn alloc<F, A, AR>(&mut self, _: A, _f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.current_cost().aux += 1;
+        self.num_aux += 1;
+
+        Ok(Variable::new_unchecked(Index::Aux(self.num_aux - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.current_cost().inputs += 1;
+        self.num_inputs += 1;
+
+        Ok(Variable::new_unchecked(Index::Input(self.num_inputs - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let terms = [
+            a(LinearCombination::zero()),
+            b(LinearCombination::zero()),
+            c(LinearCombination::zero()),
+        ]
+        .iter()
+        .map(|lc| lc.iter().filter(|(_, coeff)| !coeff.is_zero()).count())
+        .sum::<usize>();
+
+        let cost = self.current_cost();
+        cost.constraints += 1;
+        cost.terms += terms;
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        let name = name_fn().into();
+        let parent = *self.current.last().unwrap();
+
+        let node = match self.nodes[parent].children.get(&name) {
+            Some(node) => *node,
+            None => {
+                self.nodes.push(Node::default());
+                let node = self.nodes.len() - 1;
+                self.nodes[parent].children.insert(name, node);
+                node
+            }
+        };
+        self.current.push(node);
+    }
+
+    fn pop_namespace(&mut self) {
+        assert!(self.current.len() > 1);
+        self.current.pop();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::sha256::sha256;
+    use crate::util_cs::metric_cs::MetricCS;
+
+    fn synthesize<CS: ConstraintSystem<Bls12>>(cs: &mut CS) {
+        let input: Vec<Boolean> = (0..64)
+            .map(|i| {
+                AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(true))
+                    .unwrap()
+                    .into()
+            })
+            .collect();
+
+        sha256(cs.namespace(|| "first; hash"), &input).unwrap();
+        sha256(cs.namespace(|| "second hash"), &input[..32]).unwrap();
+
+        let x = cs.alloc_input(|| "x", || Ok(Fr::one())).unwrap();
+        cs.enforce(|| "x * x = x", |lc| lc + x, |lc| lc + x, |lc| lc + x);
+    }
+
+    #[test]
+    fn test_profiling_cs() {
+        let mut metric = MetricCS::<Bls12>::new();
+        synthesize(&mut metric);
+
+        let mut cs = ProfilingCS::<Bls12>::new();
+        synthesize(&mut cs);
+
+        let total = cs.total();
+        assert_eq!(total.constraints, metric.num_constraints());
+        assert_eq!(total.inputs + 1, metric.num_inputs());
+        assert_eq!(cs.self_cost("").unwrap().constraints, 1);
+        assert_eq!(cs.self_cost("").unwrap().aux, 0);
+        assert_eq!(cs.self_cost("").unwrap().terms, 3);
+
+        // The input bits are each in their own namespace
+        assert_eq!(cs.cost("input bit 7").unwrap().aux, 1);
+        assert_eq!(cs.cost("input bit 7").unwrap().constraints, 1);
+        assert_eq!(cs.cost("input bit 64"), None);
+
+        let first = cs.cost("first; hash").unwrap();
+        let second = cs.cost("second hash").unwrap();
+        assert_eq!(
+            total.constraints,
+            64 + first.constraints + second.constraints + 1
+        );
+        assert_eq!(cs.cost("first; hash/block 0"), Some(first));
+
+        let tree = cs.pretty_print();
+        assert!(tree.starts_with(&format!(
+            "root: {} constraints, {} aux, 1 inputs, {} terms\n",
+            total.constraints, total.aux, total.terms
+        )));
+        assert!(tree.contains("\n  first; hash: "));
+
+        // Each unit of cost appears exactly once in the collapsed stacks
+        for &metric in &[
+            Metric::Constraints,
+            Metric::Aux,
+            Metric::Inputs,
+            Metric::Terms,
+        ] {
+            let stacks = cs.collapsed_stacks(metric);
+            let sum: usize = stacks
+                .lines()
+                .map(|line| {
+                    let (stack, count) = line.split_at(line.rfind(' ').unwrap());
+                    assert!(stack.starts_with("root"));
+                    assert!(!stack.contains("first; hash"));
+                    count[1..].parse::<usize>().unwrap()
+                })
+                .sum();
+            assert_eq!(sum, metric.of(&total));
+        }
+        assert!(cs
+            .collapsed_stacks(Metric::Constraints)
+            .lines()
+            .any(|line| line.starts_with("root;first: hash;block 0;compression round 0;")));
+
+        let costs = cs.costs(1);
+        assert_eq!(costs[0], (String::new(), total));
+        assert_eq!(costs.len(), 64 + 2 + 1);
+        assert!(costs.contains(&("first; hash".to_string(), first)));
+        assert!(cs
+            .costs(2)
+            .contains(&("first; hash/block 0".to_string(), first)));
+
+        let mut written = vec![];
+        cs.write_collapsed_stacks(&mut written, Metric::Terms)
+            .unwrap();
+        assert_eq!(written, cs.collapsed_stacks(Metric::Terms).into_bytes());
+    }
+}
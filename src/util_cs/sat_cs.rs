@@ -0,0 +1,286 @@
+//! A constraint system which checks that a witness satisfies a circuit as
+//! it is synthesized.
+//!
+//! [`TestConstraintSystem`](crate::gadgets::test::TestConstraintSystem)
+//! keeps every constraint and every name, which doesn't scale to production
+//! circuits. [`SatCS`] only keeps the assignment, checks each constraint
+//! when it is enforced, and records the first failures, so that a witness
+//! can be checked before running the prover.
+
+use std::fmt;
+
+use ff::Field;
+
+use crate::bls::Engine;
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// The number of failures recorded by [`SatCS::new`].
+const DEFAULT_MAX_FAILURES: usize = 10;
+
+/// A constraint which is not satisfied by the witness.
+pub struct SatFailure<E: Engine> {
+    /// The index of the constraint, in the order of enforcement.
+    pub index: usize,
+    pub path: String,
+    pub a: E::Fr,
+    pub b: E::Fr,
+    pub c: E::Fr,
+}
+
+impl<E: Engine> fmt::Display for SatFailure<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "constraint {} `{}`: {} * {} != {}",
+            self.index, self.path, self.a, self.b, self.c
+        )
+    }
+}
+
+/// Checks each constraint against the assignment when it is enforced. The
+/// annotations are only computed for the recorded failures.
+#[allow(clippy::upper_case_acronyms)]
+pub struct SatCS<E: Engine> {
+    inputs: Vec<E::Fr>,
+    aux: Vec<E::Fr>,
+    current_namespace: Vec<String>,
+    num_constraints: usize,
+    num_unsatisfied: usize,
+    max_failures: usize,
+    failures: Vec<SatFailure<E>>,
+}
+
+impl<E: Engine> SatCS<E> {
+    pub fn new() -> Self {
+        SatCS::default()
+    }
+
+    /// Records at most `max_failures` unsatisfied constraints.
+    pub fn with_max_failures(max_failures: usize) -> Self {
+        SatCS {
+            max_failures,
+            ..SatCS::default()
+        }
+    }
+
+    pub fn is_satisfied(&self) -> bool {
+        self.num_unsatisfied == 0
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Returns the number of unsatisfied constraints, including the ones
+    /// which weren't recorded.
+    pub fn num_unsatisfied(&self) -> usize {
+        self.num_unsatisfied
+    }
+
+    /// Returns the first unsatisfied constraints.
+    pub fn failures(&self) -> &[SatFailure<E>] {
+        &self.failures
+    }
+
+    fn eval(&self, lc: &LinearCombination<E>) -> E::Fr {
+        let mut acc = E::Fr::zero();
+        for (var, coeff) in lc.iter() {
+            let mut tmp = match var.get_unchecked() {
+                Index::Input(i) => self.inputs[i],
+                Index::Aux(i) => self.aux[i],
+            };
+            tmp.mul_assign(coeff);
+            acc.add_assign(&tmp);
+        }
+
+        acc
+    }
+}
+
+impl<E: Engine> Default for SatCS<E> {
+    fn default() -> Self {
+        SatCS {
+            inputs: vec![E::Fr::one()],
+            aux: vec![],
+            current_namespace: vec![],
+            num_constraints: 0,
+            num_unsatisfied: 0,
+            max_failures: DEFAULT_MAX_FAILURES,
+            failures: vec![],
+        }
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for SatCS<E> {
+    type Root = Self;
+
+    fn new() -> Self {
+        SatCS::default()
+    }
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux.push(f()?);
+
+        Ok(Variable::new_unchecked(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs.push(f()?);
+
+        Ok(Variable::new_unchecked(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let a = self.eval(&a(LinearCombination::zero()));
+        let b = self.eval(&b(LinearCombination::zero()));
+        let c = self.eval(&c(LinearCombination::zero()));
+
+        let mut product = a;
+        product.mul_assign(&b);
+
+        if product != c {
+            if self.failures.len() < self.max_failures {
+                let mut path = self.current_namespace.clone();
+                path.push(annotation().into());
+
+                self.failures.push(SatFailure {
+                    index: self.num_constraints,
+                    path: path.join("/"),
+                    a,
+                    b,
+                    c,
+                });
+            }
+            self.num_unsatisfied += 1;
+        }
+
+        self.num_constraints += 1;
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.current_namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self) {
+        assert!(self.current_namespace.pop().is_some());
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::num::AllocatedNum;
+    use crate::gadgets::sha256::sha256;
+    use crate::gadgets::test::TestConstraintSystem;
+
+    fn synthesize<CS: ConstraintSystem<Bls12>>(cs: &mut CS) {
+        let input: Vec<Boolean> = (0..64)
+            .map(|i| {
+                AllocatedBit::alloc(
+                    cs.namespace(|| format!("input bit {}", i)),
+                    Some(i % 3 == 0),
+                )
+                .unwrap()
+                .into()
+            })
+            .collect();
+
+        sha256(cs.namespace(|| "sha256"), &input).unwrap();
+    }
+
+    #[test]
+    fn test_sat_cs() {
+        let mut cs = SatCS::<Bls12>::new();
+        synthesize(&mut cs);
+
+        let mut test_cs = TestConstraintSystem::<Bls12>::new();
+        synthesize(&mut test_cs);
+
+        assert!(cs.is_satisfied());
+        assert!(cs.failures().is_empty());
+        assert_eq!(cs.num_constraints(), test_cs.num_constraints());
+        assert_eq!(cs.num_inputs(), test_cs.num_inputs());
+    }
+
+    #[test]
+    fn test_sat_cs_failures() {
+        let mut cs = SatCS::<Bls12>::with_max_failures(2);
+
+        let mut three = Fr::one();
+        three.double();
+        three.add_assign(&Fr::one());
+
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(three)).unwrap();
+        for i in 0..4 {
+            let mut cs = cs.namespace(|| format!("round {}", i));
+
+            // Claims that x^2 = x, then that x * x = x^2
+            let squared = AllocatedNum::alloc(cs.namespace(|| "squared"), || Ok(three)).unwrap();
+            cs.enforce(
+                || "x * x = squared",
+                |lc| lc + x.get_variable(),
+                |lc| lc + x.get_variable(),
+                |lc| lc + squared.get_variable(),
+            );
+            x.square(cs.namespace(|| "square")).unwrap();
+        }
+
+        assert!(!cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 8);
+        assert_eq!(cs.num_unsatisfied(), 4);
+
+        let failures = cs.failures();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].index, 0);
+        assert_eq!(failures[0].path, "round 0/x * x = squared");
+        assert!(failures[0].a == three);
+        assert!(failures[0].b == three);
+        assert!(failures[0].c == three);
+        assert_eq!(failures[1].index, 2);
+        assert_eq!(failures[1].path, "round 1/x * x = squared");
+        assert_eq!(
+            failures[1].to_string(),
+            format!(
+                "constraint 2 `round 1/x * x = squared`: {} * {} != {}",
+                three, three, three
+            )
+        );
+
+        // The witness is needed
+        let mut cs = SatCS::<Bls12>::new();
+        assert!(cs
+            .alloc(|| "x", || Err(SynthesisError::AssignmentMissing))
+            .is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! A constraint system which computes a digest of the shape of a circuit.
+//!
+//! The parameters of a circuit only depend on its R1CS matrices, so that
+//! two circuits with the same digest share their parameters. The digest
+//! ignores the annotations, the namespaces and the witness, and doesn't
+//! depend on the order in which the terms of a linear combination are added.
+//!
+//! The digest is the BLAKE2s-256 hash of, for each constraint, the linear
+//! combinations `A`, `B` and `C`, each encoded as:
+//!
+//! - the number of terms with a nonzero coefficient, as a big-endian `u64`,
+//! - then for each of them, ordered by variable with the inputs first,
+//!   `b'I'` or `b'A'`, the index of the variable as a big-endian `u64`, and
+//!   the coefficient as its big-endian representation,
+//!
+//! followed by the number of inputs (including `ONE`), of auxiliary
+//! variables and of constraints, each as a big-endian `u64`. This encoding
+//! is part of the API: changing it would invalidate the digests recorded by
+//! the users.
+
+use blake2s_simd::{Params as Blake2sParams, State as Blake2sState};
+use byteorder::{BigEndian, ByteOrder};
+use ff::{PrimeField, PrimeFieldRepr};
+
+use super::terms::{terms, Terms, Var};
+use crate::bls::Engine;
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// Computes the digest of the R1CS matrices of a circuit, without storing
+/// them.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
+pub struct ShapeCS<E: Engine> {
+    inputs: usize,
+    aux: usize,
+    constraints: usize,
+    state: Blake2sState,
+    _e: std::marker::PhantomData<fn() -> E>,
+}
+
+impl<E: Engine> ShapeCS<E> {
+    pub fn new() -> Self {
+        ShapeCS::default()
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.aux
+    }
+
+    /// Returns the digest of the circuit synthesized so far, in hexadecimal.
+    pub fn digest(&self) -> String {
+        let mut state = self.state.clone();
+
+        let mut buf = [0u8; 24];
+        BigEndian::write_u64(&mut buf[0..8], self.inputs as u64);
+        BigEndian::write_u64(&mut buf[8..16], self.aux as u64);
+        BigEndian::write_u64(&mut buf[16..24], self.constraints as u64);
+        state.update(&buf);
+
+        state.finalize().to_hex().to_string()
+    }
+
+    fn hash_terms(&mut self, terms: &Terms<E::Fr>) {
+        let mut buf = [0u8; 9 + 32];
+        BigEndian::write_u64(&mut buf[0..8], terms.len() as u64);
+        self.state.update(&buf[0..8]);
+
+        for (var, coeff) in terms {
+            let (tag, index) = match *var {
+                Var::Input(i) => (b'I', i),
+                Var::Aux(i) => (b'A', i),
+            };
+            buf[0] = tag;
+            BigEndian::write_u64(&mut buf[1..9], index as u64);
+            coeff
+                .into_repr()
+                .write_be(&mut buf[9..])
+                .expect("failed to write coeff");
+
+            self.state.update(&buf);
+        }
+    }
+}
+
+impl<E: Engine> Default for ShapeCS<E> {
+    fn default() -> Self {
+        ShapeCS {
+            inputs: 1,
+            aux: 0,
+            constraints: 0,
+            state: Blake2sParams::new().hash_length(32).to_state(),
+            _e: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for ShapeCS<E> {
+    type Root = Self;
+
+    fn new() -> Self {
+        ShapeCS::default()
+    }
+
+    fn alloc<F, A, AR>(&mut self, _: A, _f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux += 1;
+
+        Ok(Variable::new_unchecked(Index::Aux(self.aux - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs += 1;
+
+        Ok(Variable::new_unchecked(Index::Input(self.inputs - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        self.hash_terms(&terms(&a(LinearCombination::zero())));
+        self.hash_terms(&terms(&b(LinearCombination::zero())));
+        self.hash_terms(&terms(&c(LinearCombination::zero())));
+        self.constraints += 1;
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::sha256::sha256;
+    use ff::Field;
+
+    // Computes `z = 2 * (x + y)` and `y^2 = 1 - z`, with `x` an input.
+    fn synthesize<CS: ConstraintSystem<Bls12>>(cs: &mut CS, prefix: &str, witness: Option<Fr>) {
+        let value = || witness.ok_or(SynthesisError::AssignmentMissing);
+        let x = cs.alloc_input(|| format!("{} x", prefix), value).unwrap();
+        let y = cs.alloc(|| format!("{} y", prefix), value).unwrap();
+        let z = cs.alloc(|| format!("{} z", prefix), value).unwrap();
+
+        let mut two = Fr::one();
+        two.double();
+        let one = CS::one();
+        cs.enforce(
+            || format!("{} z = 2 * (x + y)", prefix),
+            |lc| lc + y + x,
+            |lc| lc + (two, one),
+            |lc| lc + z,
+        );
+        cs.enforce(
+            || format!("{} y^2 = 1 - z", prefix),
+            |lc| lc + y,
+            |lc| lc + y - z + z + (Fr::zero(), x),
+            |lc| lc + one - z,
+        );
+    }
+
+    #[test]
+    fn test_shape_cs() {
+        let mut cs = ShapeCS::<Bls12>::new();
+        synthesize(&mut cs, "a", None);
+        assert_eq!(cs.num_constraints(), 2);
+        assert_eq!(cs.num_inputs(), 2);
+        assert_eq!(cs.num_aux(), 2);
+
+        // The encoding must stay the same across releases
+        assert_eq!(
+            cs.digest(),
+            "8953a322fcc3f611b0d610c70d68a1f5e7333744dcd76f3af2ef0f4280340f66"
+        );
+
+        // Annotations and witness don't matter
+        let mut other = ShapeCS::<Bls12>::new();
+        synthesize(&mut other, "b", Some(Fr::one()));
+        assert_eq!(other.digest(), cs.digest());
+
+        // Nor which constraint system the namespaces are pushed on
+        let mut other = ShapeCS::<Bls12>::new();
+        synthesize(&mut other.namespace(|| "c"), "c", None);
+        assert_eq!(other.digest(), cs.digest());
+
+        // But the structure does
+        let mut other = ShapeCS::<Bls12>::new();
+        synthesize(&mut other, "a", None);
+        other.alloc(|| "unused", || Ok(Fr::one())).unwrap();
+        assert_ne!(other.digest(), cs.digest());
+
+        let mut other = ShapeCS::<Bls12>::new();
+        synthesize(&mut other, "a", None);
+        other.enforce(|| "0 = 0", |lc| lc, |lc| lc, |lc| lc);
+        assert_ne!(other.digest(), cs.digest());
+    }
+
+    #[test]
+    fn test_shape_cs_gadget() {
+        let digest = |bits: &[bool]| {
+            let mut cs = ShapeCS::<Bls12>::new();
+            let input: Vec<Boolean> = bits
+                .iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(*b))
+                        .unwrap()
+                        .into()
+                })
+                .collect();
+            sha256(cs.namespace(|| "sha256"), &input).unwrap();
+            cs.digest()
+        };
+
+        assert_eq!(digest(&[false; 64]), digest(&[true; 64]));
+        assert_ne!(digest(&[false; 64]), digest(&[false; 72]));
+    }
+}
@@ -0,0 +1,192 @@
+//! Snapshots of the constraint counts of a circuit per namespace, checked
+//! in next to the tests, so that any change in the cost of a gadget shows
+//! up in review.
+//!
+//! ```ignore
+//! let mut cs = ProfilingCS::<Bls12>::new();
+//! circuit.synthesize(&mut cs)?;
+//! assert_constraint_snapshot(&cs, 2, "tests/snapshots/circuit.txt");
+//! ```
+//!
+//! The assertion fails with the namespaces whose count changed. Running the
+//! tests with `BELLPERSON_BLESS=1` writes the new counts to the snapshots
+//! instead.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use super::profiling_cs::ProfilingCS;
+use crate::bls::Engine;
+
+/// The environment variable which makes the snapshot assertions write the
+/// current counts instead of checking them, unless empty or `0`.
+pub const BLESS_VAR: &str = "BELLPERSON_BLESS";
+
+/// The name of the whole circuit in the snapshots.
+const ROOT_NAME: &str = "root";
+
+/// Renders the constraint counts of the namespaces at most `max_depth`
+/// levels deep, one `path: count` line each.
+pub fn constraint_snapshot<E: Engine>(cs: &ProfilingCS<E>, max_depth: usize) -> String {
+    let mut s = format!(
+        "# Constraint counts per namespace, updated with {}=1\n",
+        BLESS_VAR
+    );
+    for (path, cost) in cs.costs(max_depth) {
+        let path = if path.is_empty() {
+            ROOT_NAME.to_string()
+        } else {
+            format!("{}/{}", ROOT_NAME, path)
+        };
+        s += &format!("{}: {}\n", path, cost.constraints);
+    }
+
+    s
+}
+
+/// Panics if the constraint counts of the namespaces of `cs`, at most
+/// `max_depth` levels deep, differ from the snapshot at `path`, or writes
+/// them to `path` if [`BLESS_VAR`] is set.
+pub fn assert_constraint_snapshot<E: Engine, P: AsRef<Path>>(
+    cs: &ProfilingCS<E>,
+    max_depth: usize,
+    path: P,
+) {
+    let bless = env::var(BLESS_VAR).map_or(false, |value| !value.is_empty() && value != "0");
+
+    if let Err(e) = check_snapshot(&constraint_snapshot(cs, max_depth), path.as_ref(), bless) {
+        panic!("{}", e);
+    }
+}
+
+fn check_snapshot(actual: &str, path: &Path, bless: bool) -> Result<(), String> {
+    if bless {
+        return fs::write(path, actual)
+            .map_err(|e| format!("failed to write the snapshot {}: {}", path.display(), e));
+    }
+
+    let expected = fs::read_to_string(path).map_err(|e| {
+        format!(
+            "failed to read the snapshot {}: {}\nrun with {}=1 to create it",
+            path.display(),
+            e,
+            BLESS_VAR
+        )
+    })?;
+
+    let diff = diff(&parse(&expected), &parse(actual));
+    if diff.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "the constraint counts differ from the snapshot {}:\n{}\
+             run with {}=1 to update it",
+            path.display(),
+            diff,
+            BLESS_VAR
+        ))
+    }
+}
+
+fn parse(snapshot: &str) -> BTreeMap<&str, &str> {
+    snapshot
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut parts = line.rsplitn(2, ": ");
+            let count = parts.next().unwrap();
+            (parts.next().unwrap_or(""), count)
+        })
+        .collect()
+}
+
+fn diff(expected: &BTreeMap<&str, &str>, actual: &BTreeMap<&str, &str>) -> String {
+    let paths: BTreeSet<&str> = expected.keys().chain(actual.keys()).cloned().collect();
+
+    let mut s = String::new();
+    for path in paths {
+        match (expected.get(path), actual.get(path)) {
+            (Some(old), Some(new)) if old != new => {
+                s += &format!("~ {}: {} -> {}\n", path, old, new);
+            }
+            (Some(old), None) => s += &format!("- {}: {}\n", path, old),
+            (None, Some(new)) => s += &format!("+ {}: {}\n", path, new),
+            _ => {}
+        }
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::Bls12;
+    use crate::gadgets::multieq::MultiEq;
+    use crate::gadgets::uint32::UInt32;
+    use crate::ConstraintSystem;
+
+    fn synthesize<CS: ConstraintSystem<Bls12>>(cs: &mut CS, operands: usize) {
+        let words: Vec<_> = (0..operands)
+            .map(|i| UInt32::alloc(cs.namespace(|| format!("word {}", i)), Some(1)).unwrap())
+            .collect();
+
+        let mut cs = cs.namespace(|| "sum");
+        let mut cs = MultiEq::new(cs.namespace(|| "addition"));
+        UInt32::addmany(cs.namespace(|| "addmany"), &words).unwrap();
+    }
+
+    fn snapshot(operands: usize) -> String {
+        let mut cs = ProfilingCS::<Bls12>::new();
+        synthesize(&mut cs, operands);
+        constraint_snapshot(&cs, 1)
+    }
+
+    #[test]
+    fn test_constraint_snapshot() {
+        // Each word has 32 boolean constraints, and the sum of three or four
+        // words has 34 result bits and one constraint to check them
+        assert_eq!(
+            snapshot(3),
+            "# Constraint counts per namespace, updated with BELLPERSON_BLESS=1\n\
+             root: 131\n\
+             root/sum: 35\n\
+             root/word 0: 32\n\
+             root/word 1: 32\n\
+             root/word 2: 32\n"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.txt");
+
+        let e = check_snapshot(&snapshot(3), &path, false).unwrap_err();
+        assert!(e.starts_with("failed to read the snapshot "));
+        assert!(e.ends_with("run with BELLPERSON_BLESS=1 to create it"));
+
+        check_snapshot(&snapshot(3), &path, true).unwrap();
+        check_snapshot(&snapshot(3), &path, false).unwrap();
+
+        // Line endings and trailing blank lines don't matter
+        fs::write(&path, snapshot(3).replace('\n', "\r\n") + "\n").unwrap();
+        check_snapshot(&snapshot(3), &path, false).unwrap();
+
+        let e = check_snapshot(&snapshot(4), &path, false).unwrap_err();
+        assert_eq!(
+            e,
+            format!(
+                "the constraint counts differ from the snapshot {}:\n\
+                 ~ root: 131 -> 163\n\
+                 + root/word 3: 32\n\
+                 run with BELLPERSON_BLESS=1 to update it",
+                path.display()
+            )
+        );
+
+        check_snapshot(&snapshot(4), &path, true).unwrap();
+        let e = check_snapshot(&snapshot(2), &path, false).unwrap_err();
+        assert!(e.contains("\n~ root/sum: 35 -> 34\n- root/word 2: 32\n- root/word 3: 32\n"));
+    }
+}
@@ -0,0 +1,55 @@
+//! Linear combinations as ordered maps of terms, shared by the constraint
+//! systems of this module which analyze the matrices of a circuit.
+//!
+//! The digest of [`ShapeCS`](super::shape_cs::ShapeCS) is computed from
+//! this representation, terms ordered by variable with the inputs first
+//! and without zero coefficients, so it must not change.
+
+use std::collections::BTreeMap;
+
+use ff::{Field, ScalarEngine};
+
+use crate::{Index, LinearCombination, Variable};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(super) enum Var {
+    Input(usize),
+    Aux(usize),
+}
+
+impl From<Variable> for Var {
+    fn from(var: Variable) -> Self {
+        match var.get_unchecked() {
+            Index::Input(i) => Var::Input(i),
+            Index::Aux(i) => Var::Aux(i),
+        }
+    }
+}
+
+/// A linear combination with no zero coefficient, ordered by variable.
+pub(super) type Terms<F> = BTreeMap<Var, F>;
+
+pub(super) fn terms<E: ScalarEngine>(lc: &LinearCombination<E>) -> Terms<E::Fr> {
+    let mut terms = Terms::new();
+    for (&var, coeff) in lc.iter() {
+        add_term(&mut terms, var.into(), *coeff);
+    }
+    terms
+}
+
+pub(super) fn add_term<F: Field>(terms: &mut Terms<F>, var: Var, coeff: F) {
+    let entry = terms.entry(var).or_insert_with(F::zero);
+    entry.add_assign(&coeff);
+    if entry.is_zero() {
+        terms.remove(&var);
+    }
+}
+
+/// Adds `coeff * other` to `terms`.
+pub(super) fn add_scaled<F: Field>(terms: &mut Terms<F>, other: &Terms<F>, coeff: F) {
+    for (&var, c) in other {
+        let mut c = *c;
+        c.mul_assign(&coeff);
+        add_term(terms, var, c);
+    }
+}
@@ -0,0 +1,427 @@
+//! A constraint system which looks for the auxiliary variables of a circuit
+//! whose value is not uniquely determined by the inputs.
+//!
+//! Parameter generation only rejects the variables which appear in no
+//! constraint at all. A variable which is constrained, but not enough, lets
+//! a malicious prover choose its value, which usually breaks the soundness
+//! of the circuit.
+//!
+//! The analysis propagates the determined variables through the
+//! constraints, starting from the inputs and the variables which are
+//! assumed to be determined, such as the private inputs of the circuit:
+//!
+//! - once `A` (or `B`) is determined, `A * B = C` is a linear equation, which
+//!   determines its variable if there is only one left,
+//! - or all of its variables, if they are booleans packed with distinct
+//!   powers of two.
+//!
+//! It needs the witness, to know which factors are nonzero. Only these two
+//! rules are followed, so that the reported variables may actually be
+//! determined through other reasoning, such as several equalities packed in
+//! one constraint by [`MultiEq`](crate::gadgets::multieq::MultiEq): they are
+//! suspicious, and worth a look.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ff::{Field, PrimeField};
+
+use super::optimizing_cs::{aux_variables, known, Constraint};
+use super::terms::{add_scaled, terms, Terms, Var};
+use crate::bls::Engine;
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// Records a circuit with its witness and the paths of its auxiliary
+/// variables, to find the under-constrained ones.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
+pub struct UnderconstrainedCS<E: Engine> {
+    inputs: Vec<Option<E::Fr>>,
+    aux: Vec<(Option<E::Fr>, String)>,
+    constraints: Vec<Constraint<E::Fr>>,
+    current_namespace: Vec<String>,
+    assumed: Vec<String>,
+}
+
+impl<E: Engine> UnderconstrainedCS<E> {
+    pub fn new() -> Self {
+        UnderconstrainedCS::default()
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Assumes that the auxiliary variables at `path`, or in the namespace at
+    /// `path`, are determined, as the private inputs of the circuit are.
+    pub fn assume_determined(&mut self, path: &str) {
+        self.assumed.push(path.to_string());
+    }
+
+    /// Returns the paths of the auxiliary variables which could not be
+    /// shown to be determined, in the order they were allocated.
+    pub fn underconstrained(&self) -> Vec<&str> {
+        let mut determined: Vec<bool> = self
+            .aux
+            .iter()
+            .map(|(_, path)| self.is_assumed(path))
+            .collect();
+        let booleans = self.booleans();
+        let powers = powers_of_two::<E::Fr>();
+
+        let mut pending: Vec<&Constraint<E::Fr>> = self.constraints.iter().collect();
+        loop {
+            let before = pending.len();
+            pending.retain(|constraint| {
+                match self.solve(constraint, &determined, &booleans, &powers) {
+                    Some(solved) => {
+                        for i in solved {
+                            determined[i] = true;
+                        }
+                        false
+                    }
+                    None => true,
+                }
+            });
+
+            if pending.len() == before {
+                break;
+            }
+        }
+
+        self.aux
+            .iter()
+            .zip(determined)
+            .filter(|(_, determined)| !determined)
+            .map(|((_, path), _)| &path[..])
+            .collect()
+    }
+
+    fn is_assumed(&self, path: &str) -> bool {
+        self.assumed.iter().any(|assumed| {
+            path.starts_with(&assumed[..])
+                && (path.len() == assumed.len() || path[assumed.len()..].starts_with('/'))
+        })
+    }
+
+    /// Returns the variables constrained to be booleans, by `(1 - x) * x = 0`
+    /// up to the order of the factors and their scaling.
+    fn booleans(&self) -> BTreeSet<usize> {
+        self.constraints
+            .iter()
+            .filter(|constraint| constraint.c.is_empty())
+            .filter_map(|constraint| {
+                boolean_variable(&constraint.a, &constraint.b)
+                    .or_else(|| boolean_variable(&constraint.b, &constraint.a))
+            })
+            .collect()
+    }
+
+    /// Returns the auxiliary variables which `constraint` determines, once
+    /// the `determined` ones are, or `None` if it can't be used yet.
+    fn solve(
+        &self,
+        constraint: &Constraint<E::Fr>,
+        determined: &[bool],
+        booleans: &BTreeSet<usize>,
+        powers: &BTreeMap<<E::Fr as PrimeField>::Repr, i64>,
+    ) -> Option<Vec<usize>> {
+        let is_determined = |terms: &Terms<E::Fr>| aux_variables(terms).all(|i| determined[i]);
+
+        // The linear equation `a * B - C = 0` or `b * A - C = 0`
+        let mut linear = Terms::new();
+        if is_determined(&constraint.a) {
+            add_scaled(&mut linear, &constraint.b, self.eval(&constraint.a)?);
+        } else if is_determined(&constraint.b) {
+            add_scaled(&mut linear, &constraint.a, self.eval(&constraint.b)?);
+        } else {
+            return None;
+        }
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
+        add_scaled(&mut linear, &constraint.c, minus_one);
+
+        let unknowns: Vec<(usize, E::Fr)> = linear
+            .iter()
+            .filter_map(|(var, coeff)| match *var {
+                Var::Aux(i) if !determined[i] => Some((i, *coeff)),
+                _ => None,
+            })
+            .collect();
+
+        if unknowns.len() > 1 && !packs_bits(&unknowns, booleans, powers) {
+            return None;
+        }
+
+        Some(unknowns.into_iter().map(|(i, _)| i).collect())
+    }
+
+    fn eval(&self, terms: &Terms<E::Fr>) -> Option<E::Fr> {
+        let mut acc = E::Fr::zero();
+        for (var, coeff) in terms {
+            let mut tmp = match *var {
+                Var::Input(i) => self.inputs[i]?,
+                Var::Aux(i) => self.aux[i].0?,
+            };
+            tmp.mul_assign(coeff);
+            acc.add_assign(&tmp);
+        }
+
+        Some(acc)
+    }
+}
+
+/// Returns `x` if `a` is `k * x` and `b` is `m * (1 - x)`.
+fn boolean_variable<F: Field>(a: &Terms<F>, b: &Terms<F>) -> Option<usize> {
+    let var = match (a.len(), a.iter().next()) {
+        (1, Some((Var::Aux(i), _))) => *i,
+        _ => return None,
+    };
+
+    let one = b.get(&Var::Input(0))?;
+    let mut coeff = *b.get(&Var::Aux(var))?;
+    coeff.add_assign(one);
+
+    if b.len() == 2 && coeff.is_zero() {
+        Some(var)
+    } else {
+        None
+    }
+}
+
+/// Checks that `unknowns` are booleans whose coefficients are distinct
+/// powers of two, up to a common factor, and span fewer bits than the
+/// capacity of the field, so that only one assignment satisfies the
+/// equation.
+fn packs_bits<F: PrimeField>(
+    unknowns: &[(usize, F)],
+    booleans: &BTreeSet<usize>,
+    powers: &BTreeMap<F::Repr, i64>,
+) -> bool {
+    if !unknowns.iter().all(|(i, _)| booleans.contains(i)) {
+        return false;
+    }
+
+    let inv = unknowns[0].1.inverse().unwrap();
+    let exponents: Option<BTreeSet<i64>> = unknowns
+        .iter()
+        .map(|(_, coeff)| {
+            let mut ratio = *coeff;
+            ratio.mul_assign(&inv);
+            powers.get(&ratio.into_repr()).cloned()
+        })
+        .collect();
+
+    match exponents {
+        Some(exponents) if exponents.len() == unknowns.len() => {
+            let min = exponents.iter().next().unwrap();
+            let max = exponents.iter().next_back().unwrap();
+            max - min < i64::from(F::CAPACITY)
+        }
+        _ => false,
+    }
+}
+
+/// Maps `2^e` to `e`, for `e` between `-CAPACITY` and `CAPACITY` exclusive.
+fn powers_of_two<F: PrimeField>() -> BTreeMap<F::Repr, i64> {
+    let mut two = F::one();
+    two.double();
+    let half = two.inverse().unwrap();
+
+    let mut powers = BTreeMap::new();
+    let mut power = F::one();
+    let mut inverse = F::one();
+    for e in 0..i64::from(F::CAPACITY) {
+        powers.insert(power.into_repr(), e);
+        powers.insert(inverse.into_repr(), -e);
+        power.double();
+        inverse.mul_assign(&half);
+    }
+
+    powers
+}
+
+impl<E: Engine> Default for UnderconstrainedCS<E> {
+    fn default() -> Self {
+        UnderconstrainedCS {
+            inputs: vec![Some(E::Fr::one())],
+            aux: vec![],
+            constraints: vec![],
+            current_namespace: vec![],
+            assumed: vec![],
+        }
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for UnderconstrainedCS<E> {
+    type Root = Self;
+
+    fn new() -> Self {
+        UnderconstrainedCS::default()
+    }
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let mut path = self.current_namespace.clone();
+        path.push(annotation().into());
+        self.aux.push((known(f())?, path.join("/")));
+
+        Ok(Variable::new_unchecked(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs.push(known(f())?);
+
+        Ok(Variable::new_unchecked(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        self.constraints.push(Constraint {
+            a: terms(&a(LinearCombination::zero())),
+            b: terms(&b(LinearCombination::zero())),
+            c: terms(&c(LinearCombination::zero())),
+        });
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.current_namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self) {
+        assert!(self.current_namespace.pop().is_some());
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::multieq::MultiEq;
+    use crate::gadgets::num::AllocatedNum;
+    use crate::gadgets::uint32::UInt32;
+
+    #[test]
+    fn test_square_root() {
+        let mut x = Fr::one();
+        x.double();
+        let mut out = x;
+        out.square();
+
+        // Both `x` and `-x` are square roots of `out`
+        let mut cs = UnderconstrainedCS::<Bls12>::new();
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+        let x_squared = x.square(cs.namespace(|| "x^2")).unwrap();
+        let out = cs.alloc_input(|| "out", || Ok(out)).unwrap();
+
+        let one = UnderconstrainedCS::<Bls12>::one();
+        cs.enforce(
+            || "x^2 = out",
+            |lc| lc + x_squared.get_variable(),
+            |lc| lc + one,
+            |lc| lc + out,
+        );
+
+        assert_eq!(cs.underconstrained(), vec!["x/num"]);
+
+        cs.assume_determined("x");
+        assert!(cs.underconstrained().is_empty());
+    }
+
+    #[test]
+    fn test_gadgets() {
+        let mut cs = UnderconstrainedCS::<Bls12>::new();
+
+        let words: Vec<_> = (0..3)
+            .map(|i| UInt32::alloc(cs.namespace(|| format!("word {}", i)), Some(i * 1000)).unwrap())
+            .collect();
+        let sum = {
+            let mut cs = MultiEq::new(&mut cs);
+            UInt32::addmany(cs.namespace(|| "addition"), &words).unwrap()
+        };
+        sum.xor(cs.namespace(|| "xor"), &words[0]).unwrap();
+
+        // Nothing is determined until the words are
+        let underconstrained = cs.underconstrained();
+        assert_eq!(underconstrained.len(), 96 + 34 + 32);
+        assert!(underconstrained[..96]
+            .iter()
+            .all(|path| path.starts_with("word ")));
+
+        cs.assume_determined("word 0");
+        cs.assume_determined("word 1");
+        assert_eq!(cs.underconstrained().len(), 32 + 34 + 32);
+
+        cs.assume_determined("word 2");
+        assert!(cs.underconstrained().is_empty());
+    }
+
+    // An "is zero" gadget which forgets to enforce `out = 1 - x * inv`.
+    fn broken_is_zero(x: Fr) -> UnderconstrainedCS<Bls12> {
+        let mut cs = UnderconstrainedCS::<Bls12>::new();
+
+        let inv_value = x.inverse().unwrap_or_else(Fr::zero);
+        let out_value = if x.is_zero() { Fr::one() } else { Fr::zero() };
+
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+        AllocatedNum::alloc(cs.namespace(|| "inv"), || Ok(inv_value)).unwrap();
+        let out = AllocatedNum::alloc(cs.namespace(|| "out"), || Ok(out_value)).unwrap();
+        cs.enforce(
+            || "x * out = 0",
+            |lc| lc + x.get_variable(),
+            |lc| lc + out.get_variable(),
+            |lc| lc,
+        );
+
+        cs.assume_determined("x");
+        cs
+    }
+
+    #[test]
+    fn test_underconstrained() {
+        assert_eq!(
+            broken_is_zero(Fr::zero()).underconstrained(),
+            vec!["inv/num", "out/num"]
+        );
+
+        // A nonzero `x` determines `out`
+        assert_eq!(
+            broken_is_zero(Fr::one()).underconstrained(),
+            vec!["inv/num"]
+        );
+
+        // Without the witness, `x` could be zero
+        let mut cs = UnderconstrainedCS::<Bls12>::new();
+        let x = cs
+            .alloc(|| "x", || Err(SynthesisError::AssignmentMissing))
+            .unwrap();
+        let y = cs
+            .alloc(|| "y", || Err(SynthesisError::AssignmentMissing))
+            .unwrap();
+        cs.enforce(|| "x * y = x", |lc| lc + x, |lc| lc + y, |lc| lc + x);
+        cs.assume_determined("x");
+        assert_eq!(cs.underconstrained(), vec!["y"]);
+    }
+}
@@ -0,0 +1,251 @@
+//! Looks for soundness holes in a circuit by perturbing its witness.
+//!
+//! Each auxiliary variable is set in turn to a few other values, keeping
+//! the rest of the witness unchanged: if all the constraints are still
+//! satisfied, the variable isn't pinned down by its neighbours, and a
+//! malicious prover could choose it. This only catches the variables which
+//! can change on their own, such as a result which nothing ties to its
+//! operands.
+//!
+//! ```ignore
+//! #[test]
+//! fn test_my_gadget_is_sound() {
+//!     let mut rng = XorShiftRng::from_seed([0x5d; 16]);
+//!     assert_witness_sound(MyCircuit { x: Some(Fr::one()) }, &mut rng, 4);
+//! }
+//! ```
+
+use std::fmt;
+
+use ff::{Field, ScalarEngine};
+use rand_core::RngCore;
+
+use crate::gadgets::test::TestConstraintSystem;
+use crate::{Circuit, SynthesisError};
+
+/// A perturbation of one auxiliary variable which still satisfies all the
+/// constraints.
+pub struct UndetectedMutation<E: ScalarEngine> {
+    pub path: String,
+    pub original: E::Fr,
+    pub mutated: E::Fr,
+}
+
+impl<E: ScalarEngine> fmt::Display for UndetectedMutation<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` can be changed from {} to {}",
+            self.path, self.original, self.mutated
+        )
+    }
+}
+
+/// Synthesizes `circuit` into a [`TestConstraintSystem`], then perturbs
+/// each auxiliary variable by adding and subtracting one, negating it and
+/// setting it to `random_mutations` values drawn from `rng`. Returns the
+/// first perturbation of each variable which went unnoticed.
+///
+/// The circuit must be synthesized with a satisfying witness, otherwise
+/// `SynthesisError::Unsatisfiable` is returned. Each perturbation checks
+/// the whole constraint system, so that this is meant for gadgets rather
+/// than for full circuits.
+pub fn fuzz_witness<E, C, R>(
+    circuit: C,
+    rng: &mut R,
+    random_mutations: usize,
+) -> Result<Vec<UndetectedMutation<E>>, SynthesisError>
+where
+    E: ScalarEngine,
+    C: Circuit<E>,
+    R: RngCore,
+{
+    let mut cs = TestConstraintSystem::<E>::new();
+    circuit.synthesize(&mut cs)?;
+
+    if !cs.is_satisfied() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    let aux = cs.get_aux().to_vec();
+    let mut undetected = vec![];
+    for (original, path) in aux {
+        for mutated in mutations(original, rng, random_mutations) {
+            cs.set(&path, mutated);
+
+            if cs.is_satisfied() {
+                undetected.push(UndetectedMutation {
+                    path: path.clone(),
+                    original,
+                    mutated,
+                });
+                break;
+            }
+        }
+
+        cs.set(&path, original);
+    }
+
+    Ok(undetected)
+}
+
+/// Panics if [`fuzz_witness`] fails or finds a perturbation of the witness
+/// which satisfies the constraints, listing all of them.
+pub fn assert_witness_sound<E, C, R>(circuit: C, rng: &mut R, random_mutations: usize)
+where
+    E: ScalarEngine,
+    C: Circuit<E>,
+    R: RngCore,
+{
+    let undetected = fuzz_witness(circuit, rng, random_mutations)
+        .unwrap_or_else(|e| panic!("failed to fuzz the witness: {}", e));
+
+    if !undetected.is_empty() {
+        let list = undetected
+            .iter()
+            .map(|mutation| format!("  {}", mutation))
+            .collect::<Vec<_>>()
+            .join("\n");
+        panic!("undetected witness mutations:\n{}", list);
+    }
+}
+
+fn mutations<F: Field, R: RngCore>(value: F, rng: &mut R, random_mutations: usize) -> Vec<F> {
+    let mut plus_one = value;
+    plus_one.add_assign(&F::one());
+    let mut minus_one = value;
+    minus_one.sub_assign(&F::one());
+    let mut negated = value;
+    negated.negate();
+
+    let mut mutations = vec![plus_one, minus_one, negated];
+    mutations.extend((0..random_mutations).map(|_| F::random(rng)));
+    mutations.retain(|mutated| *mutated != value);
+    mutations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::ConstraintSystem;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ])
+    }
+
+    // Computes `a AND b` for public `a` and `b`, but forgets to constrain
+    // the result to the operands unless `sound`.
+    struct And {
+        a: bool,
+        b: bool,
+        sound: bool,
+    }
+
+    fn public_bit<CS: ConstraintSystem<Bls12>>(
+        mut cs: CS,
+        value: bool,
+    ) -> Result<AllocatedBit, SynthesisError> {
+        let bit = AllocatedBit::alloc(cs.namespace(|| "bit"), Some(value))?;
+        let input = cs.alloc_input(
+            || "input",
+            || Ok(if value { Fr::one() } else { Fr::zero() }),
+        )?;
+        cs.enforce(
+            || "bit = input",
+            |lc| lc + bit.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + input,
+        );
+
+        Ok(bit)
+    }
+
+    impl Circuit<Bls12> for And {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let a = public_bit(cs.namespace(|| "a"), self.a)?;
+            let b = public_bit(cs.namespace(|| "b"), self.b)?;
+
+            if self.sound {
+                AllocatedBit::and(cs.namespace(|| "a and b"), &a, &b)?;
+            } else {
+                AllocatedBit::alloc(cs.namespace(|| "a and b"), Some(self.a && self.b))?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_fuzz_witness() {
+        let mut rng = rng();
+
+        for &(a, b) in &[(false, false), (false, true), (true, false), (true, true)] {
+            assert_witness_sound(And { a, b, sound: true }, &mut rng, 4);
+
+            let undetected = fuzz_witness(And { a, b, sound: false }, &mut rng, 4).unwrap();
+            assert_eq!(undetected.len(), 1);
+            assert_eq!(undetected[0].path, "a and b/boolean");
+        }
+
+        let undetected = fuzz_witness(
+            And {
+                a: true,
+                b: true,
+                sound: false,
+            },
+            &mut rng,
+            4,
+        )
+        .unwrap();
+        assert!(undetected[0].original == Fr::one());
+        assert!(undetected[0].mutated == Fr::zero());
+        assert_eq!(
+            undetected[0].to_string(),
+            format!(
+                "`a and b/boolean` can be changed from {} to {}",
+                Fr::one(),
+                Fr::zero()
+            )
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "undetected witness mutations:\n  `a and b/boolean` can be changed")]
+    fn test_assert_witness_sound() {
+        let circuit = And {
+            a: false,
+            b: true,
+            sound: false,
+        };
+        assert_witness_sound(circuit, &mut rng(), 0);
+    }
+
+    #[test]
+    fn test_unsatisfied_witness() {
+        struct Unsatisfied;
+
+        impl Circuit<Bls12> for Unsatisfied {
+            fn synthesize<CS: ConstraintSystem<Bls12>>(
+                self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let x = cs.alloc(|| "x", || Ok(Fr::one()))?;
+                cs.enforce(|| "x = 0", |lc| lc + x, |lc| lc + CS::one(), |lc| lc);
+                Ok(())
+            }
+        }
+
+        let result = fuzz_witness(Unsatisfied, &mut rng(), 4);
+        assert!(matches!(result, Err(SynthesisError::Unsatisfiable)));
+    }
+}
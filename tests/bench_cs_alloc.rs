@@ -0,0 +1,58 @@
+//! Measures the allocations of `BenchCS::with_timing`, which needs the
+//! counting allocator of this test binary.
+
+use std::time::Duration;
+
+use bellperson::bls::{Bls12, Fr};
+use bellperson::util_cs::bench_cs::{BenchCS, CountingAllocator};
+use bellperson::ConstraintSystem;
+use ff::{Field, PrimeField};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn synthesize<CS: ConstraintSystem<Bls12>>(cs: &mut CS) {
+    for i in 0..3 {
+        let mut cs = cs.namespace(|| "slow");
+        cs.alloc(
+            || format!("var {}", i),
+            || {
+                let buf = vec![1u8; 1 << 20];
+                std::thread::sleep(Duration::from_millis(10));
+                Ok(Fr::from_str(&buf.len().to_string()).unwrap())
+            },
+        )
+        .unwrap();
+    }
+
+    let mut cs = cs.namespace(|| "fast");
+    let x = {
+        let mut cs = cs.namespace(|| "nested");
+        cs.alloc(|| "x", || Ok(Fr::one())).unwrap()
+    };
+    cs.enforce(|| "x = x", |lc| lc + x, |lc| lc + CS::one(), |lc| lc + x);
+}
+
+#[test]
+fn test_bench_cs() {
+    let mut cs = BenchCS::<Bls12>::new();
+    synthesize(&mut cs);
+    assert_eq!(cs.num_constraints(), 1);
+    assert_eq!(cs.timings(), None);
+
+    let mut cs = BenchCS::<Bls12>::with_timing();
+    synthesize(&mut cs);
+    assert_eq!(cs.num_constraints(), 1);
+
+    let timings = cs.timings().unwrap();
+    assert_eq!(timings.len(), 2);
+
+    let slow = timings["slow"];
+    assert_eq!(slow.entered, 3);
+    assert!(slow.duration >= Duration::from_millis(30));
+    assert!(slow.allocated_bytes >= 3 << 20);
+
+    let fast = timings["fast"];
+    assert_eq!(fast.entered, 1);
+    assert!(fast.duration < slow.duration);
+}